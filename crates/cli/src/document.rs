@@ -1,5 +1,6 @@
 use adaptarr_i18n::LanguageTag;
 use adaptarr_models::{
+    Book,
     CNXML_MIME,
     File,
     FindModelError,
@@ -8,7 +9,7 @@ use adaptarr_models::{
     Team,
     TeamResource,
     db::{self, models, schema::{documents, drafts, modules, module_versions}},
-    processing::TargetProcessor,
+    processing::{TargetProcessor, export, links::{self, ProblemKind}},
 };
 use adaptarr_util::bytes_to_hex;
 use diesel::prelude::*;
@@ -46,6 +47,12 @@ pub enum Command {
     /// Crate a new document
     #[structopt(name = "new")]
     New(NewOpts),
+    /// Export a document as a ZIP archive
+    #[structopt(name = "export")]
+    Export(ExportOpts),
+    /// Check links and media references in a document or a book
+    #[structopt(name = "check")]
+    Check(CheckOpts),
 }
 
 pub fn main(cfg: &Config, opts: Opts) -> Result<(), Error> {
@@ -64,6 +71,8 @@ pub fn main(cfg: &Config, opts: Opts) -> Result<(), Error> {
         Command::File(file_opts) => file(cfg, &opts, file_opts),
         Command::Cat(cat_opts) => cat(cfg, &opts, cat_opts),
         Command::New(new_opts) => new(cfg, &opts, new_opts),
+        Command::Export(export_opts) => export(cfg, &opts, export_opts),
+        Command::Check(check_opts) => check(cfg, &opts, check_opts),
     }
 }
 
@@ -160,12 +169,11 @@ fn file(cfg: &Config, opts: &Opts, file: &FileOpts) -> Result<()> {
     let db = db::connect(cfg.model.database.as_ref())?;
     let module = opts.document(&db)?;
     let file = module.get_file(&db, &file.name)?;
-    let metadata = std::fs::metadata(&file.path)?;
 
     println!("Type:    {}", file.mime);
     println!("Hash:    {}", bytes_to_hex(&file.hash));
     println!("Storage: {}", file.path);
-    println!("Size:    {}", metadata.len());
+    println!("Size:    {}", file.size()?);
 
     Ok(())
 }
@@ -215,8 +223,7 @@ fn new(cfg: &Config, opts: &Opts, new: &NewOpts) -> Result<()> {
 
     let document = db.transaction::<_, failure::Error, _>(|| {
         let index = std::fs::File::open(&new.index)?;
-        let index = File::from_read(
-            &db, &cfg.model.storage.path, index, Some(CNXML_MIME))?;
+        let index = File::from_read(&db, index, Some(CNXML_MIME))?;
         let module = Module::create::<&str, _>(
             &db,
             &team,
@@ -247,6 +254,86 @@ fn new(cfg: &Config, opts: &Opts, new: &NewOpts) -> Result<()> {
     })
 }
 
+#[derive(StructOpt)]
+pub struct ExportOpts {
+    /// File to write the archive to, defaults to <UUID>.zip
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+fn export(cfg: &Config, opts: &Opts, export: &ExportOpts) -> Result<()> {
+    let db = db::connect(cfg.model.database.as_ref())?;
+    let module = opts.document(&db)?;
+    let path = export.output.clone()
+        .unwrap_or_else(|| PathBuf::from(format!("{}.zip", module.id())));
+    let file = std::fs::File::create(&path)?;
+
+    export::export_module(&db, &module, file)?;
+
+    println!("Exported {} to {}", module.id(), path.display());
+
+    Ok(())
+}
+
+#[derive(StructOpt)]
+pub struct CheckOpts {
+    /// Check all modules in this book instead of a single document
+    #[structopt(short = "b", long = "book")]
+    book: Option<Uuid>,
+}
+
+fn check(cfg: &Config, opts: &Opts, check: &CheckOpts) -> Result<()> {
+    let db = db::connect(cfg.model.database.as_ref())?;
+
+    let problems = match check.book {
+        Some(book) => links::check_book(&db, &Book::by_id(&db, book)?)?,
+        None => {
+            let module = opts.document(&db)?;
+            links::check_document(&db, module.id(), &module)?
+        }
+    };
+
+    if problems.is_empty() {
+        println!("No broken links found");
+        return Ok(());
+    }
+
+    let rows = problems.iter()
+        .map(|problem| {
+            let description = match problem.kind {
+                ProblemKind::MissingTarget { ref target } =>
+                    format!("missing target {}", target),
+                ProblemKind::MissingModule { document } =>
+                    format!("missing module {}", document),
+                ProblemKind::ExternalModule { ref document } =>
+                    format!("unverifiable link to module {}", document),
+                ProblemKind::MissingModuleTarget { document, ref target } =>
+                    format!("missing target {} in module {}", target, document),
+                ProblemKind::ModuleNotInBook { document } =>
+                    format!("module {} is not in the book", document),
+                ProblemKind::MissingFile { ref name } =>
+                    format!("missing file {}", name),
+            };
+
+            (
+                problem.module.to_string(),
+                problem.element.clone().unwrap_or_default(),
+                description,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    print_table(("Module", "Element", "Problem"), &rows);
+
+    let broken = problems.iter().filter(|problem| problem.is_broken()).count();
+
+    if broken == 0 {
+        return Ok(());
+    }
+
+    Err(format_err!("Found {} broken links", broken))
+}
+
 impl Opts {
     fn document_id(&self) -> Result<Uuid> {
         match self.document {
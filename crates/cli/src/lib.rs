@@ -8,7 +8,9 @@ use structopt::StructOpt;
 
 mod config;
 mod document;
+mod process;
 mod server;
+mod storage;
 mod team;
 mod user;
 mod util;
@@ -41,6 +43,12 @@ enum Command {
     /// Manage teams
     #[structopt(name = "team")]
     Team(team::Opts),
+    /// Manage editing processes
+    #[structopt(name = "process")]
+    Process(process::Opts),
+    /// Manage file storage
+    #[structopt(name = "storage")]
+    Storage(storage::Opts),
 }
 
 pub fn main() -> Result<(), Error> {
@@ -62,6 +70,8 @@ pub fn main() -> Result<(), Error> {
         Command::Document(opts) => with_system(document::main, &config, opts),
         Command::User(opts) => with_system(user::main, &config, opts),
         Command::Team(opts) => with_system(team::main, &config, opts),
+        Command::Process(opts) => with_system(process::main, &config, opts),
+        Command::Storage(opts) => with_system(storage::main, &config, opts),
     }
 }
 
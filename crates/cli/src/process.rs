@@ -0,0 +1,80 @@
+use adaptarr_models::{
+    Model,
+    Team,
+    db,
+    editing::{Process, structure::{self, Validation}},
+};
+use failure::format_err;
+use std::{fs::File, path::PathBuf};
+use structopt::StructOpt;
+
+use crate::{Config, Result};
+
+#[derive(StructOpt)]
+pub struct Opts {
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Add a new editing process
+    #[structopt(name = "add")]
+    Add(AddOpts),
+}
+
+pub fn main(cfg: &Config, opts: Opts) -> Result<()> {
+    match opts.command {
+        Command::Add(ref add) => add_process(cfg, add),
+    }
+}
+
+#[derive(StructOpt)]
+pub struct AddOpts {
+    /// JSON file describing the process, in the same format as accepted by
+    /// POST /api/v1/processes
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+    /// ID of team in which to create the process
+    #[structopt(short = "t", long = "team")]
+    team: Option<i32>,
+    /// Only validate the process and report warnings, don't create it
+    #[structopt(long = "check")]
+    check: bool,
+}
+
+fn add_process(cfg: &Config, opts: &AddOpts) -> Result<()> {
+    let structure: structure::Process = serde_json::from_reader(
+        File::open(&opts.file)?)?;
+
+    if opts.check {
+        let validation = structure::validate(&structure)?;
+        print_warnings(&validation);
+
+        if validation.warnings.is_empty() {
+            println!("Process is valid");
+        }
+
+        return Ok(());
+    }
+
+    let team = match opts.team {
+        Some(team) => team,
+        None => return Err(format_err!("This command requires a team")),
+    };
+
+    let db = db::connect(cfg.model.database.as_ref())?;
+    let team = Team::by_id(&db, team)?;
+    let (version, validation) = Process::create(&db, &team, &structure)?;
+
+    print_warnings(&validation);
+    println!("Created process {}", version.process().id());
+
+    Ok(())
+}
+
+fn print_warnings(validation: &Validation) {
+    for warning in &validation.warnings {
+        println!("Warning: {}", warning);
+    }
+}
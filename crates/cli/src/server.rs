@@ -2,7 +2,10 @@
 
 use actix::{Actor, System};
 use actix_web::{App, HttpServer, middleware::{Compress, Logger}};
-use adaptarr_models::processing::{Importer, TargetProcessor};
+use adaptarr_models::{
+    events::DeadlineMonitor,
+    processing::{Importer, TargetProcessor},
+};
 use adaptarr_web::{Secret, SessionManager};
 use failure::Error;
 use structopt::StructOpt;
@@ -33,7 +36,7 @@ pub fn start(config: Config) -> Result<(), Error> {
 
     let pool = adaptarr_models::db::configure_pool(config.model.database.as_ref())?;
     let i18n = adaptarr_i18n::load()?;
-    let importer = Importer::start(pool.clone(), config.model.storage.path.clone());
+    let importer = Importer::start(pool.clone());
 
     let address = config.server.address;
     let domain = config.server.domain.clone();
@@ -56,6 +59,8 @@ pub fn start(config: Config) -> Result<(), Error> {
     // immediately.
     TargetProcessor::start_default();
 
+    DeadlineMonitor::start_default();
+
     let server = if let Some(fd) = listenfd::ListenFd::from_env().take_tcp_listener(0)? {
         server.listen(fd)?
     } else {
@@ -0,0 +1,119 @@
+use adaptarr_models::{File, Integrity, Model, db};
+use failure::{Error, format_err};
+use structopt::StructOpt;
+
+use crate::{Config, Result};
+use super::util::print_table;
+
+/// Manage file storage
+#[derive(StructOpt)]
+pub struct Opts {
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Find files which are not used by any document or resource, and which
+    /// were not uploaded in the last 24 hours
+    #[structopt(name = "gc")]
+    Gc(GcOpts),
+    /// Verify that contents of all files match their hashes
+    #[structopt(name = "fsck")]
+    Fsck,
+    /// Read dimensions of images created before they were recorded
+    #[structopt(name = "dimensions")]
+    Dimensions,
+}
+
+pub fn main(cfg: &Config, opts: Opts) -> Result<(), Error> {
+    match opts.command {
+        Command::Gc(ref gc_opts) => gc(cfg, gc_opts),
+        Command::Fsck => fsck(cfg),
+        Command::Dimensions => dimensions(cfg),
+    }
+}
+
+#[derive(StructOpt)]
+pub struct GcOpts {
+    /// Delete unused files instead of only listing them
+    #[structopt(short = "d", long = "delete")]
+    delete: bool,
+}
+
+fn gc(cfg: &Config, opts: &GcOpts) -> Result<()> {
+    let db = db::connect(cfg.model.database.as_ref())?;
+
+    let files = if opts.delete {
+        File::delete_orphans(&db)?
+    } else {
+        File::orphans(&db)?
+    };
+
+    let rows = files.iter()
+        .map(|file| (
+            file.id().to_string(),
+            file.mime.as_str(),
+            file.path.as_str(),
+        ))
+        .collect::<Vec<_>>();
+
+    print_table(("ID", "Type", "Storage"), &rows);
+
+    if opts.delete {
+        println!("\nDeleted {} files", files.len());
+    } else {
+        let size = files.iter()
+            .filter_map(|file| file.size().ok())
+            .sum::<u64>();
+
+        println!("\n{} unused files, {} bytes", files.len(), size);
+    }
+
+    Ok(())
+}
+
+fn fsck(cfg: &Config) -> Result<()> {
+    let db = db::connect(cfg.model.database.as_ref())?;
+    let files = File::all(&db)?;
+    let mut rows = Vec::new();
+
+    for file in &files {
+        let status = match file.verify() {
+            Ok(Integrity::Ok) => continue,
+            Ok(Integrity::Missing) => "missing".to_string(),
+            Ok(Integrity::Corrupted) => "corrupted".to_string(),
+            Err(err) => err.to_string(),
+        };
+
+        rows.push((file.id().to_string(), file.path.as_str(), status));
+    }
+
+    if rows.is_empty() {
+        println!("Verified {} files, no problems found", files.len());
+        return Ok(());
+    }
+
+    print_table(("ID", "Storage", "Problem"), &rows);
+
+    Err(format_err!("{} of {} files are damaged", rows.len(), files.len()))
+}
+
+fn dimensions(cfg: &Config) -> Result<()> {
+    let db = db::connect(cfg.model.database.as_ref())?;
+    let mut count = 0;
+
+    for mut file in File::all(&db)? {
+        if file.width.is_some() {
+            continue;
+        }
+
+        if file.read_dimensions(&db)?.is_some() {
+            count += 1;
+        }
+    }
+
+    println!("Read dimensions of {} images", count);
+
+    Ok(())
+}
@@ -1,4 +1,9 @@
-use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use actix_web::{
+    HttpResponse,
+    ResponseError,
+    error::BlockingError,
+    http::StatusCode,
+};
 use adaptarr_macros::From;
 use failure::Fail;
 use log::error;
@@ -7,6 +12,9 @@ use std::borrow::Cow;
 
 pub use adaptarr_macros::ApiError;
 
+/// Additional, structured information about an error.
+pub type Data = serde_json::Value;
+
 /// An error that occurred while handling an API request.
 pub trait ApiError: Fail {
     /// HTTP response status code.
@@ -18,6 +26,29 @@ pub trait ApiError: Fail {
     /// should only be present for errors which are intended to be reported
     /// to the user in detail.
     fn code(&self) -> Option<Cow<str>>;
+
+    /// Additional information about this error, reported to the user together
+    /// with its code.
+    ///
+    /// When deriving this trait, the only field of a variant annotated with
+    /// `#[api(data)]` is used as its data.
+    fn data(&self) -> Option<Data> {
+        None
+    }
+}
+
+/// Convert a value into error [`Data`].
+///
+/// This function is used by `#[derive(ApiError)]`.
+#[doc(hidden)]
+pub fn to_data<T: Serialize>(value: &T) -> Option<Data> {
+    match serde_json::to_value(value) {
+        Ok(data) => Some(data),
+        Err(err) => {
+            error!("Could not serialize error data: {}", err);
+            None
+        }
+    }
 }
 
 /// This implementation is required to make `#[cause]` on a `Box<dyn ApiError>`
@@ -97,6 +128,16 @@ impl From<tera::Error> for Error {
     }
 }
 
+impl From<BlockingError<Error>> for Error {
+    fn from(e: BlockingError<Error>) -> Self {
+        match e {
+            BlockingError::Error(e) => e,
+            BlockingError::Canceled => Error::System(std::io::Error::new(
+                std::io::ErrorKind::Other, "thread pool is gone")),
+        }
+    }
+}
+
 impl ResponseError for Error {
     fn error_response(&self) -> HttpResponse {
         match self {
@@ -105,6 +146,7 @@ impl ResponseError for Error {
                     .json(ErrorResponse {
                         error: code,
                         raw: err.to_string(),
+                        data: err.data(),
                     }),
                 None => {
                     error!("{}", err);
@@ -129,4 +171,6 @@ impl ResponseError for Error {
 struct ErrorResponse<'s> {
     error: Cow<'s, str>,
     raw: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Data>,
 }
@@ -28,8 +28,14 @@ pub fn derive_error(s: Structure) -> TokenStream {
         Err(e) => e.into_tokens(),
     });
 
+    let data = s.each_variant(|v| match find_data(v) {
+        Ok(v) => v,
+        Err(e) => e.into_tokens(),
+    });
+
     s.gen_impl(quote! {
         extern crate actix_web;
+        extern crate adaptarr_error;
         use std::borrow::Cow;
 
         gen impl ApiError for @Self {
@@ -40,6 +46,10 @@ pub fn derive_error(s: Structure) -> TokenStream {
             fn code(&self) -> Option<Cow<str>> {
                 match *self { #codes }
             }
+
+            fn data(&self) -> Option<adaptarr_error::Data> {
+                match *self { #data }
+            }
         }
     })
 }
@@ -102,6 +112,7 @@ fn find_status(v: &VariantInfo) -> Result<TokenStream, Error> {
         match item {
             NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("internal") =>
                 internal = Some(item),
+            NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("data") => (),
             NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("code") => (),
             NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("status") =>
                 status = Some(match nv.lit {
@@ -113,7 +124,7 @@ fn find_status(v: &VariantInfo) -> Result<TokenStream, Error> {
                 }),
             _ => return Err(Error::new(
                 item.span(),
-                "expected one of: internal, code, status",
+                "expected one of: internal, code, status, data",
             )),
         }
     }
@@ -150,12 +161,13 @@ fn find_code(v: &VariantInfo) -> Result<TokenStream, Error> {
         match item {
             NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("internal") =>
                 internal = Some(item),
+            NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("data") => (),
             NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("code") =>
                 code = Some(nv.lit.clone()),
             NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("status") => (),
             _ => return Err(Error::new(
                 item.span(),
-                "expected one of: internal, code, status",
+                "expected one of: internal, code, status, data",
             )),
         }
     }
@@ -171,6 +183,41 @@ fn find_code(v: &VariantInfo) -> Result<TokenStream, Error> {
     }
 }
 
+/// Find value of [`ApiError::data()`] for a variant.
+///
+/// Variants annotated with `#[api(data)]` use their only field as data.
+fn find_data(v: &VariantInfo) -> Result<TokenStream, Error> {
+    let meta = match find_ast(v.ast().attrs)? {
+        Some(meta) => meta,
+        None => return v.bindings()
+            .iter()
+            .find(is_cause)
+            .map(|cause| quote!(#cause.data()))
+            .ok_or_else(|| Error::new(
+                v.ast().ident.span(),
+                "each variant must be #[api]-annotated or have a #[cause]",
+            )),
+    };
+
+    let data = meta.nested.iter().find(|item| match item {
+        NestedMeta::Meta(Meta::Path(ref path)) => path.is_ident("data"),
+        _ => false,
+    });
+
+    let data = match data {
+        Some(data) => data,
+        None => return Ok(quote!(None)),
+    };
+
+    match v.bindings() {
+        [field] => Ok(quote!(adaptarr_error::to_data(#field))),
+        _ => Err(Error::new(
+            data.span(),
+            "#[api(data)] requires a variant with exactly one field",
+        )),
+    }
+}
+
 fn is_cause(bi: &&BindingInfo) -> bool {
     bi.ast()
         .attrs
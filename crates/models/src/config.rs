@@ -19,7 +19,26 @@ pub struct Config {
 #[derive(Clone, Debug, Deserialize)]
 pub struct Storage {
     /// Path to a directory in which user-uploaded files will be kept.
+    ///
+    /// When files are kept in an object storage, this directory is only used
+    /// for temporary files.
     pub path: std::path::PathBuf,
+    /// S3-compatible object storage in which to keep files instead of `path`.
+    pub s3: Option<S3>,
+}
+
+/// S3-compatible object storage configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct S3 {
+    /// Name of the bucket in which to keep files.
+    pub bucket: String,
+    /// Region in which the bucket is located.
+    pub region: String,
+    /// Custom endpoint, for services other than Amazon S3.
+    pub endpoint: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
 }
 
 impl Config {
@@ -57,6 +76,7 @@ impl Config {
     /// ([`Config::global`]).
     pub fn register(&'static self, domain: &str, secret: &[u8]) {
         CONFIG.get_or_init(|| self);
+        crate::storage::register(&self.storage);
         DOMAIN.get_or_init(|| domain.to_string());
         SECRET.get_or_init(|| secret.to_vec().into_boxed_slice());
     }
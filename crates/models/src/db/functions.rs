@@ -8,6 +8,43 @@ use diesel::{
 
 sql_function!(fn duplicate_document(id: Int4) -> Int4);
 
+/// PostgreSQL's `tsvector` type, a document preprocessed for text search.
+#[derive(Clone, Copy, Debug, QueryId, SqlType)]
+#[postgres(type_name = "tsvector")]
+pub struct TsVector;
+
+/// PostgreSQL's `tsquery` type, a preprocessed text search query.
+#[derive(Clone, Copy, Debug, QueryId, SqlType)]
+#[postgres(type_name = "tsquery")]
+pub struct TsQuery;
+
+sql_function! {
+    /// Convert text into a search vector.
+    fn search_vector(content: Text) -> TsVector;
+}
+
+sql_function! {
+    /// Convert user's query into a search query.
+    fn search_query(query: Text) -> TsQuery;
+}
+
+sql_function! {
+    /// Rank how well does a search vector match a search query.
+    fn ts_rank(vector: TsVector, query: TsQuery) -> Float4;
+}
+
+diesel_infix_operator!(Matches, " @@ ", backend: diesel::pg::Pg);
+
+/// Create a SQL `@@` expression, testing whether a search vector matches
+/// a search query.
+pub fn matches<V, Q>(vector: V, query: Q) -> Matches<V, Q>
+where
+    V: Expression<SqlType = TsVector>,
+    Q: Expression<SqlType = TsQuery>,
+{
+    Matches::new(vector, query)
+}
+
 /// Create a SQL `COUNT(DISTINCT)` expression.
 pub fn count_distinct<T, Expr>(expr: Expr)
 -> CountDistinct<<Expr as AsExpression<T>>::Expression>
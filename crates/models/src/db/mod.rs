@@ -61,6 +61,45 @@ pub fn connect(cfg: Option<&Config>) -> Result<Connection, ConnectionError> {
     Ok(conn)
 }
 
+/// Create a connection for use in tests.
+///
+/// Tests requiring a database should be marked `#[ignore]`, and are run with
+/// `cargo test -- --ignored` when `TEST_DATABASE_URL` names a migrated
+/// database. Nothing done through the returned connection is ever committed.
+///
+/// This also registers a configuration keeping files in a temporary
+/// directory, which is shared by all tests and never cleaned up.
+#[cfg(test)]
+pub(crate) fn test_connection() -> Connection {
+    use diesel::Connection;
+
+    lazy_static::lazy_static! {
+        static ref CONFIG: crate::Config = {
+            let path = env::temp_dir().join("adaptarr-test-storage");
+            std::fs::create_dir_all(&path)
+                .expect("cannot create test storage directory");
+
+            crate::Config {
+                database: None,
+                storage: crate::config::Storage { path, s3: None },
+            }
+        };
+    }
+
+    CONFIG.register("adaptarr.test", b"test secret");
+
+    let url = env::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL must be set to run database tests");
+    let conn = PgConnection::establish(&url)
+        .expect("cannot connect to the test database");
+
+    conn.begin_test_transaction()
+        .expect("cannot begin a test transaction");
+    crate::audit::set_actor(crate::audit::Actor::System);
+
+    conn
+}
+
 static POOL: SingleInit<Pool> = SingleInit::uninit();
 
 /// Create a connection pool for the database.
@@ -165,6 +165,12 @@ pub struct File {
     pub path: String,
     /// Has of this file's contents.
     pub hash: Vec<u8>,
+    /// Width of the image in this file, if it is an image.
+    pub width: Option<i32>,
+    /// Height of the image in this file, if it is an image.
+    pub height: Option<i32>,
+    /// Time at which contents of this file were last uploaded.
+    pub uploaded: DateTime<Utc>,
 }
 
 #[derive(Clone, Debug, Insertable)]
@@ -173,6 +179,19 @@ pub struct NewFile<'a> {
     pub mime: &'a str,
     pub path: &'a str,
     pub hash: &'a [u8],
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, Insertable)]
+#[table_name = "file_derivatives"]
+pub struct NewFileDerivative<'a> {
+    /// Hash of the file this is a derivative of.
+    pub source: &'a [u8],
+    /// Width to which the source image was scaled.
+    pub width: i32,
+    /// File containing the scaled image.
+    pub file: i32,
 }
 
 #[derive(Clone, Debug, Identifiable, Queryable)]
@@ -218,6 +237,15 @@ pub struct NewDocumentFile<'a> {
     pub file: i32,
 }
 
+#[derive(Clone, Copy, Debug, Insertable)]
+#[table_name = "document_search"]
+pub struct NewDocumentSearch<'a> {
+    /// ID of the indexed document.
+    pub document: i32,
+    /// Plain text content of the document.
+    pub content: &'a str,
+}
+
 #[derive(Clone, Copy, Debug, Identifiable, Insertable, Queryable)]
 pub struct Module {
     /// ID of this module.
@@ -305,6 +333,70 @@ pub struct Draft {
     pub step: i32,
     /// Team owning this draft.
     pub team: i32,
+    /// Date and time by which this draft should leave its current step.
+    pub deadline: Option<DateTime<Utc>>,
+    /// Date and time when users were last notified about the deadline
+    /// approaching or having passed.
+    pub deadline_notified: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy, Debug, Identifiable, Queryable)]
+pub struct DraftChange {
+    /// ID of this change set.
+    pub id: i32,
+    /// Draft to which changes were proposed.
+    pub draft: Uuid,
+    /// User who proposed the changes.
+    pub user: Option<i32>,
+    /// Version of `index.cnxml` against which changes were proposed.
+    pub base: i32,
+    /// Version of `index.cnxml` with changes applied.
+    pub file: i32,
+    /// Whether the changes were accepted or rejected.
+    pub state: super::types::ChangeState,
+    /// Date and time when the changes were proposed.
+    pub created: DateTime<Utc>,
+    /// Date and time when the changes were accepted or rejected.
+    pub resolved: Option<DateTime<Utc>>,
+    /// User who accepted or rejected the changes.
+    pub resolved_by: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, Insertable)]
+#[table_name = "draft_changes"]
+pub struct NewDraftChange {
+    pub draft: Uuid,
+    pub user: Option<i32>,
+    pub base: i32,
+    pub file: i32,
+}
+
+#[derive(Clone, Copy, Debug, Identifiable, Queryable)]
+pub struct DraftRevision {
+    /// ID of this revision.
+    pub id: i32,
+    /// Draft of which this is a revision.
+    pub draft: Uuid,
+    /// Contents of the draft at this revision.
+    pub document: i32,
+    /// Why was this revision created.
+    pub kind: super::types::RevisionKind,
+    /// Editing step the draft was in when this revision was created.
+    pub step: i32,
+    /// User who created this revision.
+    pub user: Option<i32>,
+    /// Date and time when this revision was created.
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, Insertable)]
+#[table_name = "draft_revisions"]
+pub struct NewDraftRevision {
+    pub draft: Uuid,
+    pub document: i32,
+    pub kind: super::types::RevisionKind,
+    pub step: i32,
+    pub user: Option<i32>,
 }
 
 /// Describes users assigned to particular slots in a draft.
@@ -494,6 +586,8 @@ pub struct EditProcessStep {
     /// This name is used to identify a step when editing a process, and when
     /// displaying a module's status.
     pub name: String,
+    /// Number of days a draft should spend in this step, if limited.
+    pub duration: Option<i32>,
 }
 
 #[derive(AsChangeset, Clone, Copy, Debug, Insertable)]
@@ -501,6 +595,7 @@ pub struct EditProcessStep {
 pub struct NewEditProcessStep<'a> {
     pub process: i32,
     pub name: &'a str,
+    pub duration: Option<i32>,
 }
 
 /// List of slots assigned to a document at a given editing step.
@@ -531,6 +626,8 @@ pub struct EditProcessLink {
     pub name: String,
     /// ID of slot allowed to change modules step.
     pub slot: i32,
+    /// Conditions a draft must satisfy before it can be moved along this link.
+    pub guards: Vec<super::types::LinkGuard>,
 }
 
 #[derive(AsChangeset, Clone, Copy, Debug, Insertable)]
@@ -540,6 +637,7 @@ pub struct NewEditProcessLink<'a> {
     pub to: i32,
     pub name: &'a str,
     pub slot: i32,
+    pub guards: &'a [super::types::LinkGuard],
 }
 
 #[derive(Clone, Debug, Queryable)]
@@ -642,3 +740,109 @@ pub struct NewConversationEvent<'a> {
     pub author: Option<i32>,
     pub data: &'a [u8],
 }
+
+#[derive(Clone, Debug, Identifiable, Queryable)]
+pub struct Comment {
+    /// ID of this comment.
+    pub id: i32,
+    /// Module on which this comment was made, if it was made on a module.
+    pub module: Option<Uuid>,
+    /// Draft on which this comment was made, if it was made on a draft.
+    pub draft: Option<Uuid>,
+    /// Comment to which this is a reply, if any.
+    pub parent: Option<i32>,
+    /// ID of the CNXML element this comment is anchored to, if any.
+    pub element: Option<String>,
+    /// Author's ID.
+    pub author: Option<i32>,
+    /// Comment's body, in the same format as conversation messages.
+    pub body: Vec<u8>,
+    /// Date and time when this comment was made.
+    pub created: DateTime<Utc>,
+    /// Date and time when this comment was marked as resolved.
+    pub resolved: Option<DateTime<Utc>>,
+    /// User who marked this comment as resolved.
+    pub resolved_by: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, Insertable)]
+#[table_name = "comments"]
+pub struct NewComment<'a> {
+    pub module: Option<Uuid>,
+    pub draft: Option<Uuid>,
+    pub parent: Option<i32>,
+    pub element: Option<&'a str>,
+    pub author: Option<i32>,
+    pub body: &'a [u8],
+}
+
+#[derive(Clone, Debug, Identifiable, Queryable)]
+pub struct ImportJob {
+    /// Job's ID.
+    pub id: i32,
+    /// What is being imported.
+    pub kind: super::types::ImportKind,
+    /// Current state of this job.
+    pub state: super::types::ImportState,
+    /// Team into which contents are imported.
+    pub team: i32,
+    /// User who requested this import, or `None` for imports started by
+    /// the system or CLI.
+    pub user: Option<i32>,
+    /// Title of the module or book being imported.
+    pub title: String,
+    /// Module into which contents are imported.
+    ///
+    /// For jobs creating a new module this is only set once the job is done.
+    pub module: Option<Uuid>,
+    /// Book into which contents are imported.
+    ///
+    /// For jobs creating a new book this is only set once the job is done.
+    pub book: Option<Uuid>,
+    /// Code of the error which caused this job to fail.
+    pub error: Option<String>,
+    /// Description of the error which caused this job to fail, if it can be
+    /// shown to users.
+    pub message: Option<String>,
+    /// Date and time when this job was created.
+    pub created: DateTime<Utc>,
+    /// Date and time when this job was finished.
+    pub finished: Option<DateTime<Utc>>,
+    /// ID of the server instance which will process this job.
+    pub owner: Uuid,
+    /// Date and time when the owner last reported it's still alive.
+    pub heartbeat: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, Insertable)]
+#[table_name = "import_jobs"]
+pub struct NewImportJob<'a> {
+    pub kind: super::types::ImportKind,
+    pub team: i32,
+    pub user: Option<i32>,
+    pub title: &'a str,
+    pub module: Option<Uuid>,
+    pub book: Option<Uuid>,
+    pub owner: Uuid,
+}
+
+#[derive(Clone, Debug, Identifiable, Queryable)]
+#[primary_key(job, index)]
+pub struct ImportJobModule {
+    /// Job's ID.
+    pub job: i32,
+    /// Position of this module in the order of importing.
+    pub index: i32,
+    /// Module's title.
+    pub title: String,
+    /// Has this module been imported?
+    pub done: bool,
+}
+
+#[derive(Clone, Copy, Debug, Insertable)]
+#[table_name = "import_job_modules"]
+pub struct NewImportJobModule<'a> {
+    pub job: i32,
+    pub index: i32,
+    pub title: &'a str,
+}
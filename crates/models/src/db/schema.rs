@@ -30,6 +30,21 @@ table! {
     }
 }
 
+table! {
+    comments (id) {
+        id -> Int4,
+        module -> Nullable<Uuid>,
+        draft -> Nullable<Uuid>,
+        parent -> Nullable<Int4>,
+        element -> Nullable<Varchar>,
+        author -> Nullable<Int4>,
+        body -> Bytea,
+        created -> Timestamptz,
+        resolved -> Nullable<Timestamptz>,
+        resolved_by -> Nullable<Int4>,
+    }
+}
+
 table! {
     conversation_events (id) {
         id -> Int4,
@@ -63,6 +78,13 @@ table! {
     }
 }
 
+table! {
+    document_search (document) {
+        document -> Int4,
+        content -> Text,
+    }
+}
+
 table! {
     documents (id) {
         id -> Int4,
@@ -73,12 +95,40 @@ table! {
     }
 }
 
+table! {
+    draft_changes (id) {
+        id -> Int4,
+        draft -> Uuid,
+        user -> Nullable<Int4>,
+        base -> Int4,
+        file -> Int4,
+        state -> crate::db::types::Change_state,
+        created -> Timestamptz,
+        resolved -> Nullable<Timestamptz>,
+        resolved_by -> Nullable<Int4>,
+    }
+}
+
+table! {
+    draft_revisions (id) {
+        id -> Int4,
+        draft -> Uuid,
+        document -> Int4,
+        kind -> crate::db::types::Revision_kind,
+        step -> Int4,
+        user -> Nullable<Int4>,
+        created -> Timestamptz,
+    }
+}
+
 table! {
     drafts (module) {
         module -> Uuid,
         document -> Int4,
         step -> Int4,
         team -> Int4,
+        deadline -> Nullable<Timestamptz>,
+        deadline_notified -> Nullable<Timestamptz>,
     }
 }
 
@@ -104,6 +154,7 @@ table! {
         to -> Int4,
         name -> Varchar,
         slot -> Int4,
+        guards -> Array<crate::db::types::Link_guard>,
     }
 }
 
@@ -128,6 +179,7 @@ table! {
         id -> Int4,
         process -> Int4,
         name -> Varchar,
+        duration -> Nullable<Int4>,
     }
 }
 
@@ -159,12 +211,51 @@ table! {
     }
 }
 
+table! {
+    file_derivatives (source, width) {
+        source -> Bytea,
+        width -> Int4,
+        file -> Int4,
+    }
+}
+
 table! {
     files (id) {
         id -> Int4,
         mime -> Varchar,
         path -> Varchar,
         hash -> Bytea,
+        width -> Nullable<Int4>,
+        height -> Nullable<Int4>,
+        uploaded -> Timestamptz,
+    }
+}
+
+table! {
+    import_jobs (id) {
+        id -> Int4,
+        kind -> crate::db::types::Import_kind,
+        state -> crate::db::types::Import_state,
+        team -> Int4,
+        user -> Nullable<Int4>,
+        title -> Varchar,
+        module -> Nullable<Uuid>,
+        book -> Nullable<Uuid>,
+        error -> Nullable<Varchar>,
+        message -> Nullable<Varchar>,
+        created -> Timestamptz,
+        finished -> Nullable<Timestamptz>,
+        owner -> Uuid,
+        heartbeat -> Timestamptz,
+    }
+}
+
+table! {
+    import_job_modules (job, index) {
+        job -> Int4,
+        index -> Int4,
+        title -> Varchar,
+        done -> Bool,
     }
 }
 
@@ -277,13 +368,21 @@ joinable!(audit_log -> users (actor));
 joinable!(book_parts -> books (book));
 joinable!(book_parts -> modules (module));
 joinable!(books -> teams (team));
+joinable!(comments -> drafts (draft));
+joinable!(comments -> modules (module));
 joinable!(conversation_events -> conversations (conversation));
 joinable!(conversation_events -> users (author));
 joinable!(conversation_members -> conversations (conversation));
 joinable!(conversation_members -> users (user));
 joinable!(document_files -> documents (document));
 joinable!(document_files -> files (file));
+joinable!(document_search -> documents (document));
 joinable!(documents -> files (index));
+joinable!(draft_changes -> drafts (draft));
+joinable!(draft_revisions -> documents (document));
+joinable!(draft_revisions -> drafts (draft));
+joinable!(draft_revisions -> edit_process_steps (step));
+joinable!(draft_revisions -> users (user));
 joinable!(draft_slots -> drafts (draft));
 joinable!(draft_slots -> edit_process_slots (slot));
 joinable!(draft_slots -> users (user));
@@ -300,6 +399,12 @@ joinable!(edit_process_step_slots -> edit_process_steps (step));
 joinable!(edit_process_versions -> edit_processes (process));
 joinable!(edit_processes -> teams (team));
 joinable!(events -> users (user));
+joinable!(file_derivatives -> files (file));
+joinable!(import_job_modules -> import_jobs (job));
+joinable!(import_jobs -> books (book));
+joinable!(import_jobs -> modules (module));
+joinable!(import_jobs -> teams (team));
+joinable!(import_jobs -> users (user));
 joinable!(invites -> roles (role));
 joinable!(invites -> teams (team));
 joinable!(invites -> users (user));
@@ -321,11 +426,15 @@ allow_tables_to_appear_in_same_query!(
     audit_log,
     book_parts,
     books,
+    comments,
     conversation_events,
     conversation_members,
     conversations,
     document_files,
+    document_search,
     documents,
+    draft_changes,
+    draft_revisions,
     drafts,
     draft_slots,
     edit_processes,
@@ -336,7 +445,10 @@ allow_tables_to_appear_in_same_query!(
     edit_process_step_slots,
     edit_process_versions,
     events,
+    file_derivatives,
     files,
+    import_jobs,
+    import_job_modules,
     invites,
     modules,
     module_versions,
@@ -51,3 +51,81 @@ impl fmt::Display for SlotPermission {
         })
     }
 }
+
+/// Kind of an import job.
+#[derive(Clone, Copy, DbEnum, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[DieselType = "Import_kind"]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportKind {
+    /// Job imports a single module.
+    Module,
+    /// Job imports a book and all modules in it.
+    Book,
+}
+
+/// State of an import job.
+#[derive(Clone, Copy, DbEnum, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[DieselType = "Import_state"]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportState {
+    /// Job is waiting to be processed.
+    Queued,
+    /// Job is being processed.
+    Running,
+    /// Job has finished successfully.
+    Done,
+    /// Job has failed.
+    Failed,
+}
+
+/// Reason for which a draft revision was created.
+#[derive(Clone, Copy, DbEnum, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[DieselType = "Revision_kind"]
+#[serde(rename_all = "kebab-case")]
+pub enum RevisionKind {
+    /// Revision was created when the draft was advanced to another step.
+    Advance,
+    /// Revision was explicitly saved by a user.
+    Save,
+    /// Revision was created before restoring another revision.
+    Restore,
+}
+
+/// State of a set of changes proposed to a draft.
+#[derive(Clone, Copy, DbEnum, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[DieselType = "Change_state"]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeState {
+    /// Changes are waiting to be accepted or rejected.
+    Pending,
+    /// Changes were accepted and applied to the draft.
+    Accepted,
+    /// Changes were rejected.
+    Rejected,
+}
+
+/// Condition which a draft has to satisfy before it can be moved along a link.
+#[derive(Clone, Copy, DbEnum, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[DieselType = "Link_guard"]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkGuard {
+    /// All comment threads on the draft have been resolved.
+    NoUnresolvedComments,
+    /// There are no changes proposed to the draft awaiting review.
+    NoPendingChanges,
+    /// Draft's `index.cnxml` is a well-formed CNXML document.
+    ValidCnxml,
+    /// All links and media references in the draft resolve.
+    ResolvedLinks,
+}
+
+impl fmt::Display for LinkGuard {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match *self {
+            LinkGuard::NoUnresolvedComments => "no-unresolved-comments",
+            LinkGuard::NoPendingChanges => "no-pending-changes",
+            LinkGuard::ValidCnxml => "valid-cnxml",
+            LinkGuard::ResolvedLinks => "resolved-links",
+        })
+    }
+}
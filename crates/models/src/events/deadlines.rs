@@ -0,0 +1,203 @@
+//! Monitoring of drafts' deadlines.
+
+use actix::{Actor, AsyncContext, Context};
+use chrono::{DateTime, Duration as TimeDelta, Utc};
+use diesel::{Connection as _, prelude::*, result::Error as DbError};
+use log::error;
+use std::time::Duration;
+
+use crate::db::{
+    Connection,
+    Pool,
+    models as db,
+    schema::{draft_slots, drafts, edit_process_step_slots},
+};
+use super::{DueSoon, Error, EventManager, Overdue};
+
+/// Interval between two checks of deadlines.
+///
+/// It's set to 15 minutes in production and one minute in development.
+#[cfg(any(not(debug_assertions), doc))]
+const CHECK_INTERVAL: Duration = Duration::from_secs(900);
+
+#[cfg(all(debug_assertions, not(doc)))]
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How many hours before a deadline users are warned about it.
+const DUE_SOON_HOURS: i64 = 24;
+
+/// Actix actor periodically notifying users of drafts whose deadlines are
+/// approaching or have passed.
+pub struct DeadlineMonitor {
+    pool: Pool,
+}
+
+impl DeadlineMonitor {
+    fn on_interval(&mut self, _: &mut Context<Self>) {
+        if let Err(err) = self.check() {
+            error!("Could not check deadlines: {}", err);
+        }
+    }
+
+    fn check(&mut self) -> Result<(), Error> {
+        let db = self.pool.get()?;
+        check_deadlines(&*db)?;
+        Ok(())
+    }
+}
+
+impl Default for DeadlineMonitor {
+    fn default() -> Self {
+        DeadlineMonitor {
+            pool: crate::db::pool(),
+        }
+    }
+}
+
+impl Actor for DeadlineMonitor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.on_interval(ctx);
+        ctx.run_interval(CHECK_INTERVAL, Self::on_interval);
+    }
+}
+
+/// Notify users of all drafts whose deadlines are approaching or have passed,
+/// and who were not yet notified about it.
+///
+/// Each draft is marked as notified in a separate transaction, and its users
+/// are only notified once that transaction commits, so that a failure doesn't
+/// cause them to be notified again during the next check.
+pub fn check_deadlines(db: &Connection) -> Result<(), DbError> {
+    let now = Utc::now();
+    let soon = now + TimeDelta::hours(DUE_SOON_HOURS);
+
+    let drafts = drafts::table
+        .filter(drafts::deadline.le(soon)
+            .and(drafts::deadline_notified.is_null()
+                .or(drafts::deadline_notified.lt(drafts::deadline))))
+        .get_results::<db::Draft>(db)?;
+
+    for draft in drafts {
+        let deadline = draft.deadline.expect("filtered by query");
+        let notice = match Notice::of(now, deadline, draft.deadline_notified) {
+            Some(notice) => notice,
+            None => continue,
+        };
+
+        let assignees = db.transaction(|| {
+            // Draft could have been changed since we've loaded it.
+            let updated = diesel::update(drafts::table
+                .filter(drafts::module.eq(draft.module)
+                    .and(drafts::step.eq(draft.step))
+                    .and(drafts::deadline.eq(deadline))
+                    .and(drafts::deadline_notified
+                        .is_not_distinct_from(draft.deadline_notified))))
+                .set(drafts::deadline_notified.eq(now))
+                .execute(db)?;
+
+            if updated == 0 {
+                return Ok(None);
+            }
+
+            get_assignees(db, &draft).map(Some)
+        })?;
+
+        let assignees = match assignees {
+            Some(assignees) => assignees,
+            None => continue,
+        };
+
+        match notice {
+            Notice::DueSoon => EventManager::notify(assignees, DueSoon {
+                module: draft.module,
+                step: draft.step,
+                deadline,
+            }),
+            Notice::Overdue => EventManager::notify(assignees, Overdue {
+                module: draft.module,
+                step: draft.step,
+                deadline,
+            }),
+        }
+    }
+
+    Ok(())
+}
+
+/// Notification about a deadline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Notice {
+    DueSoon,
+    Overdue,
+}
+
+impl Notice {
+    /// Determine which notification, if any, should be sent about a draft with
+    /// a given deadline at a given time.
+    ///
+    /// `notified` is the time users were last notified about the deadline.
+    /// A draft which was warned about an approaching deadline has it set to
+    /// a date before the deadline.
+    fn of(
+        now: DateTime<Utc>,
+        deadline: DateTime<Utc>,
+        notified: Option<DateTime<Utc>>,
+    ) -> Option<Notice> {
+        if deadline <= now {
+            match notified {
+                Some(notified) if notified >= deadline => None,
+                _ => Some(Notice::Overdue),
+            }
+        } else if deadline <= now + TimeDelta::hours(DUE_SOON_HOURS) {
+            match notified {
+                Some(_) => None,
+                None => Some(Notice::DueSoon),
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// Get IDs of users occupying slots which have any permissions in draft's
+/// current step.
+fn get_assignees(db: &Connection, draft: &db::Draft)
+-> Result<Vec<i32>, DbError> {
+    draft_slots::table
+        .inner_join(edit_process_step_slots::table
+            .on(draft_slots::slot.eq(edit_process_step_slots::slot)))
+        .filter(draft_slots::draft.eq(draft.module)
+            .and(edit_process_step_slots::step.eq(draft.step)))
+        .select(draft_slots::user)
+        .distinct()
+        .get_results(db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notices() {
+        let now = Utc::now();
+        let hours = TimeDelta::hours;
+
+        // Deadline is far away.
+        assert_eq!(Notice::of(now, now + hours(48), None), None);
+
+        // Deadline is approaching, and users were already warned about it.
+        let due = now + hours(12);
+        assert_eq!(Notice::of(now, due, None), Some(Notice::DueSoon));
+        assert_eq!(Notice::of(now, due, Some(now - hours(1))), None);
+
+        // Deadline has passed, users were warned about it before, and then
+        // notified that it has passed.
+        let due = now - hours(1);
+        assert_eq!(Notice::of(now, due, None), Some(Notice::Overdue));
+        assert_eq!(
+            Notice::of(now, due, Some(now - hours(13))), Some(Notice::Overdue));
+        assert_eq!(Notice::of(now, due, Some(now)), None);
+    }
+}
@@ -1,4 +1,5 @@
 use adaptarr_macros::From;
+use chrono::{DateTime, Utc};
 use failure::Fail;
 use serde::{Deserialize, Serialize};
 use std::fmt::Write as _;
@@ -7,6 +8,8 @@ use uuid::Uuid;
 use crate::{
     AssertExists,
     Book,
+    Comment,
+    ImportJob,
     Model,
     Module,
     User,
@@ -29,6 +32,12 @@ pub enum Event {
     SlotVacated(#[from] SlotVacated),
     DraftAdvanced(#[from] DraftAdvanced),
     NewMessage(#[from] NewMessage),
+    ImportFinished(#[from] ImportFinished),
+    ChangesProposed(#[from] ChangesProposed),
+    ChangesResolved(#[from] ChangesResolved),
+    NewComment(#[from] NewComment),
+    DueSoon(#[from] DueSoon),
+    Overdue(#[from] Overdue),
 }
 
 impl Event {
@@ -48,6 +57,18 @@ impl Event {
                 Ok(Event::DraftAdvanced(rmps::from_slice(&data)?)),
             Kind::NewMessage =>
                 Ok(Event::NewMessage(rmps::from_slice(&data)?)),
+            Kind::ImportFinished =>
+                Ok(Event::ImportFinished(rmps::from_slice(&data)?)),
+            Kind::ChangesProposed =>
+                Ok(Event::ChangesProposed(rmps::from_slice(&data)?)),
+            Kind::ChangesResolved =>
+                Ok(Event::ChangesResolved(rmps::from_slice(&data)?)),
+            Kind::NewComment =>
+                Ok(Event::NewComment(rmps::from_slice(&data)?)),
+            Kind::DueSoon =>
+                Ok(Event::DueSoon(rmps::from_slice(&data)?)),
+            Kind::Overdue =>
+                Ok(Event::Overdue(rmps::from_slice(&data)?)),
             Kind::Other => Err(LoadEventError::UnknownEvent(kind.to_string())),
         }
     }
@@ -132,6 +153,72 @@ pub struct NewMessage {
     pub message: i32,
 }
 
+/// An import job has finished.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ImportFinished {
+    /// Job which has finished.
+    pub job: i32,
+    /// Did the job finish successfully?
+    pub success: bool,
+}
+
+/// Changes to a draft were proposed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChangesProposed {
+    /// Draft to which changes were proposed.
+    pub module: Uuid,
+    /// ID of the proposed change set.
+    pub change: i32,
+    /// User who proposed the changes.
+    pub author: i32,
+}
+
+/// Proposed changes were accepted or rejected.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChangesResolved {
+    /// Draft to which changes were proposed.
+    pub module: Uuid,
+    /// ID of the change set.
+    pub change: i32,
+    /// Were the changes accepted?
+    pub accepted: bool,
+}
+
+/// A new comment was made on a module or a draft.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NewComment {
+    /// Module on which, or on a draft of which, the comment was made.
+    pub module: Uuid,
+    /// Was the comment made on a draft?
+    pub draft: bool,
+    /// ID of the comment.
+    pub comment: i32,
+    /// Author of the comment.
+    pub author: i32,
+}
+
+/// A draft's deadline is approaching.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DueSoon {
+    /// Draft whose deadline is approaching.
+    pub module: Uuid,
+    /// Step the draft is in.
+    pub step: i32,
+    /// Date and time by which the draft should leave its current step.
+    pub deadline: DateTime<Utc>,
+}
+
+/// A draft's deadline has passed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Overdue {
+    /// Draft whose deadline has passed.
+    pub module: Uuid,
+    /// Step the draft is in.
+    pub step: i32,
+    /// Date and time by which the draft should have left its current step.
+    pub deadline: DateTime<Utc>,
+}
+
 impl Event {
     pub fn kind(&self) -> &'static str {
         match *self {
@@ -142,6 +229,12 @@ impl Event {
             Event::SlotVacated(_) => "slot-vacated",
             Event::DraftAdvanced(_) => "draft-advanced",
             Event::NewMessage(_) => "new-message",
+            Event::ImportFinished(_) => "import-finished",
+            Event::ChangesProposed(_) => "changes-proposed",
+            Event::ChangesResolved(_) => "changes-resolved",
+            Event::NewComment(_) => "new-comment",
+            Event::DueSoon(_) => "due-soon",
+            Event::Overdue(_) => "overdue",
         }
     }
 }
@@ -154,6 +247,10 @@ pub enum Group {
     SlotAssignment,
     DraftAdvanced,
     Conversation,
+    Import,
+    Changes,
+    Comments,
+    Deadlines,
     Other,
 }
 
@@ -167,6 +264,12 @@ pub enum Kind {
     SlotVacated,
     DraftAdvanced,
     NewMessage,
+    ImportFinished,
+    ChangesProposed,
+    ChangesResolved,
+    NewComment,
+    DueSoon,
+    Overdue,
     Other,
 }
 
@@ -181,6 +284,12 @@ impl Kind {
             "slot-vacated" => Kind::SlotVacated,
             "draft-advanced" => Kind::DraftAdvanced,
             "new-message" => Kind::NewMessage,
+            "import-finished" => Kind::ImportFinished,
+            "changes-proposed" => Kind::ChangesProposed,
+            "changes-resolved" => Kind::ChangesResolved,
+            "new-comment" => Kind::NewComment,
+            "due-soon" => Kind::DueSoon,
+            "overdue" => Kind::Overdue,
             _ => Kind::Other,
         }
     }
@@ -192,6 +301,10 @@ impl Kind {
             Kind::SlotFilled | Kind::SlotVacated => Group::SlotAssignment,
             Kind::DraftAdvanced => Group::DraftAdvanced,
             Kind::NewMessage => Group::Conversation,
+            Kind::ImportFinished => Group::Import,
+            Kind::ChangesProposed | Kind::ChangesResolved => Group::Changes,
+            Kind::NewComment => Group::Comments,
+            Kind::DueSoon | Kind::Overdue => Group::Deadlines,
             Kind::Other => Group::Other,
         }
     }
@@ -232,7 +345,35 @@ pub enum ExpandedEvent {
     NewMessage {
         author: ExpandedUser,
         message: ExpandedMessage,
-    }
+    },
+    ImportFinished {
+        import: ExpandedImport,
+        success: bool,
+    },
+    ChangesProposed {
+        author: ExpandedUser,
+        draft: ExpandedDraft,
+    },
+    ChangesResolved {
+        draft: ExpandedDraft,
+        accepted: bool,
+    },
+    NewComment {
+        author: ExpandedUser,
+        module: ExpandedModule,
+        draft: bool,
+        comment: ExpandedMessage,
+    },
+    DueSoon {
+        draft: ExpandedDraft,
+        step: ExpandedStep,
+        deadline: String,
+    },
+    Overdue {
+        draft: ExpandedDraft,
+        step: ExpandedStep,
+        deadline: String,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -292,6 +433,14 @@ pub struct ExpandedMessage {
     pub html: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ExpandedImport {
+    /// Title of the imported module or book.
+    pub title: String,
+    /// URL of the imported module or book, if the import was successful.
+    pub url: Option<String>,
+}
+
 pub fn expand_event(domain: &str, db: &Connection, event: &db::Event)
 -> Result<ExpandedEvent, Error> {
     match Kind::from_str(&event.kind) {
@@ -309,6 +458,18 @@ pub fn expand_event(domain: &str, db: &Connection, event: &db::Event)
             expand_draft_advanced(domain, db, rmps::from_slice(&event.data)?),
         Kind::NewMessage =>
             expand_new_message(domain, db, rmps::from_slice(&event.data)?),
+        Kind::ImportFinished =>
+            expand_import_finished(domain, db, rmps::from_slice(&event.data)?),
+        Kind::ChangesProposed =>
+            expand_changes_proposed(domain, db, rmps::from_slice(&event.data)?),
+        Kind::ChangesResolved =>
+            expand_changes_resolved(domain, db, rmps::from_slice(&event.data)?),
+        Kind::NewComment =>
+            expand_new_comment(domain, db, rmps::from_slice(&event.data)?),
+        Kind::DueSoon =>
+            expand_due_soon(domain, db, rmps::from_slice(&event.data)?),
+        Kind::Overdue =>
+            expand_overdue(domain, db, rmps::from_slice(&event.data)?),
         Kind::Other => Err(Error::UnknownEvent(event.kind.clone())),
     }
 }
@@ -478,6 +639,142 @@ fn expand_new_message(domain: &str, db: &Connection, ev: NewMessage)
     })
 }
 
+fn expand_import_finished(domain: &str, db: &Connection, ev: ImportFinished)
+-> Result<ExpandedEvent, Error> {
+    let job = ImportJob::by_id(db, ev.job)
+        .assert_exists()?
+        .into_db();
+
+    let url = match (job.module, job.book) {
+        (_, Some(book)) if ev.success =>
+            Some(format!("https://{}/books/{}", domain, book)),
+        (Some(module), _) if ev.success =>
+            Some(format!("https://{}/modules/{}", domain, module)),
+        _ => None,
+    };
+
+    Ok(ExpandedEvent::ImportFinished {
+        import: ExpandedImport {
+            title: job.title,
+            url,
+        },
+        success: ev.success,
+    })
+}
+
+fn expand_changes_proposed(domain: &str, db: &Connection, ev: ChangesProposed)
+-> Result<ExpandedEvent, Error> {
+    let author = User::by_id(db, ev.author)
+        .assert_exists()?
+        .into_db();
+    let module = Module::by_id(db, ev.module)
+        .assert_exists()?
+        .into_db();
+
+    Ok(ExpandedEvent::ChangesProposed {
+        author: ExpandedUser {
+            name: author.name,
+            url: format!("https://{}/users/{}", domain, author.id),
+        },
+        draft: ExpandedDraft {
+            title: module.1.title,
+            url: format!("https://{}/drafts/{}", domain, module.0.id),
+        },
+    })
+}
+
+fn expand_changes_resolved(domain: &str, db: &Connection, ev: ChangesResolved)
+-> Result<ExpandedEvent, Error> {
+    let module = Module::by_id(db, ev.module)
+        .assert_exists()?
+        .into_db();
+
+    Ok(ExpandedEvent::ChangesResolved {
+        draft: ExpandedDraft {
+            title: module.1.title,
+            url: format!("https://{}/drafts/{}", domain, module.0.id),
+        },
+        accepted: ev.accepted,
+    })
+}
+
+fn expand_new_comment(domain: &str, db: &Connection, ev: NewComment)
+-> Result<ExpandedEvent, Error> {
+    let comment = Comment::by_id(db, ev.comment)
+        .assert_exists()?
+        .into_db();
+    let author = User::by_id(db, ev.author)
+        .assert_exists()?
+        .into_db();
+    let module = Module::by_id(db, ev.module)
+        .assert_exists()?
+        .into_db();
+
+    let url = format!("https://{}/{}/{}",
+        domain, if ev.draft { "drafts" } else { "modules" }, module.0.id);
+    let comment_url = format!("{}#comment-{}", url, comment.id);
+
+    Ok(ExpandedEvent::NewComment {
+        author: ExpandedUser {
+            name: author.name,
+            url: format!("https://{}/users/{}", domain, author.id),
+        },
+        module: ExpandedModule {
+            title: module.1.title,
+            url,
+        },
+        draft: ev.draft,
+        comment: message_format::render(
+            &comment.body.into(), MessageRenderer::new(db, comment_url),
+        ).expect("Inconsistent database: comment has an invalid body"),
+    })
+}
+
+fn expand_due_soon(domain: &str, db: &Connection, ev: DueSoon)
+-> Result<ExpandedEvent, Error> {
+    let (draft, step) = expand_deadline(domain, db, ev.module, ev.step)?;
+
+    Ok(ExpandedEvent::DueSoon {
+        draft,
+        step,
+        deadline: ev.deadline.format(DEADLINE_FORMAT).to_string(),
+    })
+}
+
+fn expand_overdue(domain: &str, db: &Connection, ev: Overdue)
+-> Result<ExpandedEvent, Error> {
+    let (draft, step) = expand_deadline(domain, db, ev.module, ev.step)?;
+
+    Ok(ExpandedEvent::Overdue {
+        draft,
+        step,
+        deadline: ev.deadline.format(DEADLINE_FORMAT).to_string(),
+    })
+}
+
+/// Format in which deadlines are presented in notifications.
+const DEADLINE_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+fn expand_deadline(domain: &str, db: &Connection, module: Uuid, step: i32)
+-> Result<(ExpandedDraft, ExpandedStep), Error> {
+    let module = Module::by_id(db, module)
+        .assert_exists()?
+        .into_db();
+    let step = Step::by_id(db, step)
+        .assert_exists()?
+        .into_db();
+
+    Ok((
+        ExpandedDraft {
+            title: module.1.title,
+            url: format!("https://{}/drafts/{}", domain, module.0.id),
+        },
+        ExpandedStep {
+            name: step.name,
+        },
+    ))
+}
+
 struct MessageRenderer<'a> {
     db: &'a Connection,
     text: String,
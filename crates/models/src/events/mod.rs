@@ -4,11 +4,13 @@ use adaptarr_macros::From;
 use diesel::result::Error as DbError;
 use failure::Fail;
 
+mod deadlines;
 #[allow(clippy::module_inception)]
 mod events;
 mod service;
 
 pub use self::{
+    deadlines::{DeadlineMonitor, check_deadlines},
     events::*,
     service::{
         EventManager,
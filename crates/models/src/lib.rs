@@ -12,6 +12,7 @@ pub mod events;
 pub mod models;
 pub mod permissions;
 pub mod processing;
+pub mod storage;
 
 pub use self::{
     config::Config,
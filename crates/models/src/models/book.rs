@@ -1,9 +1,35 @@
-use diesel::{expression::dsl::any, prelude::*, result::Error as DbError};
+use adaptarr_error::ApiError;
+use adaptarr_macros::From;
+use diesel::{
+    Connection as _,
+    expression::dsl::any,
+    prelude::*,
+    result::Error as DbError,
+};
+use failure::Fail;
 use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-use crate::{audit, db::{Connection, models as db, schema::books}};
-use super::{AssertExists, BookPart, FindModelResult, Model, Team, TeamResource};
+use crate::{
+    audit,
+    db::{Connection, models as db, schema::{books, drafts}},
+};
+use super::{
+    AssertExists,
+    BookPart,
+    Draft,
+    FindModelResult,
+    Model,
+    Module,
+    Team,
+    TeamResource,
+    Tree,
+    User,
+    XrefTarget,
+    editing::{Slot, Version},
+    module::{BeginProcessError, GetXrefTargetsError},
+};
 
 /// A book is a collection of modules and their structure.
 #[derive(Debug)]
@@ -11,6 +37,18 @@ pub struct Book {
     data: db::Book,
 }
 
+/// A cross-reference target within a book.
+#[derive(Debug, Serialize)]
+pub struct BookXrefTarget {
+    /// ID of the module containing this target.
+    pub module: Uuid,
+    /// Number of this target within the book, such as `3.4` for the fourth
+    /// figure in the third chapter.
+    pub number: Option<String>,
+    #[serde(flatten)]
+    pub target: <XrefTarget as Model>::Public,
+}
+
 /// A subset of book's data that can safely be publicly exposed.
 #[derive(Debug, Serialize)]
 pub struct Public {
@@ -131,6 +169,148 @@ impl Book {
         self.data.title = title;
         Ok(())
     }
+
+    /// Get list of all possible cross-reference targets within this book.
+    ///
+    /// Targets are numbered across the whole book: targets in a chapter (a group
+    /// at the top level of the book) are numbered `chapter.n`, and targets in
+    /// modules outside of any chapter are numbered `n`. Targets which are part
+    /// of other targets (such as solutions to exercises) are not numbered.
+    pub fn xref_targets(&self, db: &Connection)
+    -> Result<Vec<BookXrefTarget>, GetXrefTargetsError> {
+        let tree = self.root_part(db)?.get_tree(db)?;
+        let mut targets = Vec::new();
+        let mut chapter = 0;
+        let mut numbering = Numbering::new(None);
+
+        for part in tree.parts().unwrap_or(&[]) {
+            if part.module().is_some() {
+                collect_targets(db, part, &mut numbering, &mut targets)?;
+            } else {
+                chapter += 1;
+                collect_targets(
+                    db, part, &mut Numbering::new(Some(chapter)), &mut targets)?;
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// Begin a new editing process for modules in this book.
+    ///
+    /// When `filter` is given, process is only started for the modules it
+    /// names, all of which must be in this book. Modules which already have
+    /// a draft are skipped, and reported without one.
+    ///
+    /// This operation is atomic: if the process could not be started for any
+    /// of the modules, it is not started for any of them.
+    pub fn begin_process(
+        &self,
+        db: &Connection,
+        version: &Version,
+        slots: &[(Slot, User)],
+        filter: Option<&[Uuid]>,
+    ) -> Result<Vec<(Uuid, Option<Draft>)>, BeginBookProcessError> {
+        let mut seen = HashSet::new();
+        let mut modules = self.root_part(db)?.get_tree(db)?.modules();
+        modules.retain(|id| seen.insert(*id));
+
+        if let Some(filter) = filter {
+            if let Some(id) = filter.iter().find(|id| !seen.contains(*id)) {
+                return Err(BeginBookProcessError::NotInBook(*id));
+            }
+
+            modules.retain(|id| filter.contains(id));
+        }
+
+        db.transaction(|| {
+            let existing = drafts::table
+                .filter(drafts::module.eq(any(&modules)))
+                .select(drafts::module)
+                .get_results::<Uuid>(db)?
+                .into_iter()
+                .collect::<HashSet<_>>();
+
+            modules.iter()
+                .map(|&id| {
+                    if existing.contains(&id) {
+                        return Ok((id, None));
+                    }
+
+                    let draft = Module::by_id(db, id)
+                        .assert_exists()?
+                        .begin_process(db, version, slots.iter().cloned())
+                        .map_err(|err| BeginBookProcessError::Module(id, err))?;
+
+                    Ok((id, Some(draft)))
+                })
+                .collect()
+        })
+    }
+}
+
+/// Collect cross-reference targets in all modules of a part, in order.
+fn collect_targets(
+    db: &Connection,
+    part: &Tree,
+    numbering: &mut Numbering,
+    targets: &mut Vec<BookXrefTarget>,
+) -> Result<(), GetXrefTargetsError> {
+    if let Some(module) = part.module() {
+        let mut module_targets = Module::by_id(db, module.id)
+            .assert_exists()?
+            .xref_targets(db)?
+            .get_public();
+
+        // Per-document counters preserve order of targets of the same type.
+        module_targets.sort_by(|a, b|
+            (&a.type_, a.counter).cmp(&(&b.type_, b.counter)));
+
+        for target in module_targets {
+            let number = match target.context {
+                Some(_) => None,
+                None => Some(numbering.next(&target.type_)),
+            };
+
+            targets.push(BookXrefTarget {
+                module: module.id,
+                number,
+                target,
+            });
+        }
+    }
+
+    for part in part.parts().unwrap_or(&[]) {
+        collect_targets(db, part, numbering, targets)?;
+    }
+
+    Ok(())
+}
+
+/// Book-level counters of cross-reference targets.
+struct Numbering {
+    chapter: Option<i32>,
+    counters: HashMap<String, usize>,
+}
+
+impl Numbering {
+    fn new(chapter: Option<i32>) -> Numbering {
+        Numbering {
+            chapter,
+            counters: HashMap::new(),
+        }
+    }
+
+    /// Get number of the next target of a given type.
+    fn next(&mut self, type_: &str) -> String {
+        let counter = self.counters.entry(type_.to_string()).or_insert(0);
+        *counter += 1;
+
+        match self.chapter {
+            Some(chapter) => format!("{}.{}", chapter, counter),
+            None => counter.to_string(),
+        }
+    }
 }
 
 impl std::ops::Deref for Book {
@@ -141,7 +321,137 @@ impl std::ops::Deref for Book {
     }
 }
 
+#[derive(ApiError, Debug, Fail, From)]
+pub enum BeginBookProcessError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// One of the modules to start the process for is not in this book.
+    #[fail(display = "Module {} is not in this book", _0)]
+    #[api(code = "book:process:not-in-book", status = "BAD_REQUEST")]
+    NotInBook(Uuid),
+    /// Process could not be started for one of the modules.
+    #[fail(display = "Could not begin process for module {}: {}", _0, _1)]
+    Module(Uuid, #[cause] BeginProcessError),
+}
+
 #[derive(Serialize)]
 struct LogCreation<'a> {
     title: &'a str,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::db;
+    use super::*;
+    use super::super::fixtures;
+
+    /// Create a book containing `modules` in a group, with the first of them
+    /// also included again outside of that group.
+    fn book(db: &Connection, team: &Team, modules: &[&Module]) -> Book {
+        let book = Book::create(db, team, "Book").unwrap();
+        let root = book.root_part(db).unwrap();
+        let group = root.create_group(db, 0, "Group").unwrap();
+
+        for (inx, module) in modules.iter().enumerate() {
+            group.insert_module(db, inx as i32, "Module", module).unwrap();
+        }
+
+        root.insert_module(db, 1, "Again", modules[0]).unwrap();
+
+        book
+    }
+
+    fn slots(db: &Connection, version: &Version, user: &User)
+    -> Vec<(Slot, User)> {
+        version.get_slots(db).unwrap()
+            .into_iter()
+            .map(|slot| (slot, user.clone()))
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn begin_process_for_all_modules() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let mut team = fixtures::team(&db);
+        let user = fixtures::member(&db, &mut team);
+        let process = fixtures::process(&db, &team);
+        let first = fixtures::module(&db, &team, "First");
+        let second = fixtures::module(&db, &team, "Second");
+        let third = fixtures::module(&db, &team, "Third");
+        let book = book(&db, &team, &[&first, &second, &third]);
+        fixtures::draft(&db, &first, &process, &user);
+
+        let drafts = book.begin_process(
+            &db, &process, &slots(&db, &process, &user), None).unwrap()
+            .into_iter()
+            .map(|(id, draft)| (id, draft.is_some()))
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(drafts.len(), 3);
+        assert!(!drafts[&first.id()]);
+        assert!(drafts[&second.id()]);
+        assert!(drafts[&third.id()]);
+    }
+
+    #[test]
+    #[ignore]
+    fn begin_process_for_some_modules() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let mut team = fixtures::team(&db);
+        let user = fixtures::member(&db, &mut team);
+        let process = fixtures::process(&db, &team);
+        let first = fixtures::module(&db, &team, "First");
+        let second = fixtures::module(&db, &team, "Second");
+        let outside = fixtures::module(&db, &team, "Outside");
+        let book = book(&db, &team, &[&first, &second]);
+        let slots = slots(&db, &process, &user);
+
+        match book.begin_process(
+            &db, &process, &slots, Some(&[outside.id()][..]))
+        {
+            Err(BeginBookProcessError::NotInBook(id)) =>
+                assert_eq!(id, outside.id()),
+            result => panic!("expected NotInBook, got {:?}", result),
+        }
+
+        let drafts = book.begin_process(
+            &db, &process, &slots, Some(&[second.id()][..])).unwrap();
+
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].0, second.id());
+        assert!(Draft::by_id(&db, first.id()).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn begin_process_is_atomic() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let mut team = fixtures::team(&db);
+        let user = fixtures::member(&db, &mut team);
+        let process = fixtures::process(&db, &team);
+        let first = fixtures::module(&db, &team, "First");
+        let other = fixtures::team(&db);
+        let foreign = fixtures::module(&db, &other, "Foreign");
+        let book = book(&db, &team, &[&first, &foreign]);
+
+        match book.begin_process(
+            &db, &process, &slots(&db, &process, &user), None)
+        {
+            Err(BeginBookProcessError::Module(
+                id, BeginProcessError::BadProcess(_))) =>
+                assert_eq!(id, foreign.id()),
+            result => panic!("expected BadProcess, got {:?}", result),
+        }
+
+        assert!(Draft::by_id(&db, first.id()).is_err());
+    }
+}
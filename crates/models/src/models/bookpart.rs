@@ -375,6 +375,46 @@ impl BookPart {
     }
 }
 
+impl Tree {
+    /// Title of this part.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// If this part is a module, get its data.
+    pub fn module(&self) -> Option<&<Module as Model>::Public> {
+        match self.part {
+            Variant::Module(ref module) => Some(module),
+            Variant::Group { .. } => None,
+        }
+    }
+
+    /// If this part is a group, get its sub-parts.
+    pub fn parts(&self) -> Option<&[Tree]> {
+        match self.part {
+            Variant::Module(_) => None,
+            Variant::Group { ref parts } => Some(parts),
+        }
+    }
+
+    /// Get IDs of all modules in this part, in order.
+    pub fn modules(&self) -> Vec<Uuid> {
+        fn collect(part: &Tree, modules: &mut Vec<Uuid>) {
+            if let Some(module) = part.module() {
+                modules.push(module.id);
+            }
+
+            for part in part.parts().unwrap_or(&[]) {
+                collect(part, modules);
+            }
+        }
+
+        let mut modules = Vec::new();
+        collect(self, &mut modules);
+        modules
+    }
+}
+
 impl std::ops::Deref for BookPart {
     type Target = db::BookPart;
 
@@ -0,0 +1,398 @@
+use adaptarr_error::ApiError;
+use adaptarr_macros::From;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use diesel::{Connection as _, prelude::*, result::Error as DbError};
+use failure::Fail;
+use minidom::Element;
+use serde::Serialize;
+use std::{collections::HashSet, io, str::FromStr};
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    db::{
+        Connection,
+        models as db,
+        schema::{comments, draft_slots, users},
+    },
+    events::{EventManager, NewComment},
+    permissions::TeamPermissions,
+};
+use super::{
+    AssertExists,
+    Document,
+    FindModelResult,
+    Model,
+    conversation::format::{self, Validation},
+};
+
+/// A comment made on a module or a draft.
+///
+/// Comments can be replies to other comments, forming threads, and can be
+/// anchored to an element of the document's `index.cnxml`. Their bodies use the
+/// same format as conversation messages.
+#[derive(Debug)]
+pub struct Comment {
+    data: db::Comment,
+}
+
+/// Object on which a comment was made.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Target {
+    Module(Uuid),
+    Draft(Uuid),
+}
+
+/// A subset of comment's data that can safely be publicly exposed.
+#[derive(Debug, Serialize)]
+pub struct Public {
+    pub id: i32,
+    pub parent: Option<i32>,
+    pub element: Option<String>,
+    pub author: Option<i32>,
+    /// Comment's body, encoded with base64.
+    pub body: String,
+    pub created: DateTime<Utc>,
+    pub resolved: Option<DateTime<Utc>>,
+    pub resolved_by: Option<i32>,
+}
+
+impl Model for Comment {
+    const ERROR_CATEGORY: &'static str = "comment";
+
+    type Id = i32;
+    type Database = db::Comment;
+    type Public = Public;
+    type PublicParams = ();
+
+    fn by_id(db: &Connection, id: Self::Id) -> FindModelResult<Self> {
+        comments::table
+            .filter(comments::id.eq(id))
+            .get_result(db)
+            .map(Self::from_db)
+            .map_err(From::from)
+    }
+
+    fn from_db(data: Self::Database) -> Self {
+        Comment { data }
+    }
+
+    fn into_db(self) -> Self::Database {
+        self.data
+    }
+
+    fn id(&self) -> Self::Id {
+        self.data.id
+    }
+
+    fn get_public(&self) -> Public {
+        Public {
+            id: self.data.id,
+            parent: self.data.parent,
+            element: self.data.element.clone(),
+            author: self.data.author,
+            body: base64::encode(&self.data.body),
+            created: self.data.created,
+            resolved: self.data.resolved,
+            resolved_by: self.data.resolved_by,
+        }
+    }
+}
+
+impl Comment {
+    /// Get all comments made on a module or a draft, oldest first.
+    pub fn by_target(db: &Connection, target: Target)
+    -> Result<Vec<Comment>, DbError> {
+        let query = comments::table
+            .order_by(comments::id.asc())
+            .into_boxed();
+
+        let query = match target {
+            Target::Module(id) => query.filter(comments::module.eq(id)),
+            Target::Draft(id) => query.filter(comments::draft.eq(id)),
+        };
+
+        query.get_results(db)
+            .map(|v| v.into_iter().map(Self::from_db).collect())
+    }
+
+    /// Make a new comment.
+    ///
+    /// `document` is the current content of `target`, and is used to verify
+    /// that `element` exists. Replies are always anchored to the same element
+    /// as the comment they reply to.
+    ///
+    /// Users holding slots in a draft, users mentioned in the comment, and
+    /// the author of the comment replied to are notified about the new
+    /// comment.
+    pub(super) fn create(
+        db: &Connection,
+        target: Target,
+        document: &Document,
+        author: i32,
+        parent: Option<i32>,
+        element: Option<&str>,
+        body: &Bytes,
+    ) -> Result<Comment, CreateCommentError> {
+        let Validation { mentions, body, .. } = format::validate(body)?;
+
+        db.transaction(|| {
+            let parent = match parent {
+                Some(id) => {
+                    let parent = comments::table
+                        .filter(comments::id.eq(id))
+                        .get_result::<db::Comment>(db)
+                        .optional()?
+                        .map(Comment::from_db)
+                        .filter(|parent| parent.target() == target)
+                        .ok_or(CreateCommentError::NoSuchParent)?;
+                    Some(parent)
+                }
+                None => None,
+            };
+
+            let element = match parent {
+                Some(ref parent) =>
+                    parent.data.element.as_ref().map(String::as_str),
+                None => element,
+            };
+
+            if let Some(element) = element {
+                check_element(db, document, element)?;
+            }
+
+            let (module, draft, id) = match target {
+                Target::Module(id) => (Some(id), None, id),
+                Target::Draft(id) => (None, Some(id), id),
+            };
+
+            let data = diesel::insert_into(comments::table)
+                .values(&db::NewComment {
+                    module,
+                    draft,
+                    parent: parent.as_ref().map(|parent| parent.data.id),
+                    element,
+                    author: Some(author),
+                    body: body.as_ref(),
+                })
+                .get_result::<db::Comment>(db)?;
+
+            audit::log_db_actor(
+                db, author, "comments", data.id, "create", LogCreation {
+                    module,
+                    draft,
+                    parent: data.parent,
+                    element,
+                    body: body.as_ref(),
+                });
+
+            let mut recipients = users::table
+                .filter(users::id.eq_any(mentions))
+                .select(users::id)
+                .get_results::<i32>(db)?
+                .into_iter()
+                .collect::<HashSet<_>>();
+
+            if let Target::Draft(draft) = target {
+                recipients.extend(draft_slots::table
+                    .filter(draft_slots::draft.eq(draft))
+                    .select(draft_slots::user)
+                    .get_results::<i32>(db)?);
+            }
+
+            if let Some(user) = parent.and_then(|parent| parent.data.author) {
+                recipients.insert(user);
+            }
+
+            recipients.remove(&author);
+
+            let recipients = recipients.into_iter().collect::<Vec<_>>();
+
+            EventManager::notify(recipients, NewComment {
+                module: id,
+                draft: draft.is_some(),
+                comment: data.id,
+                author,
+            });
+
+            Ok(Comment::from_db(data))
+        })
+    }
+
+    /// Get the object on which this comment was made.
+    pub fn target(&self) -> Target {
+        match (self.data.module, self.data.draft) {
+            (Some(module), _) => Target::Module(module),
+            (None, Some(draft)) => Target::Draft(draft),
+            (None, None) => panic!(
+                "Inconsistent database: comment {} has no target", self.data.id),
+        }
+    }
+
+    /// Move all comments made on a draft onto its module.
+    ///
+    /// This should be done before a draft is deleted, so that comments made on
+    /// it are preserved.
+    pub(super) fn move_to_module(db: &Connection, draft: Uuid)
+    -> Result<(), DbError> {
+        // Drafts share IDs with their modules.
+        diesel::update(comments::table.filter(comments::draft.eq(draft)))
+            .set((
+                comments::module.eq(Some(draft)),
+                comments::draft.eq(None::<Uuid>),
+            ))
+            .execute(db)?;
+        Ok(())
+    }
+
+    /// Mark this comment as resolved or unresolved.
+    ///
+    /// Only comments which start a thread can be resolved, and only by their
+    /// authors, users occupying slots in the draft they were made on, and users
+    /// with the [`TeamPermissions::MANAGE_PROCESS`] permission. `permissions`
+    /// are the permissions `user` has in the team owning this comment's target.
+    pub fn set_resolved(
+        &mut self,
+        db: &Connection,
+        user: i32,
+        permissions: TeamPermissions,
+        resolved: bool,
+    ) -> Result<(), ResolveCommentError> {
+        if self.data.parent.is_some() {
+            return Err(ResolveCommentError::Reply);
+        }
+
+        if !self.can_resolve(db, user, permissions)? {
+            return Err(ResolveCommentError::Forbidden);
+        }
+
+        if self.data.resolved.is_some() == resolved {
+            return Ok(());
+        }
+
+        let (date, by) = if resolved {
+            (Some(Utc::now()), Some(user))
+        } else {
+            (None, None)
+        };
+
+        db.transaction(|| {
+            audit::log_db_actor(
+                db, user, "comments", self.data.id, "set-resolved", resolved);
+
+            self.data = diesel::update(&self.data)
+                .set((
+                    comments::resolved.eq(date),
+                    comments::resolved_by.eq(by),
+                ))
+                .get_result(db)?;
+
+            Ok(())
+        })
+    }
+
+    /// Check whether a user can resolve this comment.
+    fn can_resolve(
+        &self,
+        db: &Connection,
+        user: i32,
+        permissions: TeamPermissions,
+    ) -> Result<bool, DbError> {
+        if self.data.author == Some(user)
+        || permissions.contains(TeamPermissions::MANAGE_PROCESS) {
+            return Ok(true);
+        }
+
+        match self.target() {
+            Target::Module(_) => Ok(false),
+            Target::Draft(draft) => diesel::select(diesel::dsl::exists(
+                draft_slots::table
+                    .filter(draft_slots::draft.eq(draft)
+                        .and(draft_slots::user.eq(user)))))
+                .get_result(db),
+        }
+    }
+}
+
+impl std::ops::Deref for Comment {
+    type Target = db::Comment;
+
+    fn deref(&self) -> &db::Comment {
+        &self.data
+    }
+}
+
+/// Verify that an element with specified ID exists in a document.
+fn check_element(db: &Connection, document: &Document, id: &str)
+-> Result<(), CreateCommentError> {
+    let content = document.get_file(db, "index.cnxml")
+        .assert_exists()?
+        .read_to_string()?;
+    let root = Element::from_str(&content)?;
+
+    fn find(element: &Element, id: &str) -> bool {
+        element.attr("id") == Some(id)
+            || element.children().any(|child| find(child, id))
+    }
+
+    if find(&root, id) {
+        Ok(())
+    } else {
+        Err(CreateCommentError::NoSuchElement)
+    }
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum CreateCommentError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// Comment's body is not a valid message.
+    #[fail(display = "Invalid comment body: {}", _0)]
+    #[api(code = "comment:invalid-body", status = "BAD_REQUEST")]
+    InvalidBody(#[cause] #[from] format::Error),
+    /// Comment replied to doesn't exist, or was made on a different module or
+    /// draft.
+    #[fail(display = "No such comment to reply to")]
+    #[api(code = "comment:parent:not-found", status = "BAD_REQUEST")]
+    NoSuchParent,
+    /// Element the comment was to be anchored to doesn't exist.
+    #[fail(display = "No such element")]
+    #[api(code = "comment:element:not-found", status = "BAD_REQUEST")]
+    NoSuchElement,
+    /// Document's `index.cnxml` is not valid XML.
+    #[fail(display = "Invalid XML: {}", _0)]
+    #[api(internal)]
+    InvalidXml(#[cause] #[from] minidom::Error),
+    /// An operating system error.
+    #[fail(display = "System error: {}", _0)]
+    #[api(internal)]
+    System(#[cause] #[from] io::Error),
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum ResolveCommentError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// Only comments starting a thread can be resolved.
+    #[fail(display = "Replies cannot be resolved")]
+    #[api(code = "comment:resolve:reply", status = "BAD_REQUEST")]
+    Reply,
+    /// User is not allowed to resolve this comment.
+    #[fail(display = "Insufficient permissions to resolve this comment")]
+    #[api(code = "comment:resolve:forbidden", status = "FORBIDDEN")]
+    Forbidden,
+}
+
+#[derive(Serialize)]
+struct LogCreation<'a> {
+    module: Option<Uuid>,
+    draft: Option<Uuid>,
+    parent: Option<i32>,
+    element: Option<&'a str>,
+    body: &'a [u8],
+}
@@ -152,6 +152,44 @@ impl Document {
 
         Ok(())
     }
+
+    /// Replace content of this document with that of another document.
+    ///
+    /// The document is marked as needing processing, but it's up to the caller
+    /// to send it to [`TargetProcessor`] once the transaction commits.
+    ///
+    /// [`TargetProcessor`]: crate::processing::TargetProcessor
+    pub(super) fn replace_content(&mut self, db: &Connection, source: &Document)
+    -> Result<(), DbError> {
+        db.transaction(|| {
+            let files = source.get_files(db)?;
+
+            diesel::delete(document_files::table
+                .filter(document_files::document.eq(self.data.id)))
+                .execute(db)?;
+
+            diesel::insert_into(document_files::table)
+                .values(files.iter()
+                    .map(|(name, file)| db::NewDocumentFile {
+                        document: self.data.id,
+                        name,
+                        file: file.id,
+                    })
+                    .collect::<Vec<_>>())
+                .execute(db)?;
+
+            self.data = diesel::update(&self.data)
+                .set((
+                    documents::title.eq(&source.data.title),
+                    documents::language.eq(&source.data.language),
+                    documents::index.eq(source.data.index),
+                    documents::xrefs_ready.eq(false),
+                ))
+                .get_result(db)?;
+
+            Ok(())
+        })
+    }
 }
 
 impl std::ops::Deref for Document {
@@ -1,10 +1,13 @@
 use actix::SystemService;
 use adaptarr_error::ApiError;
 use adaptarr_macros::From;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use diesel::{Connection as _, prelude::*, result::Error as DbError};
 use failure::Fail;
 use itertools::Itertools;
 use serde::Serialize;
+use std::{fmt, io};
 use uuid::Uuid;
 
 use crate::{
@@ -14,23 +17,43 @@ use crate::{
         models as db,
         schema::{
             book_parts,
+            comments,
             document_files,
             documents,
+            draft_changes,
             draft_slots,
             drafts,
             edit_process_links,
             edit_process_step_slots,
+            files,
             modules,
         },
-        types::SlotPermission,
+        types::{ChangeState, LinkGuard, RevisionKind, SlotPermission},
+    },
+    events::{
+        ChangesProposed,
+        ChangesResolved,
+        DraftAdvanced,
+        EventManager,
+        ProcessCancelled,
+        ProcessEnded,
     },
-    events::{DraftAdvanced, EventManager, ProcessCancelled, ProcessEnded},
     permissions::TeamPermissions,
-    processing::{TargetProcessor, ProcessDocument},
+    processing::{
+        TargetProcessor,
+        ProcessDocument,
+        import::check_cnxml,
+        links::{self, CheckLinksError, LinkProblem},
+    },
 };
 use super::{
     AssertExists,
+    Comment,
+    CommentTarget,
+    CreateCommentError,
     Document,
+    DraftChange,
+    DraftRevision,
     File,
     FindModelError,
     FindModelResult,
@@ -61,6 +84,7 @@ pub struct Public {
     pub step: Option<<Step as Model>::Public>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub books: Option<Vec<Uuid>>,
+    pub deadline: Option<DateTime<Utc>>,
 }
 
 impl TeamResource for Draft {
@@ -109,6 +133,7 @@ impl Model for Draft {
             permissions: None,
             step: None,
             books: None,
+            deadline: self.data.deadline,
         }
     }
 
@@ -122,6 +147,7 @@ impl Model for Draft {
                 .get_public_full(db, &(Some(self.data.module), Some(user)))
                 .map(Some)?,
             books: self.get_books(db).map(Some)?,
+            deadline: self.data.deadline,
         })
     }
 }
@@ -162,6 +188,8 @@ impl Draft {
                 .select(draft_slots::user)
                 .get_results::<i32>(db)?;
 
+            DraftRevision::delete_all(db, self.data.module)?;
+            Comment::move_to_module(db, self.data.module)?;
             diesel::delete(&self.data).execute(db)?;
             self.document.delete(db)?;
 
@@ -255,6 +283,22 @@ impl Draft {
     /// If there already is a file with this name it will be updated, otherwise
     /// a new file will be created.
     pub fn write_file(&self, db: &Connection, name: &str, file: &File)
+    -> Result<(), DbError> {
+        db.transaction(|| self.put_file(db, name, file))?;
+
+        if name == "index.cnxml" {
+            self.process_document(db)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write into a file in this draft, without sending the document for
+    /// processing.
+    ///
+    /// If `index.cnxml` is written the caller must call
+    /// [`Draft::process_document()`] once the current transaction commits.
+    fn put_file(&self, db: &Connection, name: &str, file: &File)
     -> Result<(), DbError> {
         db.transaction(|| {
             audit::log_db(
@@ -265,7 +309,10 @@ impl Draft {
 
             if name == "index.cnxml" {
                 diesel::update(&*self.document)
-                    .set(documents::index.eq(file.id))
+                    .set((
+                        documents::index.eq(file.id),
+                        documents::xrefs_ready.eq(false),
+                    ))
                     .execute(db)?;
                 return Ok(());
             }
@@ -285,6 +332,80 @@ impl Draft {
         })
     }
 
+    /// Send this draft's document for processing (creating its list of
+    /// cross-reference targets and updating the search index) after its
+    /// content has changed.
+    ///
+    /// This must not be called from the transaction which changed the content.
+    fn process_document(&self, db: &Connection) -> Result<(), DbError> {
+        let document = documents::table
+            .filter(documents::id.eq(self.document.id))
+            .get_result::<db::Document>(db)?;
+
+        TargetProcessor::process(document);
+
+        Ok(())
+    }
+
+    /// Write into a file in this draft, but only if its current version
+    /// satisfies a precondition.
+    ///
+    /// The document is locked before its current version is passed to `check`,
+    /// so that concurrent writes can't both satisfy the same precondition.
+    pub fn write_file_if<F>(
+        &self,
+        db: &Connection,
+        name: &str,
+        file: &File,
+        check: F,
+    ) -> Result<(), WriteFileError>
+    where
+        F: FnOnce(Option<&File>) -> bool,
+    {
+        db.transaction(|| {
+            let current = self.lock_file(db, name)?;
+
+            if !check(current.as_ref()) {
+                return Err(WriteFileError::Precondition(current));
+            }
+
+            self.put_file(db, name, file)?;
+
+            Ok(())
+        })?;
+
+        if name == "index.cnxml" {
+            self.process_document(db)?;
+        }
+
+        Ok(())
+    }
+
+    /// Lock this draft's document until the end of current transaction, and
+    /// get the current version of a file in it.
+    fn lock_file(&self, db: &Connection, name: &str)
+    -> Result<Option<File>, DbError> {
+        let document = documents::table
+            .filter(documents::id.eq(self.document.id))
+            .for_update()
+            .get_result::<db::Document>(db)?;
+
+        if name == "index.cnxml" {
+            return files::table
+                .filter(files::id.eq(document.index))
+                .get_result::<db::File>(db)
+                .map(|data| Some(File::from_db(data)));
+        }
+
+        document_files::table
+            .filter(document_files::document.eq(document.id)
+                .and(document_files::name.eq(name)))
+            .inner_join(files::table)
+            .get_result::<(db::DocumentFile, db::File)>(db)
+            .optional()
+            .map(|file| file.map(|(_, data)| File::from_db(data)))
+    }
+
     /// Delete a file from this draft.
     pub fn delete_file(&self, db: &Connection, name: &str) -> Result<(), DbError> {
         diesel::delete(document_files::table
@@ -297,6 +418,214 @@ impl Draft {
         Ok(())
     }
 
+    /// Delete a file from this draft, but only if its current version
+    /// satisfies a precondition.
+    ///
+    /// See [`Draft::write_file_if()`] for details.
+    pub fn delete_file_if<F>(&self, db: &Connection, name: &str, check: F)
+    -> Result<(), WriteFileError>
+    where
+        F: FnOnce(Option<&File>) -> bool,
+    {
+        db.transaction(|| {
+            let current = self.lock_file(db, name)?;
+
+            if !check(current.as_ref()) {
+                return Err(WriteFileError::Precondition(current));
+            }
+
+            self.delete_file(db, name)?;
+
+            Ok(())
+        })
+    }
+
+    /// Get the version of `index.cnxml` against which new changes will be
+    /// proposed.
+    ///
+    /// Consecutive proposals build upon each other, so this is the version
+    /// proposed in the last pending change set which (transitively) builds
+    /// upon the current version, or the current version if there is no such
+    /// change set.
+    pub fn get_changes_base(&self, db: &Connection) -> Result<File, DbError> {
+        let mut base = documents::table
+            .filter(documents::id.eq(self.document.id))
+            .select(documents::index)
+            .get_result::<i32>(db)?;
+
+        for change in DraftChange::pending(db, self.data.module)? {
+            if change.base == base {
+                base = change.file;
+            }
+        }
+
+        File::by_id(db, base).assert_exists()
+    }
+
+    /// Get all change sets proposed to this draft, oldest first.
+    pub fn get_changes(&self, db: &Connection)
+    -> Result<Vec<DraftChange>, DbError> {
+        DraftChange::by_draft(db, self.data.module)
+    }
+
+    /// Propose a new version of `index.cnxml`.
+    ///
+    /// Instead of being written into this draft, changes are recorded and
+    /// will only be applied once accepted.
+    ///
+    /// Changes are only proposed if `check` accepts the version against which
+    /// they would be proposed (see [`Draft::get_changes_base()`]).
+    pub fn propose_changes<F>(
+        &self,
+        db: &Connection,
+        user: i32,
+        file: &File,
+        check: F,
+    ) -> Result<DraftChange, WriteFileError>
+    where
+        F: FnOnce(Option<&File>) -> bool,
+    {
+        db.transaction(|| {
+            // Lock the draft so that concurrent proposals can't use the same
+            // base.
+            drafts::table
+                .filter(drafts::module.eq(self.data.module))
+                .select(drafts::module)
+                .for_update()
+                .get_result::<Uuid>(db)?;
+
+            let base = self.get_changes_base(db)?;
+
+            if !check(Some(&base)) {
+                return Err(WriteFileError::Precondition(Some(base)));
+            }
+
+            let change = DraftChange::create(
+                db, self.data.module, user, &base, file)?;
+
+            audit::log_db_actor(
+                db, user, "drafts", self.data.module, "propose-changes",
+                LogProposeChanges {
+                    change: change.id(),
+                    base: base.id,
+                    file: file.id,
+                });
+
+            let accepters = draft_slots::table
+                .inner_join(edit_process_step_slots::table
+                    .on(draft_slots::slot.eq(edit_process_step_slots::slot)))
+                .filter(draft_slots::draft.eq(self.data.module)
+                    .and(edit_process_step_slots::permission
+                        .eq(SlotPermission::AcceptChanges)))
+                .select(draft_slots::user)
+                .distinct()
+                .get_results::<i32>(db)?;
+
+            EventManager::notify(accepters, ChangesProposed {
+                module: self.data.module,
+                change: change.id(),
+                author: user,
+            });
+
+            Ok(change)
+        })
+    }
+
+    /// Accept proposed changes, applying them to this draft.
+    ///
+    /// Changes can only be accepted if they were proposed against the current
+    /// version of `index.cnxml`.
+    pub fn accept_changes(&self, db: &Connection, user: i32, change: &mut DraftChange)
+    -> Result<(), ResolveChangesError> {
+        db.transaction(|| {
+            self.lock_pending(db, change)?;
+
+            let document = documents::table
+                .filter(documents::id.eq(self.document.id))
+                .for_update()
+                .get_result::<db::Document>(db)?;
+
+            if document.index != change.base {
+                return Err(ResolveChangesError::Conflict);
+            }
+
+            self.put_file(db, "index.cnxml", &change.get_file(db)?)?;
+            self.resolve_changes(db, user, change, ChangeState::Accepted)?;
+
+            Ok(())
+        })?;
+
+        self.process_document(db)?;
+
+        Ok(())
+    }
+
+    /// Reject proposed changes.
+    ///
+    /// All pending changes which were proposed on top of `change` are rejected
+    /// as well.
+    pub fn reject_changes(&self, db: &Connection, user: i32, change: &mut DraftChange)
+    -> Result<(), ResolveChangesError> {
+        db.transaction(|| {
+            self.lock_pending(db, change)?;
+            self.resolve_changes(db, user, change, ChangeState::Rejected)?;
+
+            for mut dependent in change.get_dependents(db)? {
+                dependent.lock(db)?;
+                self.resolve_changes(
+                    db, user, &mut dependent, ChangeState::Rejected)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Lock a change set until the end of current transaction, and verify that
+    /// it is still pending.
+    fn lock_pending(&self, db: &Connection, change: &mut DraftChange)
+    -> Result<(), ResolveChangesError> {
+        if change.draft() != self.data.module {
+            return Err(ResolveChangesError::NotFound);
+        }
+
+        change.lock(db)?;
+
+        if change.state != ChangeState::Pending {
+            return Err(ResolveChangesError::Resolved);
+        }
+
+        Ok(())
+    }
+
+    fn resolve_changes(
+        &self,
+        db: &Connection,
+        user: i32,
+        change: &mut DraftChange,
+        state: ChangeState,
+    ) -> Result<(), DbError> {
+        db.transaction(|| {
+            change.resolve(db, user, state)?;
+
+            audit::log_db_actor(
+                db, user, "drafts", self.data.module, "resolve-changes",
+                LogResolveChanges {
+                    change: change.id(),
+                    state,
+                });
+
+            if let Some(author) = change.user {
+                EventManager::notify(author, ChangesResolved {
+                    module: self.data.module,
+                    change: change.id(),
+                    accepted: state == ChangeState::Accepted,
+                });
+            }
+
+            Ok(())
+        })
+    }
+
     /// Change title of this draft's document.
     pub fn set_title(&mut self, db: &Connection, title: &str) -> Result<(), DbError> {
         self.document.set_title(db, title)?;
@@ -304,6 +633,121 @@ impl Draft {
         Ok(())
     }
 
+    /// Get the date and time by which this draft should leave its current
+    /// step, if any.
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        self.data.deadline
+    }
+
+    /// Change the date and time by which this draft should leave its current
+    /// step.
+    ///
+    /// The deadline is reset to the step's default each time this draft is
+    /// advanced.
+    pub fn set_deadline(
+        &mut self,
+        db: &Connection,
+        deadline: Option<DateTime<Utc>>,
+    ) -> Result<(), DbError> {
+        db.transaction(|| {
+            audit::log_db(
+                db, "drafts", self.data.module, "set-deadline", deadline);
+
+            self.data = diesel::update(&self.data)
+                .set((
+                    drafts::deadline.eq(deadline),
+                    drafts::deadline_notified.eq(None::<DateTime<Utc>>),
+                ))
+                .get_result(db)?;
+
+            Ok(())
+        })
+    }
+
+    /// Get all comments made on this draft, oldest first.
+    pub fn get_comments(&self, db: &Connection)
+    -> Result<Vec<Comment>, DbError> {
+        Comment::by_target(db, CommentTarget::Draft(self.data.module))
+    }
+
+    /// Comment on this draft.
+    ///
+    /// See [`Comment`] for details.
+    pub fn add_comment(
+        &self,
+        db: &Connection,
+        user: i32,
+        parent: Option<i32>,
+        element: Option<&str>,
+        body: &Bytes,
+    ) -> Result<Comment, CreateCommentError> {
+        Comment::create(
+            db,
+            CommentTarget::Draft(self.data.module),
+            &self.document,
+            user,
+            parent,
+            element,
+            body,
+        )
+    }
+
+    /// Get all revisions of this draft, oldest first.
+    pub fn get_revisions(&self, db: &Connection)
+    -> Result<Vec<DraftRevision>, DbError> {
+        DraftRevision::by_draft(db, self.data.module)
+    }
+
+    /// Save current content of this draft as a new revision.
+    pub fn save_revision(&self, db: &Connection, user: i32)
+    -> Result<DraftRevision, DbError> {
+        db.transaction(|| {
+            let revision = DraftRevision::create(
+                db, &self.data, RevisionKind::Save, Some(user))?;
+
+            audit::log_db_actor(
+                db, user, "drafts", self.data.module, "save-revision",
+                revision.id());
+
+            Ok(revision)
+        })
+    }
+
+    /// Make content of an earlier revision current again.
+    ///
+    /// Current content is first saved as a new revision, so that restoring can
+    /// itself be undone.
+    pub fn restore_revision(
+        &mut self,
+        db: &Connection,
+        user: i32,
+        revision: &DraftRevision,
+    ) -> Result<(), RestoreRevisionError> {
+        if revision.draft() != self.data.module {
+            return Err(RestoreRevisionError::NotFound);
+        }
+
+        db.transaction(|| {
+            let saved = DraftRevision::create(
+                db, &self.data, RevisionKind::Restore, Some(user))?;
+
+            self.document.replace_content(db, revision)?;
+
+            audit::log_db_actor(
+                db, user, "drafts", self.data.module, "restore-revision",
+                LogRestore {
+                    revision: revision.id(),
+                    saved: saved.id(),
+                });
+
+            Ok(())
+        })?;
+
+        TargetProcessor::process(self.document.clone());
+
+        Ok(())
+    }
+
     /// Advance this draft to the next editing step.
     pub fn advance(
         mut self,
@@ -344,11 +788,34 @@ impl Draft {
                     return Err(AdvanceDraftError::BadLink),
             };
 
+            // Verify that the draft satisfies all conditions required to move
+            // along this link.
+
+            let failed = self.check_guards(db, &link.guards)?;
+
+            if !failed.guards.is_empty() {
+                return Err(AdvanceDraftError::Guards(failed));
+            }
+
             // Check whether the target step is a final step. If so, create
             // a new version of this draft's module and delete this draft, thus
             // ending the editing process.
 
             if next.is_final(db)? {
+                // Drafts with broken links can never finish. Links guarded by
+                // LinkGuard::ResolvedLinks have already been checked.
+                if !link.guards.contains(&LinkGuard::ResolvedLinks) {
+                    let problems = links::check_document(
+                        db, self.data.module, &self.document)?
+                        .into_iter()
+                        .filter(LinkProblem::is_broken)
+                        .collect::<Vec<_>>();
+
+                    if !problems.is_empty() {
+                        return Err(AdvanceDraftError::BrokenLinks(problems));
+                    }
+                }
+
                 let members = draft_slots::table
                     .filter(draft_slots::draft.eq(self.data.module))
                     .select(draft_slots::user)
@@ -360,6 +827,8 @@ impl Draft {
                     .set(modules::document.eq(self.data.document))
                     .execute(db)?;
 
+                DraftRevision::delete_all(db, self.data.module)?;
+                Comment::move_to_module(db, self.data.module)?;
                 diesel::delete(&self.data).execute(db)?;
 
                 TargetProcessor::from_registry()
@@ -385,7 +854,11 @@ impl Draft {
                     Module::from_db((module, self.document.into_db()))));
             }
 
-            // Otherwise we are advancing normally.
+            // Otherwise we are advancing normally. Record the draft as it was
+            // at the end of this step.
+
+            DraftRevision::create(
+                db, &self.data, RevisionKind::Advance, Some(user))?;
 
             audit::log_db_actor(
                 db, user, "drafts", self.data.module, "advance", LogAdvance {
@@ -419,7 +892,11 @@ impl Draft {
             // And finally update the draft
 
             self.data = diesel::update(&self.data)
-                .set(drafts::step.eq(next.id))
+                .set((
+                    drafts::step.eq(next.id),
+                    drafts::deadline.eq(next.deadline()),
+                    drafts::deadline_notified.eq(None::<DateTime<Utc>>),
+                ))
                 .get_result(db)?;
 
             for (user, permissions) in permissions.into_iter() {
@@ -438,6 +915,54 @@ impl Draft {
             Ok(AdvanceResult::Advanced(self))
         })
     }
+
+    /// Evaluate link guards against this draft, returning those which failed.
+    fn check_guards(&self, db: &Connection, guards: &[LinkGuard])
+    -> Result<FailedGuards, AdvanceDraftError> {
+        let mut failed = FailedGuards::default();
+
+        for &guard in guards {
+            let passed = match guard {
+                LinkGuard::NoUnresolvedComments => {
+                    let unresolved = comments::table
+                        .filter(comments::draft.eq(self.data.module)
+                            .and(comments::parent.is_null())
+                            .and(comments::resolved.is_null()))
+                        .count()
+                        .get_result::<i64>(db)?;
+                    unresolved == 0
+                }
+                LinkGuard::NoPendingChanges => {
+                    let pending = draft_changes::table
+                        .filter(draft_changes::draft.eq(self.data.module)
+                            .and(draft_changes::state.eq(ChangeState::Pending)))
+                        .count()
+                        .get_result::<i64>(db)?;
+                    pending == 0
+                }
+                LinkGuard::ValidCnxml => {
+                    let content = self.document.get_file(db, "index.cnxml")
+                        .assert_exists()?
+                        .read_to_string()?;
+                    check_cnxml(&content).is_empty()
+                }
+                LinkGuard::ResolvedLinks => {
+                    failed.links = links::check_document(
+                        db, self.data.module, &self.document)?
+                        .into_iter()
+                        .filter(LinkProblem::is_broken)
+                        .collect();
+                    failed.links.is_empty()
+                }
+            };
+
+            if !passed {
+                failed.guards.push(guard);
+            }
+        }
+
+        Ok(failed)
+    }
 }
 
 impl std::ops::Deref for Draft {
@@ -476,6 +1001,87 @@ pub enum AdvanceDraftError {
     /// Could not fill a slot,
     #[fail(display = "Could not fill slot {}: {}", _0, _1)]
     FillSlot(i32, #[cause] FillSlotError),
+    /// Draft contains broken links and can't be finished.
+    #[fail(display = "Draft contains broken links")]
+    #[api(code = "draft:advance:broken-links", status = "BAD_REQUEST", data)]
+    BrokenLinks(Vec<LinkProblem>),
+    /// Could not check links in the draft.
+    #[fail(display = "Could not check links: {}", _0)]
+    CheckLinks(#[cause] #[from] CheckLinksError),
+    /// Draft doesn't satisfy some of the link's guards.
+    #[fail(display = "Draft doesn't satisfy link guards: {}", _0)]
+    #[api(code = "draft:advance:guards", status = "BAD_REQUEST", data)]
+    Guards(FailedGuards),
+    /// An operating system error.
+    #[fail(display = "System error: {}", _0)]
+    #[api(internal)]
+    System(#[cause] #[from] io::Error),
+}
+
+/// Link guards a draft failed to satisfy.
+#[derive(Debug, Default, Serialize)]
+pub struct FailedGuards {
+    pub guards: Vec<LinkGuard>,
+    /// Broken links found in the draft, if the
+    /// [`LinkGuard::ResolvedLinks`] guard failed.
+    pub links: Vec<LinkProblem>,
+}
+
+impl fmt::Display for FailedGuards {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (inx, guard) in self.guards.iter().enumerate() {
+            if inx > 0 {
+                fmt.write_str(", ")?;
+            }
+            write!(fmt, "{}", guard)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum ResolveChangesError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// Change set was not proposed to this draft.
+    #[fail(display = "No such change set")]
+    #[api(code = "draft:changes:not-found", status = "NOT_FOUND")]
+    NotFound,
+    /// Changes were already accepted or rejected.
+    #[fail(display = "Changes were already resolved")]
+    #[api(code = "draft:changes:resolved", status = "BAD_REQUEST")]
+    Resolved,
+    /// Draft was modified since changes were proposed.
+    #[fail(display = "Changes conflict with current content of the draft")]
+    #[api(code = "draft:changes:conflict", status = "CONFLICT")]
+    Conflict,
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum WriteFileError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// Current version of the file doesn't satisfy the precondition. Contains
+    /// the current version, if the file exists.
+    #[fail(display = "Precondition failed")]
+    #[api(code = "draft:file:precondition", status = "PRECONDITION_FAILED")]
+    Precondition(Option<File>),
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum RestoreRevisionError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// Revision is not a revision of this draft.
+    #[fail(display = "No such revision")]
+    #[api(code = "draft:revision:not-found", status = "NOT_FOUND")]
+    NotFound,
 }
 
 #[derive(Serialize)]
@@ -496,3 +1102,187 @@ struct LogAdvance {
     link: (i32, i32),
     next: i32,
 }
+
+#[derive(Serialize)]
+struct LogRestore {
+    /// Revision which was restored.
+    revision: i32,
+    /// Revision in which content preceding restoration was saved.
+    saved: i32,
+}
+
+#[derive(Serialize)]
+struct LogProposeChanges {
+    change: i32,
+    /// Version of `index.cnxml` against which changes were proposed.
+    base: i32,
+    /// Proposed version of `index.cnxml`.
+    file: i32,
+}
+
+#[derive(Serialize)]
+struct LogResolveChanges {
+    change: i32,
+    state: ChangeState,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db;
+    use super::*;
+    use super::super::fixtures;
+
+    /// Create a draft of a new module, with `user` occupying all its slots.
+    fn draft(db: &Connection) -> (User, Draft) {
+        let mut team = fixtures::team(db);
+        let user = fixtures::member(db, &mut team);
+        let process = fixtures::process(db, &team);
+        let module = fixtures::module(db, &team, "Draft");
+        let draft = fixtures::draft(db, &module, &process, &user);
+
+        (user, draft)
+    }
+
+    fn read(db: &Connection, draft: &Draft, name: &str) -> Option<String> {
+        draft.get_file(db, name).ok().map(|file| file.read_to_string().unwrap())
+    }
+
+    #[test]
+    #[ignore]
+    fn write_file_if_precondition() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+        let (_, draft) = draft(&db);
+
+        let first = File::from_data(&db, b"write_file_if first", None).unwrap();
+        let second = File::from_data(&db, b"write_file_if second", None)
+            .unwrap();
+
+        draft.write_file_if(&db, "media.txt", &first, |current| {
+            assert!(current.is_none());
+            true
+        }).unwrap();
+
+        match draft.write_file_if(&db, "media.txt", &second, |_| false) {
+            Err(WriteFileError::Precondition(Some(current))) =>
+                assert_eq!(current.id, first.id),
+            result => panic!("expected Precondition, got {:?}", result),
+        }
+        assert_eq!(read(&db, &draft, "media.txt").unwrap(),
+            "write_file_if first");
+
+        draft.write_file_if(&db, "media.txt", &second, |current| {
+            current.map(|file| file.id) == Some(first.id)
+        }).unwrap();
+        assert_eq!(read(&db, &draft, "media.txt").unwrap(),
+            "write_file_if second");
+    }
+
+    #[test]
+    #[ignore]
+    fn delete_file_if_precondition() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+        let (_, draft) = draft(&db);
+
+        let file = File::from_data(&db, b"delete_file_if", None).unwrap();
+        draft.write_file(&db, "media.txt", &file).unwrap();
+
+        match draft.delete_file_if(&db, "media.txt", |_| false) {
+            Err(WriteFileError::Precondition(Some(current))) =>
+                assert_eq!(current.id, file.id),
+            result => panic!("expected Precondition, got {:?}", result),
+        }
+        assert!(read(&db, &draft, "media.txt").is_some());
+
+        draft.delete_file_if(&db, "media.txt", |current| {
+            current.map(|current| current.id) == Some(file.id)
+        }).unwrap();
+        assert!(read(&db, &draft, "media.txt").is_none());
+
+        match draft.delete_file_if(&db, "media.txt", |file| file.is_some()) {
+            Err(WriteFileError::Precondition(None)) => (),
+            result => panic!("expected Precondition, got {:?}", result),
+        }
+    }
+
+    /// Propose a new version of `index.cnxml`, with `text` as its content.
+    fn propose(db: &Connection, draft: &Draft, user: &User, text: &str)
+    -> DraftChange {
+        let file = File::from_data(
+            db, fixtures::INDEX.replace("Test content", text), None).unwrap();
+
+        draft.propose_changes(db, user.id(), &file, |_| true).unwrap()
+    }
+
+    fn state(db: &Connection, change: &DraftChange) -> ChangeState {
+        DraftChange::by_id(db, change.id()).unwrap().state
+    }
+
+    #[test]
+    #[ignore]
+    fn accept_changes() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+        let (user, draft) = draft(&db);
+
+        let mut change = propose(&db, &draft, &user, "accept_changes");
+
+        assert_eq!(change.base, draft.index);
+        assert_eq!(draft.get_changes_base(&db).unwrap().id, change.file);
+
+        draft.accept_changes(&db, user.id(), &mut change).unwrap();
+
+        assert_eq!(change.state, ChangeState::Accepted);
+        assert_eq!(change.resolved_by, Some(user.id()));
+        assert_eq!(Draft::by_id(&db, draft.id()).unwrap().index, change.file);
+
+        match draft.accept_changes(&db, user.id(), &mut change) {
+            Err(ResolveChangesError::Resolved) => (),
+            result => panic!("expected Resolved, got {:?}", result),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn changes_conflicting_with_draft_cannot_be_accepted() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+        let (user, draft) = draft(&db);
+
+        let mut change = propose(&db, &draft, &user, "conflicting changes");
+        let index = File::from_data(
+            &db, fixtures::INDEX.replace("Test content", "Edited"), None)
+            .unwrap();
+        draft.write_file(&db, "index.cnxml", &index).unwrap();
+
+        match draft.accept_changes(&db, user.id(), &mut change) {
+            Err(ResolveChangesError::Conflict) => (),
+            result => panic!("expected Conflict, got {:?}", result),
+        }
+        assert_eq!(state(&db, &change), ChangeState::Pending);
+    }
+
+    #[test]
+    #[ignore]
+    fn rejecting_changes_rejects_dependent_changes() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+        let (user, draft) = draft(&db);
+
+        let first = propose(&db, &draft, &user, "first proposal");
+        let mut second = propose(&db, &draft, &user, "second proposal");
+        let third = propose(&db, &draft, &user, "third proposal");
+
+        // Consecutive proposals build upon each other.
+        assert_eq!(second.base, first.file);
+        assert_eq!(third.base, second.file);
+
+        draft.reject_changes(&db, user.id(), &mut second).unwrap();
+
+        assert_eq!(second.state, ChangeState::Rejected);
+        assert_eq!(state(&db, &first), ChangeState::Pending);
+        assert_eq!(state(&db, &third), ChangeState::Rejected);
+        assert_eq!(draft.get_changes_base(&db).unwrap().id, first.file);
+    }
+}
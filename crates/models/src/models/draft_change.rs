@@ -0,0 +1,191 @@
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error as DbError};
+use minidom::Element;
+use serde::Serialize;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        Connection,
+        models as db,
+        schema::draft_changes,
+        types::ChangeState,
+    },
+    processing::diff::{self, DiffError, ElementChange},
+};
+use super::{AssertExists, File, FindModelResult, Model};
+
+/// A set of changes to a draft's `index.cnxml`, proposed by a user with the
+/// [`SlotPermission::ProposeChanges`] permission and waiting to be accepted or
+/// rejected by a user with the [`SlotPermission::AcceptChanges`] permission.
+///
+/// [`SlotPermission::ProposeChanges`]: crate::db::types::SlotPermission::ProposeChanges
+/// [`SlotPermission::AcceptChanges`]: crate::db::types::SlotPermission::AcceptChanges
+#[derive(Debug)]
+pub struct DraftChange {
+    data: db::DraftChange,
+}
+
+/// A subset of change set's data that can safely be publicly exposed.
+#[derive(Debug, Serialize)]
+pub struct Public {
+    pub id: i32,
+    pub user: Option<i32>,
+    pub state: ChangeState,
+    pub created: DateTime<Utc>,
+    pub resolved: Option<DateTime<Utc>>,
+    pub resolved_by: Option<i32>,
+}
+
+impl Model for DraftChange {
+    const ERROR_CATEGORY: &'static str = "draft:changes";
+
+    type Id = i32;
+    type Database = db::DraftChange;
+    type Public = Public;
+    type PublicParams = ();
+
+    fn by_id(db: &Connection, id: Self::Id) -> FindModelResult<Self> {
+        draft_changes::table
+            .filter(draft_changes::id.eq(id))
+            .get_result(db)
+            .map(Self::from_db)
+            .map_err(From::from)
+    }
+
+    fn from_db(data: Self::Database) -> Self {
+        DraftChange { data }
+    }
+
+    fn into_db(self) -> Self::Database {
+        self.data
+    }
+
+    fn id(&self) -> Self::Id {
+        self.data.id
+    }
+
+    fn get_public(&self) -> Public {
+        Public {
+            id: self.data.id,
+            user: self.data.user,
+            state: self.data.state,
+            created: self.data.created,
+            resolved: self.data.resolved,
+            resolved_by: self.data.resolved_by,
+        }
+    }
+}
+
+impl DraftChange {
+    /// Get all change sets proposed to a draft, oldest first.
+    pub fn by_draft(db: &Connection, draft: Uuid)
+    -> Result<Vec<DraftChange>, DbError> {
+        draft_changes::table
+            .filter(draft_changes::draft.eq(draft))
+            .order_by(draft_changes::id.asc())
+            .get_results(db)
+            .map(|v| v.into_iter().map(Self::from_db).collect())
+    }
+
+    /// Get all pending change sets proposed to a draft, oldest first.
+    pub(super) fn pending(db: &Connection, draft: Uuid)
+    -> Result<Vec<DraftChange>, DbError> {
+        draft_changes::table
+            .filter(draft_changes::draft.eq(draft)
+                .and(draft_changes::state.eq(ChangeState::Pending)))
+            .order_by(draft_changes::id.asc())
+            .get_results(db)
+            .map(|v| v.into_iter().map(Self::from_db).collect())
+    }
+
+    /// Get all pending change sets which were proposed on top of this one,
+    /// directly or indirectly, oldest first.
+    pub(super) fn get_dependents(&self, db: &Connection)
+    -> Result<Vec<DraftChange>, DbError> {
+        let mut files = vec![self.data.file];
+        let mut dependents = Vec::new();
+
+        for change in DraftChange::pending(db, self.data.draft)? {
+            if change.data.id > self.data.id
+            && files.contains(&change.data.base) {
+                files.push(change.data.file);
+                dependents.push(change);
+            }
+        }
+
+        Ok(dependents)
+    }
+
+    /// Lock this change set until the end of current transaction, and reload
+    /// its data.
+    pub(super) fn lock(&mut self, db: &Connection) -> Result<(), DbError> {
+        self.data = draft_changes::table
+            .filter(draft_changes::id.eq(self.data.id))
+            .for_update()
+            .get_result(db)?;
+
+        Ok(())
+    }
+
+    /// Record a new change set.
+    pub(super) fn create(
+        db: &Connection,
+        draft: Uuid,
+        user: i32,
+        base: &File,
+        file: &File,
+    ) -> Result<DraftChange, DbError> {
+        diesel::insert_into(draft_changes::table)
+            .values(&db::NewDraftChange {
+                draft,
+                user: Some(user),
+                base: base.id,
+                file: file.id,
+            })
+            .get_result(db)
+            .map(Self::from_db)
+    }
+
+    /// Mark this change set as accepted or rejected.
+    pub(super) fn resolve(&mut self, db: &Connection, user: i32, state: ChangeState)
+    -> Result<(), DbError> {
+        self.data = diesel::update(&self.data)
+            .set((
+                draft_changes::state.eq(state),
+                draft_changes::resolved.eq(Some(Utc::now())),
+                draft_changes::resolved_by.eq(Some(user)),
+            ))
+            .get_result(db)?;
+
+        Ok(())
+    }
+
+    /// Get ID of the draft to which these changes were proposed.
+    pub fn draft(&self) -> Uuid {
+        self.data.draft
+    }
+
+    /// Get the version of `index.cnxml` with these changes applied.
+    pub fn get_file(&self, db: &Connection) -> Result<File, DbError> {
+        File::by_id(db, self.data.file).assert_exists()
+    }
+
+    /// Compare the version of `index.cnxml` these changes were proposed against
+    /// with the one they propose.
+    pub fn diff(&self, db: &Connection) -> Result<Vec<ElementChange>, DiffError> {
+        let old = File::by_id(db, self.data.base).assert_exists()?.read_to_string()?;
+        let new = self.get_file(db)?.read_to_string()?;
+
+        Ok(diff::diff_cnxml(&Element::from_str(&old)?, &Element::from_str(&new)?))
+    }
+}
+
+impl std::ops::Deref for DraftChange {
+    type Target = db::DraftChange;
+
+    fn deref(&self) -> &db::DraftChange {
+        &self.data
+    }
+}
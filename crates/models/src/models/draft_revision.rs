@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use diesel::{Connection as _, prelude::*, result::Error as DbError};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    db::{
+        Connection,
+        functions::duplicate_document,
+        models as db,
+        schema::{documents, draft_revisions},
+        types::RevisionKind,
+    },
+};
+use super::{Document, FindModelResult, Model};
+
+/// A snapshot of a draft's content at some point during its editing process.
+///
+/// A new revision is recorded each time a draft is advanced to another step,
+/// and each time a user explicitly saves one. Files are shared with the draft,
+/// so a revision only costs a copy of the document's file list.
+#[derive(Debug)]
+pub struct DraftRevision {
+    data: db::DraftRevision,
+    document: Document,
+}
+
+/// A subset of revision's data that can safely be publicly exposed.
+#[derive(Debug, Serialize)]
+pub struct Public {
+    pub id: i32,
+    pub kind: RevisionKind,
+    pub step: i32,
+    pub user: Option<i32>,
+    pub created: DateTime<Utc>,
+    #[serde(flatten)]
+    pub document: <Document as Model>::Public,
+}
+
+impl Model for DraftRevision {
+    const ERROR_CATEGORY: &'static str = "draft:revision";
+
+    type Id = i32;
+    type Database = (db::DraftRevision, db::Document);
+    type Public = Public;
+    type PublicParams = ();
+
+    fn by_id(db: &Connection, id: Self::Id) -> FindModelResult<Self> {
+        draft_revisions::table
+            .filter(draft_revisions::id.eq(id))
+            .inner_join(documents::table)
+            .get_result::<(db::DraftRevision, db::Document)>(db)
+            .map(Self::from_db)
+            .map_err(From::from)
+    }
+
+    fn from_db((data, document): Self::Database) -> Self {
+        DraftRevision {
+            data,
+            document: Document::from_db(document),
+        }
+    }
+
+    fn into_db(self) -> Self::Database {
+        (self.data, self.document.into_db())
+    }
+
+    fn id(&self) -> Self::Id {
+        self.data.id
+    }
+
+    fn get_public(&self) -> Public {
+        Public {
+            id: self.data.id,
+            kind: self.data.kind,
+            step: self.data.step,
+            user: self.data.user,
+            created: self.data.created,
+            document: self.document.get_public(),
+        }
+    }
+}
+
+impl DraftRevision {
+    /// Get all revisions of a draft, oldest first.
+    pub fn by_draft(db: &Connection, draft: Uuid)
+    -> Result<Vec<DraftRevision>, DbError> {
+        draft_revisions::table
+            .filter(draft_revisions::draft.eq(draft))
+            .order_by(draft_revisions::created.asc())
+            .inner_join(documents::table)
+            .get_results::<(db::DraftRevision, db::Document)>(db)
+            .map(|v| v.into_iter().map(Self::from_db).collect())
+    }
+
+    /// Record current content of a draft as a new revision.
+    pub(super) fn create(
+        db: &Connection,
+        draft: &db::Draft,
+        kind: RevisionKind,
+        user: Option<i32>,
+    ) -> Result<DraftRevision, DbError> {
+        db.transaction(|| {
+            let id = diesel::select(duplicate_document(draft.document))
+                .get_result::<i32>(db)?;
+            let document = documents::table
+                .filter(documents::id.eq(id))
+                .get_result::<db::Document>(db)?;
+
+            audit::log_db(db, "documents", document.id, "clone-from", draft.document);
+
+            let data = diesel::insert_into(draft_revisions::table)
+                .values(&db::NewDraftRevision {
+                    draft: draft.module,
+                    document: document.id,
+                    kind,
+                    step: draft.step,
+                    user,
+                })
+                .get_result::<db::DraftRevision>(db)?;
+
+            Ok(DraftRevision::from_db((data, document)))
+        })
+    }
+
+    /// Delete all revisions of a draft, together with their documents.
+    pub(super) fn delete_all(db: &Connection, draft: Uuid)
+    -> Result<(), DbError> {
+        diesel::delete(documents::table
+            .filter(documents::id.eq_any(draft_revisions::table
+                .select(draft_revisions::document)
+                .filter(draft_revisions::draft.eq(draft)))))
+            .execute(db)?;
+
+        Ok(())
+    }
+
+    /// Get ID of the draft of which this is a revision.
+    pub fn draft(&self) -> Uuid {
+        self.data.draft
+    }
+}
+
+impl std::ops::Deref for DraftRevision {
+    type Target = Document;
+
+    fn deref(&self) -> &Document {
+        &self.document
+    }
+}
@@ -0,0 +1,172 @@
+//! Rendering editing processes as diagrams.
+
+use adaptarr_error::ApiError;
+use adaptarr_macros::From;
+use failure::Fail;
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use super::structure::Process;
+
+/// Describe structure of a process in the Graphviz DOT language.
+///
+/// Steps are rendered as nodes, and links as edges labelled with link's and
+/// slot's names. The initial step is filled, final steps are drawn with
+/// a double border, and `current`, if given, is highlighted.
+pub fn render_dot(process: &Process, current: Option<i32>) -> String {
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph process {{").unwrap();
+    writeln!(dot, "    label=\"{}\";", escape(&process.name)).unwrap();
+    writeln!(dot, "    labelloc=t;").unwrap();
+    writeln!(dot, "    node [shape=box, style=rounded];").unwrap();
+
+    for (inx, step) in process.steps.iter().enumerate() {
+        let mut style = vec!["rounded"];
+        let mut attrs = Vec::new();
+
+        if inx == process.start {
+            style.push("filled");
+            attrs.push("fillcolor=lightblue".to_string());
+        }
+
+        if step.links.is_empty() {
+            attrs.push("peripheries=2".to_string());
+        }
+
+        if current == Some(step.id) {
+            style.push("bold");
+            attrs.push("color=red".to_string());
+        }
+
+        attrs.push(format!("style=\"{}\"", style.join(",")));
+
+        writeln!(dot, "    s{} [label=\"{}\", {}];",
+            inx, escape(&step.name), attrs.join(", ")).unwrap();
+    }
+
+    for (inx, step) in process.steps.iter().enumerate() {
+        for link in &step.links {
+            writeln!(dot, "    s{} -> s{} [label=\"{}\\n({})\"];",
+                inx,
+                link.to,
+                escape(&link.name),
+                escape(&process.slots[link.slot].name),
+            ).unwrap();
+        }
+    }
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
+}
+
+/// Render structure of a process as an SVG image.
+///
+/// This function requires Graphviz's `dot` program to be installed.
+pub fn render_svg(process: &Process, current: Option<i32>)
+-> Result<Vec<u8>, RenderDiagramError> {
+    let dot = render_dot(process, current);
+
+    let mut child = Command::new("dot")
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    child.stdin.take()
+        .expect("stdin should be piped")
+        .write_all(dot.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(RenderDiagramError::Graphviz(
+            String::from_utf8_lossy(&output.stderr).into_owned()));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Escape a string for use in a quoted DOT identifier.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum RenderDiagramError {
+    /// Graphviz could not be run.
+    #[fail(display = "Could not run Graphviz: {}", _0)]
+    #[api(internal)]
+    System(#[cause] #[from] io::Error),
+    /// Graphviz failed to render the diagram.
+    #[fail(display = "Graphviz failed: {}", _0)]
+    #[api(internal)]
+    Graphviz(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::structure::{Link, Slot, SlotPermission, Step, StepSlot};
+
+    #[test]
+    fn dot() {
+        let process = Process {
+            name: "Process \"A\"".into(),
+            start: 0,
+            slots: vec![
+                Slot {
+                    id: 1,
+                    name: "Author".into(),
+                    roles: Vec::new(),
+                    autofill: false,
+                },
+            ],
+            steps: vec![
+                Step {
+                    id: 3,
+                    name: "Write".into(),
+                    duration: None,
+                    slots: vec![
+                        StepSlot {
+                            slot: 0,
+                            permission: SlotPermission::Edit,
+                        },
+                    ],
+                    links: vec![
+                        Link {
+                            name: "Finish".into(),
+                            to: 1,
+                            slot: 0,
+                            guards: vec![],
+                        },
+                    ],
+                },
+                Step {
+                    id: 4,
+                    name: "Done".into(),
+                    duration: None,
+                    slots: vec![],
+                    links: vec![],
+                },
+            ],
+        };
+
+        assert_eq!(render_dot(&process, Some(3)), r#"digraph process {
+    label="Process \"A\"";
+    labelloc=t;
+    node [shape=box, style=rounded];
+    s0 [label="Write", fillcolor=lightblue, color=red, style="rounded,filled,bold"];
+    s1 [label="Done", peripheries=2, style="rounded"];
+    s0 -> s1 [label="Finish\n(Author)"];
+}
+"#);
+    }
+}
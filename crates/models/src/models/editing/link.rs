@@ -9,7 +9,12 @@ use serde::Serialize;
 
 use crate::{
     audit,
-    db::{Connection, models as db, schema::edit_process_links},
+    db::{
+        Connection,
+        models as db,
+        schema::edit_process_links,
+        types::LinkGuard,
+    },
     models::{FindModelResult, Model},
 };
 
@@ -27,6 +32,7 @@ pub struct Public {
     pub to: i32,
     pub name: String,
     pub slot: i32,
+    pub guards: Vec<LinkGuard>,
 }
 
 impl Model for Link {
@@ -60,11 +66,13 @@ impl Model for Link {
     }
 
     fn get_public(&self) -> Self::Public {
-        let db::EditProcessLink { to, ref name, slot, .. } = self.data;
+        let db::EditProcessLink { to, ref name, slot, ref guards, .. } =
+            self.data;
 
         Public {
             to, slot,
             name: name.clone(),
+            guards: guards.clone(),
         }
     }
 }
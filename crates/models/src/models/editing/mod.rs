@@ -119,6 +119,8 @@ mod slot;
 mod step;
 mod version;
 
+pub mod diagram;
+pub mod statistics;
 pub mod structure;
 
 pub use self::{
@@ -126,5 +128,5 @@ pub use self::{
     process::Process,
     slot::{FillSlotError, Slot},
     step::{Seating, Step},
-    version::{CreateVersionError, Version},
+    version::{CreateVersionError, MigrateDraftsError, Version},
 };
@@ -15,7 +15,12 @@ use crate::{
     },
     models::{FindModelResult, Model, Team, TeamResource},
 };
-use super::{CreateVersionError, Version, structure};
+use super::{
+    CreateVersionError,
+    Version,
+    statistics::{self, Statistics},
+    structure::{self, Validation},
+};
 
 /// An editing process.
 ///
@@ -102,7 +107,7 @@ impl Process {
 
     /// Create a new editing process.
     pub fn create(db: &Connection, team: &Team, structure: &structure::Process)
-    -> Result<Version, CreateVersionError> {
+    -> Result<(Version, Validation), CreateVersionError> {
         db.transaction(|| {
             let process = diesel::insert_into(edit_processes::table)
                 .values(&db::NewEditProcess {
@@ -154,6 +159,13 @@ impl Process {
             .map(|version| Version::from_db((self.data.clone(), version)))
     }
 
+    /// Compute statistics of this process.
+    pub fn get_statistics(&self, db: &Connection)
+    -> Result<Statistics, DbError> {
+        statistics::compute(db, std::slice::from_ref(self))
+            .map(|mut stats| stats.remove(0))
+    }
+
     /// Set process's name.
     pub fn set_name(&mut self, db: &Connection, name: &str)
     -> Result<(), DbError> {
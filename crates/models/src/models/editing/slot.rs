@@ -36,7 +36,7 @@ use crate::{
 };
 
 /// Abstract representation of roles a user can take during an editing process.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Slot {
     data: db::EditProcessSlot,
 }
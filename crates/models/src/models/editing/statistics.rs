@@ -0,0 +1,431 @@
+//! Statistics of editing processes.
+//!
+//! Statistics are computed from the audit log, which records when drafts are
+//! created, advanced, migrated to new versions of their processes, and
+//! finished, and who was assigned to their slots. Time a draft spent in a step
+//! is measured from the moment it entered that step until it was moved out of
+//! it, and is attributed both to the step and to users who, at the moment
+//! the draft was moved, held its slots with permissions in that step.
+//!
+//! When a draft is migrated to a new version of its process, time it spent in
+//! a step of the old version is counted for that step, and time in the new
+//! version is counted from the migration onwards.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use diesel::{expression::dsl::any, prelude::*, result::Error as DbError};
+use log::warn;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{collections::{BTreeMap, HashMap}, io::{self, Write}};
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        Connection,
+        models as db,
+        schema::{
+            audit_log,
+            drafts,
+            edit_process_step_slots,
+            edit_process_steps,
+            edit_process_versions,
+            modules,
+        },
+    },
+    models::{Model, TeamResource},
+};
+use super::Process;
+
+/// Statistics of a single editing process.
+#[derive(Debug, Serialize)]
+pub struct Statistics {
+    /// ID of the process.
+    pub process: i32,
+    /// Statistics of each step, in all versions of the process.
+    pub steps: Vec<StepStatistics>,
+    /// Time drafts spent in each step, attributed to users holding their slots
+    /// in it.
+    pub users: Vec<UserStatistics>,
+    /// Number of drafts which finished the process, per week. Weeks in which
+    /// no draft finished are omitted.
+    pub completed: Vec<WeekStatistics>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StepStatistics {
+    pub step: i32,
+    pub version: i32,
+    pub name: String,
+    /// Number of drafts currently in this step.
+    pub drafts: usize,
+    #[serde(flatten)]
+    pub time: TimeStatistics,
+}
+
+/// Time drafts spent in a step, attributed to a user holding one of their slots
+/// with permissions in that step. See [module's documentation](self) for
+/// details.
+#[derive(Debug, Serialize)]
+pub struct UserStatistics {
+    pub user: i32,
+    pub step: i32,
+    #[serde(flatten)]
+    pub time: TimeStatistics,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeekStatistics {
+    /// First day (Monday) of the week.
+    pub week: NaiveDate,
+    pub completed: usize,
+}
+
+/// Distribution of time drafts spent in a step, in seconds.
+#[derive(Debug, Default, Eq, PartialEq, Serialize)]
+pub struct TimeStatistics {
+    /// Number of times a draft left the step.
+    pub samples: usize,
+    pub median: Option<i64>,
+    /// 90th percentile.
+    pub p90: Option<i64>,
+}
+
+/// Compute statistics for a list of processes.
+///
+/// *Warning*: this function scans the entire audit log of drafts in teams
+/// owning the processes.
+pub fn compute(db: &Connection, processes: &[Process])
+-> Result<Vec<Statistics>, DbError> {
+    let ids = processes.iter().map(Model::id).collect::<Vec<_>>();
+    let teams = processes.iter().map(TeamResource::team_id).collect::<Vec<_>>();
+
+    let versions = edit_process_versions::table
+        .filter(edit_process_versions::process.eq(any(&ids)))
+        .get_results::<db::EditProcessVersion>(db)?
+        .into_iter()
+        .map(|version| (version.id, version.process))
+        .collect::<HashMap<_, _>>();
+
+    let version_ids = versions.keys().copied().collect::<Vec<_>>();
+
+    let steps = edit_process_steps::table
+        .filter(edit_process_steps::process.eq(any(&version_ids)))
+        .order_by(edit_process_steps::id.asc())
+        .get_results::<db::EditProcessStep>(db)?;
+
+    let step_process = steps.iter()
+        .map(|step| (step.id, versions[&step.process]))
+        .collect::<HashMap<_, _>>();
+
+    let step_ids = steps.iter().map(|step| step.id).collect::<Vec<_>>();
+
+    // Slots which have any permissions in each step.
+    let mut step_slots = HashMap::<i32, Vec<i32>>::new();
+
+    for (step, slot) in edit_process_step_slots::table
+        .filter(edit_process_step_slots::step.eq(any(&step_ids)))
+        .select((edit_process_step_slots::step, edit_process_step_slots::slot))
+        .distinct()
+        .get_results::<(i32, i32)>(db)?
+    {
+        step_slots.entry(step).or_default().push(slot);
+    }
+
+    let mut in_step = HashMap::<i32, usize>::new();
+
+    for step in drafts::table
+        .filter(drafts::step.eq(any(&step_ids)))
+        .select(drafts::step)
+        .get_results::<i32>(db)?
+    {
+        *in_step.entry(step).or_default() += 1;
+    }
+
+    // Drafts share IDs with their modules, and belong to the same team.
+    let entries = audit_log::table
+        .filter(audit_log::context.eq("drafts")
+            .and(audit_log::kind.eq_any(vec![
+                "create", "fill-slot", "advance", "migrate", "finish"]))
+            .and(audit_log::context_uuid.eq_any(modules::table
+                .filter(modules::team.eq(any(&teams)))
+                .select(modules::id.nullable()))))
+        .order_by(audit_log::id.asc())
+        .get_results::<db::AuditLog>(db)?;
+
+    // Time at which each draft entered its current step.
+    let mut entered = HashMap::<Uuid, DateTime<Utc>>::new();
+    // Users occupying each draft's slots.
+    let mut seats = HashMap::<Uuid, HashMap<i32, i32>>::new();
+    let mut step_times = HashMap::<i32, Vec<i64>>::new();
+    let mut user_times = BTreeMap::<(i32, i32), Vec<i64>>::new();
+    let mut completed = HashMap::<i32, BTreeMap<NaiveDate, usize>>::new();
+
+    for entry in entries {
+        let draft = match entry.context_uuid {
+            Some(draft) => draft,
+            None => continue,
+        };
+
+        let (from, migrated) = match entry.kind.as_str() {
+            "create" => {
+                entered.insert(draft, entry.timestamp);
+                seats.remove(&draft);
+                continue;
+            }
+            "fill-slot" => {
+                if let Some(LogFill { slot, user }) = parse(&entry) {
+                    seats.entry(draft).or_default().insert(slot, user);
+                }
+                continue;
+            }
+            "migrate" => match parse(&entry) {
+                Some(LogMigrate { from, slots }) => (from, Some(slots)),
+                None => continue,
+            },
+            _ => match parse(&entry) {
+                Some(LogTransition { link: (from, _) }) => (from, None),
+                None => continue,
+            },
+        };
+
+        let since = if entry.kind == "finish" {
+            entered.remove(&draft)
+        } else {
+            entered.insert(draft, entry.timestamp)
+        };
+
+        let mut holders = step_slots.get(&from)
+            .into_iter()
+            .flatten()
+            .filter_map(|slot| seats.get(&draft)?.get(slot).copied())
+            .collect::<Vec<_>>();
+        holders.sort_unstable();
+        holders.dedup();
+
+        // Slots not mapped during a migration are vacated.
+        if entry.kind == "finish" {
+            seats.remove(&draft);
+        } else if let Some(mapping) = migrated {
+            if let Some(seats) = seats.get_mut(&draft) {
+                *seats = seats.drain()
+                    .filter_map(|(slot, user)| {
+                        mapping.get(&slot).map(|&slot| (slot, user))
+                    })
+                    .collect();
+            }
+        }
+
+        let process = match step_process.get(&from) {
+            Some(&process) => process,
+            None => continue,
+        };
+
+        if let Some(since) = since {
+            let time = (entry.timestamp - since).num_seconds();
+
+            step_times.entry(from).or_default().push(time);
+
+            for user in holders {
+                user_times.entry((from, user)).or_default().push(time);
+            }
+        }
+
+        if entry.kind == "finish" {
+            *completed.entry(process)
+                .or_default()
+                .entry(week_of(entry.timestamp))
+                .or_default() += 1;
+        }
+    }
+
+    Ok(processes.iter()
+        .map(|process| {
+            let id = process.id();
+
+            let steps = steps.iter()
+                .filter(|step| versions[&step.process] == id)
+                .map(|step| StepStatistics {
+                    step: step.id,
+                    version: step.process,
+                    name: step.name.clone(),
+                    drafts: in_step.get(&step.id).copied().unwrap_or(0),
+                    time: summarize(
+                        step_times.remove(&step.id).unwrap_or_default()),
+                })
+                .collect();
+
+            let users = user_times.iter()
+                .filter(|((step, _), _)| step_process[step] == id)
+                .map(|(&(step, user), times)| UserStatistics {
+                    user,
+                    step,
+                    time: summarize(times.clone()),
+                })
+                .collect();
+
+            let completed = completed.remove(&id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(week, completed)| WeekStatistics { week, completed })
+                .collect();
+
+            Statistics { process: id, steps, users, completed }
+        })
+        .collect())
+}
+
+/// Write statistics as CSV.
+///
+/// Each row contains a single metric (`drafts`, `samples`, `median`, `p90`, or
+/// `completed`), together with the process, step, user, and week it describes.
+/// Columns which don't apply to a metric are left empty.
+pub fn write_csv<W: Write>(mut out: W, statistics: &[Statistics])
+-> io::Result<()> {
+    writeln!(out, "process,version,step,name,user,week,metric,value")?;
+
+    for stats in statistics {
+        let names = stats.steps.iter()
+            .map(|step| (step.step, step))
+            .collect::<HashMap<_, _>>();
+
+        for step in &stats.steps {
+            let prefix = [
+                stats.process.to_string(),
+                step.version.to_string(),
+                step.step.to_string(),
+                escape(&step.name),
+                String::new(),
+                String::new(),
+            ];
+
+            writeln!(out, "{},drafts,{}", prefix.join(","), step.drafts)?;
+            write_time(&mut out, &prefix, &step.time)?;
+        }
+
+        for user in &stats.users {
+            let step = names[&user.step];
+            let prefix = [
+                stats.process.to_string(),
+                step.version.to_string(),
+                step.step.to_string(),
+                escape(&step.name),
+                user.user.to_string(),
+                String::new(),
+            ];
+
+            write_time(&mut out, &prefix, &user.time)?;
+        }
+
+        for week in &stats.completed {
+            writeln!(out, "{},,,,,{},completed,{}",
+                stats.process, week.week, week.completed)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_time<W: Write>(out: &mut W, prefix: &[String], time: &TimeStatistics)
+-> io::Result<()> {
+    let prefix = prefix.join(",");
+    let or_empty = |value: Option<i64>| value.map_or_else(String::new,
+        |value| value.to_string());
+
+    writeln!(out, "{},samples,{}", prefix, time.samples)?;
+    writeln!(out, "{},median,{}", prefix, or_empty(time.median))?;
+    writeln!(out, "{},p90,{}", prefix, or_empty(time.p90))
+}
+
+/// Quote a CSV field if necessary.
+fn escape(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn summarize(mut times: Vec<i64>) -> TimeStatistics {
+    times.sort_unstable();
+
+    TimeStatistics {
+        samples: times.len(),
+        median: percentile(&times, 50),
+        p90: percentile(&times, 90),
+    }
+}
+
+/// Compute nearest-rank percentile of a sorted list.
+fn percentile(sorted: &[i64], percent: usize) -> Option<i64> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = (percent * sorted.len() + 99) / 100;
+    Some(sorted[rank.max(1) - 1])
+}
+
+/// Get the first day (Monday) of the week containing a date.
+fn week_of(date: DateTime<Utc>) -> NaiveDate {
+    let date = date.naive_utc().date();
+    date - Duration::days(date.weekday().num_days_from_monday().into())
+}
+
+/// Deserialize data of an audit log entry, logging a warning if it's invalid.
+fn parse<T: DeserializeOwned>(entry: &db::AuditLog) -> Option<T> {
+    match rmps::from_slice(&entry.data) {
+        Ok(data) => Some(data),
+        Err(err) => {
+            warn!("Invalid data in audit log entry {}: {}", entry.id, err);
+            None
+        }
+    }
+}
+
+/// Subset of data logged for `advance` and `finish` events on drafts.
+#[derive(Deserialize)]
+struct LogTransition {
+    link: (i32, i32),
+}
+
+/// Data logged for `fill-slot` events on drafts.
+#[derive(Deserialize)]
+struct LogFill {
+    slot: i32,
+    user: i32,
+}
+
+/// Subset of data logged for `migrate` events on drafts.
+#[derive(Deserialize)]
+struct LogMigrate {
+    from: i32,
+    slots: HashMap<i32, i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        assert_eq!(summarize(vec![]), TimeStatistics::default());
+
+        assert_eq!(summarize(vec![5]), TimeStatistics {
+            samples: 1,
+            median: Some(5),
+            p90: Some(5),
+        });
+
+        assert_eq!(summarize((1..=10).rev().collect()), TimeStatistics {
+            samples: 10,
+            median: Some(5),
+            p90: Some(9),
+        });
+    }
+
+    #[test]
+    fn csv_escaping() {
+        assert_eq!(escape("Review"), "Review");
+        assert_eq!(escape("Review, final"), "\"Review, final\"");
+        assert_eq!(escape("The \"last\" step"), "\"The \"\"last\"\" step\"");
+    }
+}
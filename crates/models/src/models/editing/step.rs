@@ -1,5 +1,6 @@
 use adaptarr_error::ApiError;
 use adaptarr_util::and_tuple;
+use chrono::{DateTime, Duration, Utc};
 use diesel::{
     Connection as _,
     prelude::*,
@@ -50,6 +51,7 @@ pub struct Public {
     pub id: i32,
     pub process: [i32; 2],
     pub name: String,
+    pub duration: Option<i32>,
     pub slots: Vec<StepSlot>,
     pub links: Vec<<Link as Model>::Public>,
 }
@@ -96,7 +98,9 @@ impl Model for Step {
 
     fn get_public_full(&self, db: &Connection, &(draft, slots): &Self::PublicParams)
     -> Result<Public, DbError> {
-        let db::EditProcessStep { id, process: version, ref name, .. } = self.data;
+        let db::EditProcessStep {
+            id, process: version, ref name, duration,
+        } = self.data;
 
         let seating = match draft {
             Some(draft) => self.get_slot_seating(db, draft)?
@@ -131,6 +135,7 @@ impl Model for Step {
             id,
             process: [process, version],
             name: name.clone(),
+            duration,
             slots: seating,
             links,
         })
@@ -147,6 +152,13 @@ impl Step {
             .map(|c| c == 0)
     }
 
+    /// Compute the deadline for a draft entering this step now, or `None` if
+    /// this step has no default duration.
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        self.data.duration
+            .map(|days| Utc::now() + Duration::days(i64::from(days)))
+    }
+
     /// Get the process this step is a part of.
     pub fn get_process(&self, db: &Connection) -> Result<Version, DbError> {
         let process = edit_process_versions::table
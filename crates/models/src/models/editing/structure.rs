@@ -1,8 +1,8 @@
 use failure::Fail;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::{HashMap, HashSet}, fmt};
 
-pub use crate::db::types::SlotPermission;
+pub use crate::db::types::{LinkGuard, SlotPermission};
 
 use self::ValidateStructureError::*;
 
@@ -36,6 +36,9 @@ pub struct Step {
     #[serde(default)]
     pub id: i32,
     pub name: String,
+    /// Default number of days a draft should spend in this step.
+    #[serde(default)]
+    pub duration: Option<i32>,
     #[serde(default)]
     pub slots: Vec<StepSlot>,
     #[serde(default)]
@@ -53,11 +56,53 @@ pub struct Link {
     pub name: String,
     pub to: usize,
     pub slot: usize,
+    /// Conditions a draft must satisfy before it can be moved along this link.
+    #[serde(default)]
+    pub guards: Vec<LinkGuard>,
 }
 
 /// Result of validation.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Default, Eq, PartialEq, Serialize)]
 pub struct Validation {
+    /// Problems which don't make the process invalid, but which are most
+    /// likely mistakes.
+    pub warnings: Vec<Warning>,
+}
+
+/// A problem found in a valid process.
+///
+/// IDs of slots and steps are indices into [`Process::slots`] and
+/// [`Process::steps`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "warning", rename_all = "kebab-case")]
+pub enum Warning {
+    /// Slot is not referenced by any step or link.
+    UnusedSlot {
+        slot: usize,
+    },
+    /// Final step grants permissions to slots. Drafts never stay in a final
+    /// step, so those permissions are never in effect.
+    FinalStepSlots {
+        step: usize,
+    },
+    /// Final step has a duration. Drafts never stay in a final step, so they
+    /// can't be given a deadline in it.
+    FinalStepDuration {
+        step: usize,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::UnusedSlot { slot } =>
+                write!(f, "Slot {} is not used in any step", slot),
+            Warning::FinalStepSlots { step } =>
+                write!(f, "Final step {} grants permissions to slots", step),
+            Warning::FinalStepDuration { step } =>
+                write!(f, "Final step {} has a duration", step),
+        }
+    }
 }
 
 pub fn validate(process: &Process) -> Result<Validation, ValidateStructureError> {
@@ -94,6 +139,10 @@ pub fn validate(process: &Process) -> Result<Validation, ValidateStructureError>
 
         steps.insert(&step.name, stepid);
 
+        if step.duration.map_or(false, |days| days <= 0) {
+            return Err(ValidateStructureError::InvalidStepDuration(stepid));
+        }
+
         let mut links = HashMap::new();
 
         for (linkid, link) in step.links.iter().enumerate() {
@@ -336,7 +385,89 @@ pub fn validate(process: &Process) -> Result<Validation, ValidateStructureError>
         }
     }
 
-    Ok(Validation {})
+    // Look for problems which don't make the process invalid.
+
+    let mut validation = Validation::default();
+
+    for slotid in 0..process.slots.len() {
+        let used = process.steps.iter()
+            .any(|step| step.slots.iter().any(|slot| slot.slot == slotid));
+
+        if !used {
+            validation.warnings.push(Warning::UnusedSlot { slot: slotid });
+        }
+    }
+
+    for (stepid, step) in process.steps.iter().enumerate() {
+        if !step.links.is_empty() {
+            continue;
+        }
+
+        if !step.slots.is_empty() {
+            validation.warnings.push(Warning::FinalStepSlots { step: stepid });
+        }
+
+        if step.duration.is_some() {
+            validation.warnings.push(
+                Warning::FinalStepDuration { step: stepid });
+        }
+    }
+
+    Ok(validation)
+}
+
+/// Validate a mapping used to migrate drafts between two versions of
+/// a process.
+///
+/// `steps` and `slots` map IDs of steps and slots in `from` to IDs of steps and
+/// slots in `to`. All steps of `from` in which a draft can be must be mapped,
+/// and must be mapped to steps in which a draft can be. Slots which are not
+/// mapped will be vacated.
+pub fn validate_migration(
+    from: &Process,
+    to: &Process,
+    steps: &[(i32, i32)],
+    slots: &[(i32, i32)],
+) -> Result<(), ValidateMigrationError> {
+    let mut mapped = HashSet::new();
+
+    for &(old, new) in steps {
+        let target = to.steps.iter().find(|step| step.id == new);
+
+        if !from.steps.iter().any(|step| step.id == old)
+        || target.is_none()
+        || !mapped.insert(old) {
+            return Err(ValidateMigrationError::InvalidStepMapping(old, new));
+        }
+
+        if target.map_or(false, |step| step.links.is_empty()) {
+            return Err(ValidateMigrationError::FinalStep(new));
+        }
+    }
+
+    // Drafts are never in a final step, those need not be mapped.
+    for step in &from.steps {
+        if !step.links.is_empty() && !mapped.contains(&step.id) {
+            return Err(ValidateMigrationError::UnmappedStep(step.id));
+        }
+    }
+
+    let mut mapped = HashSet::new();
+    let mut targets = HashSet::new();
+
+    for &(old, new) in slots {
+        if !from.slots.iter().any(|slot| slot.id == old)
+        || !to.slots.iter().any(|slot| slot.id == new)
+        || !mapped.insert(old) {
+            return Err(ValidateMigrationError::InvalidSlotMapping(old, new));
+        }
+
+        if !targets.insert(new) {
+            return Err(ValidateMigrationError::DuplicateSlot(new));
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Eq, Fail, PartialEq)]
@@ -353,6 +484,9 @@ pub enum ValidateStructureError {
     /// Name of a link is empty.
     #[fail(display = "Step {}'s link {}'s name cannot be empty", _0, _1)]
     EmptyLinkName(usize, usize),
+    /// Step's duration is not a positive number of days.
+    #[fail(display = "Step {}'s duration must be a positive number of days", _0)]
+    InvalidStepDuration(usize),
     /// Description names start step with ID greater than total number of steps.
     #[fail(display = "Start step's ID {} exceeds total number of steps {}", _0, _1)]
     InvalidStartStep(usize, usize),
@@ -547,6 +681,27 @@ pub enum ValidateStructureError {
     ChangesAcceptNotOnlyPropose(usize),
 }
 
+#[derive(Debug, Eq, Fail, PartialEq)]
+pub enum ValidateMigrationError {
+    /// Step in which there may be drafts is not mapped to any new step.
+    #[fail(display = "Step {} is not mapped to any step", _0)]
+    UnmappedStep(i32),
+    /// Step mapping names a step which is not part of the respective version,
+    /// or maps the same step twice.
+    #[fail(display = "Invalid mapping from step {} to step {}", _0, _1)]
+    InvalidStepMapping(i32, i32),
+    /// Step is mapped to a final step.
+    #[fail(display = "Step {} is a final step", _0)]
+    FinalStep(i32),
+    /// Slot mapping names a slot which is not part of the respective version,
+    /// or maps the same slot twice.
+    #[fail(display = "Invalid mapping from slot {} to slot {}", _0, _1)]
+    InvalidSlotMapping(i32, i32),
+    /// Two slots are mapped to the same slot.
+    #[fail(display = "Multiple slots are mapped to slot {}", _0)]
+    DuplicateSlot(i32),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -568,6 +723,7 @@ mod tests {
                 Step {
                     id: 0,
                     name: "Start".into(),
+                    duration: None,
                     slots: vec![
                         StepSlot {
                             slot: 0,
@@ -579,19 +735,45 @@ mod tests {
                             name: "Link".into(),
                             slot: 0,
                             to: 1,
+                            guards: vec![],
                         },
                     ],
                 },
                 Step {
                     id: 0,
                     name: "End".into(),
+                    duration: None,
                     slots: vec![],
                     links: vec![],
                 },
             ],
         };
 
-        assert_eq!(validate(&good), Ok(Validation {}));
+        assert_eq!(validate(&good), Ok(Validation::default()));
+
+        let mut test = good.clone();
+        test.slots.push(Slot {
+            id: 1,
+            name: "Unused".into(),
+            roles: Vec::new(),
+            autofill: false,
+        });
+        test.steps[1].duration = Some(3);
+        test.steps[1].slots.push(StepSlot {
+            slot: 0,
+            permission: SlotPermission::View,
+        });
+        assert_eq!(validate(&test), Ok(Validation {
+            warnings: vec![
+                Warning::UnusedSlot { slot: 1 },
+                Warning::FinalStepSlots { step: 1 },
+                Warning::FinalStepDuration { step: 1 },
+            ],
+        }));
+
+        let mut test = good.clone();
+        test.steps[0].duration = Some(0);
+        assert_eq!(validate(&test), Err(ValidateStructureError::InvalidStepDuration(0)));
 
         let mut test = good.clone();
         test.slots[0].name = "".into();
@@ -625,6 +807,7 @@ mod tests {
             name: "Link".into(),
             slot: 0,
             to: 1,
+            guards: vec![],
         });
         assert_eq!(
             validate(&test),
@@ -677,6 +860,7 @@ mod tests {
             name: "Another link".into(),
             slot: 0,
             to: 0,
+            guards: vec![],
         });
         assert_eq!(validate(&test), Err(ValidateStructureError::LoopedLink {
             step: 0,
@@ -688,6 +872,7 @@ mod tests {
             name: "Another link".into(),
             slot: 0,
             to: 3,
+            guards: vec![],
         });
         assert_eq!(validate(&test), Err(ValidateStructureError::InvalidLinkTarget {
             step: 0,
@@ -744,10 +929,12 @@ mod tests {
             name: "To propose".into(),
             slot: 0,
             to: 2,
+            guards: vec![],
         });
         test.steps.push(Step {
             id: 2,
             name: "Propose changes".into(),
+            duration: None,
             slots: vec![
                 StepSlot {
                     slot: 0,
@@ -759,6 +946,7 @@ mod tests {
                     name: "Link".into(),
                     slot: 0,
                     to: 1,
+                    guards: vec![],
                 },
             ],
         });
@@ -768,10 +956,12 @@ mod tests {
             name: "To accept".into(),
             slot: 0,
             to: 3,
+            guards: vec![],
         });
         test.steps.push(Step {
             id: 3,
             name: "Accept changes".into(),
+            duration: None,
             slots: vec![
                 StepSlot {
                     slot: 0,
@@ -783,15 +973,17 @@ mod tests {
                     name: "Link".into(),
                     slot: 0,
                     to: 1,
+                    guards: vec![],
                 },
             ],
         });
-        assert_eq!(validate(&test), Ok(Validation {}));
+        assert_eq!(validate(&test), Ok(Validation::default()));
 
         test.steps[0].links.push(Link {
             name: "To accept".into(),
             slot: 0,
             to: 3,
+            guards: vec![],
         });
         assert_eq!(validate(&test), Err(ValidateStructureError::ChangesAcceptNotOnlyPropose(3)));
 
@@ -799,6 +991,7 @@ mod tests {
         test.steps.push(Step {
             id: 2,
             name: "Isolated A".into(),
+            duration: None,
             slots: vec![
                 StepSlot {
                     slot: 0,
@@ -810,12 +1003,14 @@ mod tests {
                     name: "Link".into(),
                     slot: 0,
                     to: 3,
+                    guards: vec![],
                 },
             ],
         });
         test.steps.push(Step {
             id: 3,
             name: "Isolated B".into(),
+            duration: None,
             slots: vec![
                 StepSlot {
                     slot: 0,
@@ -827,6 +1022,7 @@ mod tests {
                     name: "Link".into(),
                     slot: 0,
                     to: 2,
+                    guards: vec![],
                 },
             ],
         });
@@ -834,7 +1030,78 @@ mod tests {
             name: "Another link".into(),
             slot: 0,
             to: 2,
+            guards: vec![],
         });
         assert_eq!(validate(&test), Err(ValidateStructureError::IsolatedStep(2)));
     }
+
+    #[test]
+    fn migration_validation() {
+        let process = |steps: [i32; 2], slot: i32| Process {
+            name: "Process".into(),
+            start: 0,
+            slots: vec![
+                Slot {
+                    id: slot,
+                    name: "Slot".into(),
+                    roles: Vec::new(),
+                    autofill: false,
+                },
+            ],
+            steps: vec![
+                Step {
+                    id: steps[0],
+                    name: "Start".into(),
+                    duration: None,
+                    slots: vec![
+                        StepSlot {
+                            slot: 0,
+                            permission: SlotPermission::Edit,
+                        },
+                    ],
+                    links: vec![
+                        Link {
+                            name: "Link".into(),
+                            slot: 0,
+                            to: 1,
+                            guards: vec![],
+                        },
+                    ],
+                },
+                Step {
+                    id: steps[1],
+                    name: "End".into(),
+                    duration: None,
+                    slots: vec![],
+                    links: vec![],
+                },
+            ],
+        };
+
+        let from = process([1, 2], 1);
+        let to = process([3, 4], 2);
+
+        assert_eq!(validate_migration(&from, &to, &[(1, 3)], &[(1, 2)]), Ok(()));
+        assert_eq!(validate_migration(&from, &to, &[(1, 3)], &[]), Ok(()));
+
+        assert_eq!(
+            validate_migration(&from, &to, &[], &[]),
+            Err(ValidateMigrationError::UnmappedStep(1)));
+
+        assert_eq!(
+            validate_migration(&from, &to, &[(1, 1)], &[]),
+            Err(ValidateMigrationError::InvalidStepMapping(1, 1)));
+
+        assert_eq!(
+            validate_migration(&from, &to, &[(1, 3), (1, 3)], &[]),
+            Err(ValidateMigrationError::InvalidStepMapping(1, 3)));
+
+        assert_eq!(
+            validate_migration(&from, &to, &[(1, 4)], &[]),
+            Err(ValidateMigrationError::FinalStep(4)));
+
+        assert_eq!(
+            validate_migration(&from, &to, &[(1, 3)], &[(2, 2)]),
+            Err(ValidateMigrationError::InvalidSlotMapping(2, 2)));
+    }
 }
@@ -7,7 +7,9 @@ use diesel::{
     result::{Error as DbError, DatabaseErrorKind},
 };
 use failure::Fail;
+use itertools::Itertools;
 use serde::Serialize;
+use std::collections::HashMap;
 
 use crate::{
     audit,
@@ -15,6 +17,8 @@ use crate::{
         Connection,
         models as db,
         schema::{
+            draft_slots,
+            drafts,
             edit_process_links,
             edit_process_slot_roles,
             edit_process_slots,
@@ -24,9 +28,10 @@ use crate::{
             edit_processes,
         },
     },
+    events::{DraftAdvanced, EventManager, SlotVacated},
     models::{FindModelResult, Model},
 };
-use super::{Process, Step, Slot, structure};
+use super::{Process, Step, Slot, structure::{self, Validation}};
 
 /// Particular revision of an editing [`Process`][Process]
 ///
@@ -94,12 +99,15 @@ impl Model for Version {
 
 impl Version {
     /// Create a new version of an editing process.
+    ///
+    /// Returns the new version together with the result of validating
+    /// `structure`, which may contain warnings.
     pub fn create(
         db: &Connection,
         process: Process,
         structure: &structure::Process,
-    ) -> Result<Version, CreateVersionError> {
-        let _ = structure::validate(structure)?;
+    ) -> Result<(Version, Validation), CreateVersionError> {
+        let validation = structure::validate(structure)?;
         let process = process.into_db();
 
         db.transaction(|| {
@@ -142,6 +150,7 @@ impl Version {
                         .values(&db::NewEditProcessStep {
                             name: &step.name,
                             process: version.id,
+                            duration: step.duration,
                         })
                         .get_result::<db::EditProcessStep>(db)
                 })
@@ -165,6 +174,7 @@ impl Version {
                             from: dbstep.id,
                             to: steps[link.to].id,
                             slot: slots[link.slot].id,
+                            guards: &link.guards,
                         })
                         .execute(db)?;
                 }
@@ -185,7 +195,7 @@ impl Version {
             audit::log_db(
                 db, "edit-process", process.id, "create-version", version.id);
 
-            Ok(Version::from_db((process, version)))
+            Ok((Version::from_db((process, version)), validation))
         })
     }
 
@@ -300,6 +310,7 @@ impl Version {
                                 name: link.name,
                                 to,
                                 slot,
+                                guards: link.guards,
                             }
                         })
                         .collect();
@@ -307,6 +318,7 @@ impl Version {
                     Ok(structure::Step {
                         id: step.id,
                         name: step.name.clone(),
+                        duration: step.duration,
                         slots,
                         links,
                     })
@@ -321,6 +333,108 @@ impl Version {
             })
         })
     }
+
+    /// Move all drafts following another version of this process to this
+    /// version.
+    ///
+    /// `steps` and `slots` map IDs of steps and slots in `from` to IDs of steps
+    /// and slots in this version (see [`structure::validate_migration`]).
+    /// Users occupying slots which are not mapped are removed from them. Users
+    /// remaining in a draft are notified of its new step with
+    /// a [`DraftAdvanced`] event.
+    ///
+    /// Returns number of migrated drafts.
+    pub fn migrate_drafts(
+        &self,
+        db: &Connection,
+        from: &Version,
+        steps: &[(i32, i32)],
+        slots: &[(i32, i32)],
+    ) -> Result<usize, MigrateDraftsError> {
+        if from.data.id == self.data.id {
+            return Err(MigrateDraftsError::SameVersion);
+        }
+
+        db.transaction(|| {
+            structure::validate_migration(
+                &from.get_structure(db)?,
+                &self.get_structure(db)?,
+                steps,
+                slots,
+            )?;
+
+            let steps = steps.iter().copied().collect::<HashMap<_, _>>();
+            let slots = slots.iter().copied().collect::<HashMap<_, _>>();
+
+            let drafts = drafts::table
+                .filter(drafts::step.eq_any(steps.keys().copied()))
+                .get_results::<db::Draft>(db)?;
+
+            for draft in &drafts {
+                let step = steps[&draft.step];
+
+                diesel::update(draft)
+                    .set(drafts::step.eq(step))
+                    .execute(db)?;
+
+                let seating = draft_slots::table
+                    .filter(draft_slots::draft.eq(draft.module))
+                    .get_results::<db::DraftSlot>(db)?;
+
+                for seat in seating {
+                    match slots.get(&seat.slot) {
+                        Some(&slot) => {
+                            diesel::update(&seat)
+                                .set(draft_slots::slot.eq(slot))
+                                .execute(db)?;
+                        }
+                        None => {
+                            diesel::delete(&seat).execute(db)?;
+
+                            EventManager::notify(seat.user, SlotVacated {
+                                slot: seat.slot,
+                                module: draft.module,
+                                document: draft.document,
+                            });
+                        }
+                    }
+                }
+
+                audit::log_db(
+                    db, "drafts", draft.module, "migrate", LogMigrate {
+                        version: self.data.id,
+                        from: draft.step,
+                        to: step,
+                        slots: &slots,
+                    });
+
+                let permissions = draft_slots::table
+                    .inner_join(edit_process_step_slots::table
+                        .on(draft_slots::slot.eq(edit_process_step_slots::slot)))
+                    .filter(draft_slots::draft.eq(draft.module)
+                        .and(edit_process_step_slots::step.eq(step)))
+                    .order_by(draft_slots::user)
+                    .get_results::<(db::DraftSlot, db::EditProcessStepSlot)>(db)?
+                    .into_iter()
+                    .group_by(|(slot, _)| slot.user);
+
+                for (user, permissions) in permissions.into_iter() {
+                    let permissions = permissions
+                        .map(|(_, p)| p.permission)
+                        .collect();
+
+                    EventManager::notify(user, DraftAdvanced {
+                        module: draft.module,
+                        document: draft.document,
+                        step,
+                        permissions,
+                    });
+                }
+            }
+
+            Ok(drafts.len())
+        })
+    }
 }
 
 impl std::ops::Deref for Version {
@@ -360,3 +474,98 @@ impl From<DbError> for CreateVersionError {
         }
     }
 }
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum MigrateDraftsError {
+    /// Database error.
+    #[api(internal)]
+    #[fail(display = "Database error: {}", _0)]
+    Database(#[cause] #[from] DbError),
+    /// Step or slot mapping is not valid.
+    #[api(code = "edit-process:migrate:invalid-mapping", status = "BAD_REQUEST")]
+    #[fail(display = "{}", _0)]
+    InvalidMapping(#[cause] #[from] structure::ValidateMigrationError),
+    /// Drafts can't be migrated from a version to itself.
+    #[api(code = "edit-process:migrate:same-version", status = "BAD_REQUEST")]
+    #[fail(display = "Cannot migrate drafts to the version they already follow")]
+    SameVersion,
+}
+
+#[derive(Serialize)]
+struct LogMigrate<'a> {
+    /// Version to which the draft was migrated.
+    version: i32,
+    /// Step the draft was in before migration.
+    from: i32,
+    /// Step the draft is in after migration.
+    to: i32,
+    /// Mapping of slots in the old version to slots in the new version.
+    slots: &'a HashMap<i32, i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{db, models::Team};
+    use super::*;
+    use super::structure::{LinkGuard, SlotPermission};
+
+    #[test]
+    #[ignore]
+    fn link_guards_round_trip() {
+        let db = db::test_connection();
+
+        let team = Team::create(&db, "Test team").unwrap();
+        let guards = vec![
+            LinkGuard::NoUnresolvedComments,
+            LinkGuard::ResolvedLinks,
+        ];
+
+        let (version, _) = Process::create(&db, &team, &structure::Process {
+            name: "Guarded process".into(),
+            start: 0,
+            slots: vec![
+                structure::Slot {
+                    id: 0,
+                    name: "Slot".into(),
+                    roles: Vec::new(),
+                    autofill: false,
+                },
+            ],
+            steps: vec![
+                structure::Step {
+                    id: 0,
+                    name: "Start".into(),
+                    duration: None,
+                    slots: vec![
+                        structure::StepSlot {
+                            slot: 0,
+                            permission: SlotPermission::Edit,
+                        },
+                    ],
+                    links: vec![
+                        structure::Link {
+                            name: "Finish".into(),
+                            to: 1,
+                            slot: 0,
+                            guards: guards.clone(),
+                        },
+                    ],
+                },
+                structure::Step {
+                    id: 0,
+                    name: "End".into(),
+                    duration: None,
+                    slots: vec![],
+                    links: vec![],
+                },
+            ],
+        }).unwrap();
+
+        let stored = version.get_structure(&db).unwrap();
+        let start = stored.steps.iter()
+            .find(|step| step.name == "Start")
+            .unwrap();
+
+        assert_eq!(start.links[0].guards, guards);
+    }
+}
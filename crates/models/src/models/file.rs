@@ -1,19 +1,55 @@
+use actix_web::{error::BlockingError, web};
 use adaptarr_error::ApiError;
 use adaptarr_macros::From;
 use adaptarr_util::bytes_to_hex;
 use blake2::blake2b::{Blake2b, Blake2bResult};
-use diesel::{prelude::*, result::Error as DbError};
+use chrono::{Duration, Utc};
+use diesel::{
+    Connection as _,
+    prelude::*,
+    result::Error as DbError,
+    sql_query,
+    sql_types::BigInt,
+};
 use failure::Fail;
 use futures::{Future, Stream as _, future};
+use image::{
+    FilterType,
+    ImageDecoder,
+    ImageError,
+    ImageFormat,
+    ImageOutputFormat,
+    ImageResult,
+    bmp::BMPDecoder,
+    gif::Decoder as GIFDecoder,
+    jpeg::JPEGDecoder,
+    png::PNGDecoder,
+    tiff::TIFFDecoder,
+    webp::WebpDecoder,
+};
+use log::{error, warn};
 use std::{
     convert::Infallible,
-    fs,
-    io::{self, Read, Seek, SeekFrom, Write},
-    path::Path,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+};
+use tempfile::NamedTempFile;
+
+use crate::{
+    db::{
+        Connection,
+        Pool,
+        models as db,
+        schema::{
+            documents,
+            document_files,
+            draft_changes,
+            file_derivatives,
+            files,
+            resources,
+        },
+    },
+    storage::{self, Object},
 };
-use tempfile::{Builder as TempBuilder, NamedTempFile};
-
-use crate::db::{Connection, Pool, models as db, schema::files};
 use super::{FindModelResult, Model};
 
 thread_local! {
@@ -29,12 +65,51 @@ thread_local! {
 /// MIME-type of a CNXML file.
 pub static CNXML_MIME: &str = "application/vnd.openstax.cnx+xml";
 
+/// Widths to which images can be scaled (see [`File::derivative`]).
+///
+/// Scaled images are cached indefinitely, so allowing arbitrary widths would
+/// let a client fill storage with copies of the same image.
+pub const DERIVATIVE_WIDTHS: &[u32] = &[160, 320, 640, 1280];
+
+/// Maximal size, in pixels, of an image which can be scaled (see
+/// [`File::derivative`]).
+///
+/// Images are fully decoded into memory before scaling, and this limit keeps
+/// them from taking more than about 200 MiB.
+pub const MAX_DERIVATIVE_SOURCE_PIXELS: u64 = 50_000_000;
+
+/// Key of the advisory lock protecting contents of files from being removed
+/// while new files are created.
+///
+/// [`File::delete_orphans`] holds it exclusively until it has removed contents
+/// of deleted files, and creating a file holds it shared until the end of
+/// the transaction.
+const CONTENTS_LOCK: i64 = 0x6164_6170_7461_7272;
+
+/// How many hours must pass since a file was uploaded before it can be
+/// considered an orphan (see [`File::orphans`]).
+///
+/// Files are often uploaded before they are used, for example before they are
+/// written into a draft, and this gives users time to use them.
+pub const ORPHAN_MIN_AGE_HOURS: i64 = 24;
+
 /// A virtual file.
 #[derive(Debug)]
 pub struct File {
     data: db::File,
 }
 
+/// Result of verifying contents of a file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrity {
+    /// File's contents match its hash.
+    Ok,
+    /// File's contents are missing from the storage.
+    Missing,
+    /// File's contents don't match its hash.
+    Corrupted,
+}
+
 impl Model for File {
     const ERROR_CATEGORY: &'static str = "file";
 
@@ -69,93 +144,167 @@ impl Model for File {
 }
 
 impl File {
+    /// Get all files.
+    pub fn all(db: &Connection) -> Result<Vec<File>, DbError> {
+        files::table
+            .order_by(files::id.asc())
+            .get_results::<db::File>(db)
+            .map(|v| v.into_iter().map(File::from_db).collect())
+    }
+
+    /// Find all files which are not used by any document, resource, or
+    /// proposed change.
+    ///
+    /// Files uploaded less than [`ORPHAN_MIN_AGE_HOURS`] ago are never
+    /// considered orphans. Scaled images (see [`File::derivative`]) are
+    /// orphans only after the file they were scaled from is deleted.
+    pub fn orphans(db: &Connection) -> Result<Vec<File>, DbError> {
+        let cutoff = Utc::now() - Duration::hours(ORPHAN_MIN_AGE_HOURS);
+
+        files::table
+            .filter(files::uploaded.lt(cutoff))
+            .filter(files::id.ne_all(documents::table
+                .select(documents::index)))
+            .filter(files::id.ne_all(document_files::table
+                .select(document_files::file)))
+            .filter(files::id.ne_all(draft_changes::table
+                .select(draft_changes::base)))
+            .filter(files::id.ne_all(draft_changes::table
+                .select(draft_changes::file)))
+            .filter(files::id.nullable().ne_all(resources::table
+                .select(resources::file)
+                .filter(resources::file.is_not_null())))
+            .filter(files::id.ne_all(file_derivatives::table
+                .filter(file_derivatives::source.eq_any(files::table
+                    .select(files::hash)))
+                .select(file_derivatives::file)))
+            .order_by(files::id.asc())
+            .get_results::<db::File>(db)
+            .map(|v| v.into_iter().map(File::from_db).collect())
+    }
+
+    /// Delete all files which are not used by any document, resource, or
+    /// proposed change, together with their contents, and return them.
+    ///
+    /// Should a file become used while this function runs, it will fail
+    /// without deleting anything. Errors removing contents are logged, but
+    /// otherwise ignored.
+    ///
+    /// This function must not be called inside a transaction, as contents are
+    /// only removed once deletion of files is committed.
+    pub fn delete_orphans(db: &Connection) -> Result<Vec<File>, DbError> {
+        // Creating new files waits until contents are removed, so that
+        // concurrent uploads of the same contents will either reference a file
+        // which was not deleted, or store the contents anew.
+        sql_query("SELECT pg_advisory_lock($1)")
+            .bind::<BigInt, _>(CONTENTS_LOCK)
+            .execute(db)?;
+
+        let result = db.transaction(|| {
+            let orphans = File::orphans(db)?;
+
+            diesel::delete(files::table
+                .filter(files::id.eq_any(
+                    orphans.iter().map(|file| file.id).collect::<Vec<_>>())))
+                .execute(db)?;
+
+            Ok(orphans)
+        });
+
+        if let Ok(ref orphans) = result {
+            for file in orphans {
+                match storage::backend().remove(&file.name()) {
+                    Ok(()) => (),
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+                    Err(err) => error!(
+                        "Could not remove contents of file {} ({}): {}",
+                        file.id, file.path, err),
+                }
+            }
+        }
+
+        let unlock = sql_query("SELECT pg_advisory_unlock($1)")
+            .bind::<BigInt, _>(CONTENTS_LOCK)
+            .execute(db);
+
+        let orphans = result?;
+        unlock?;
+
+        Ok(orphans)
+    }
+
+    /// Verify that contents of this file match its hash.
+    pub fn verify(&self) -> Result<Integrity, io::Error> {
+        let mut file = match self.open() {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound =>
+                return Ok(Integrity::Missing),
+            Err(err) => return Err(err),
+        };
+
+        let digest = {
+            let mut sink = io::sink();
+            let mut hash = HashingWriter::new(64, &mut sink);
+            io::copy(&mut file, &mut hash)?;
+            hash.finalize()
+        };
+
+        if digest.as_bytes() == &self.data.hash[..] {
+            Ok(Integrity::Ok)
+        } else {
+            Ok(Integrity::Corrupted)
+        }
+    }
+
     /// Create new file from a stream of bytes.
-    pub fn from_stream<P, S, I, E>(
+    pub fn from_stream<S, I, E>(
         dbpool: Pool,
-        storage_path: P,
         data: S,
         mime: Option<&'static str>,
     ) -> impl Future<Item=File, Error=E>
     where
-        P: AsRef<Path>,
         S: futures::Stream<Item=I>,
         I: AsRef<[u8]>,
         E: From<CreateFileError> + From<S::Error> + From<io::Error>,
     {
-        future::result(TempBuilder::new().tempfile_in(storage_path.as_ref()))
+        future::result(storage::temporary())
             .map_err(E::from)
             .and_then(|tmp| copy_hash(64, data, tmp))
-            .and_then(move |(hash, tmp)| future::result(
-                dbpool.get()
-                    .map_err(Into::into)
-                    .and_then(|db|
-                        File::from_file_with_hash(&*db, storage_path.as_ref(), tmp, hash, mime))
-                    .map_err(E::from)))
+            // Storing contents may require uploading them to a remote storage.
+            .and_then(move |(hash, tmp)| web::block(move || {
+                let db = dbpool.get()?;
+                File::from_file_with_hash(&*db, hash, mime, || Ok(tmp))
+            }).map_err(|err| match err {
+                BlockingError::Error(err) => E::from(err),
+                BlockingError::Canceled => E::from(io::Error::new(
+                    io::ErrorKind::Other, "thread pool is gone")),
+            }))
     }
 
     /// Create new file from a data in memory.
-    pub fn from_data<P, B>(
-        db: &Connection,
-        storage_path: P,
-        data: B,
-        mime: Option<&str>,
-    ) -> Result<File, CreateFileError>
+    pub fn from_data<B>(db: &Connection, data: B, mime: Option<&str>)
+    -> Result<File, CreateFileError>
     where
-        P: AsRef<Path>,
         B: AsRef<[u8]>,
     {
         let mut hash = Blake2b::new(64);
         hash.update(data.as_ref());
         let hash = hash.finalize();
 
-        match files::table
-            .filter(files::hash.eq(hash.as_bytes()))
-            .get_result::<db::File>(db)
-            .optional()?
-        {
-            // There already is a file with this hash.
-            Some(data) => Ok(File { data }),
-            // It's a new file; we need to create database entry for it.
-            None => {
-                let name = bytes_to_hex(hash.as_bytes());
-                let path = storage_path.as_ref().join(name);
-
-                let mut file = std::fs::File::create(&path)?;
-                file.write_all(data.as_ref())?;
-
-                let magic = match mime {
-                    Some(_) => None,
-                    None => Some(MAGIC.with(|magic| magic.file(&path))
-                        .expect("libmagic to work")),
-                };
-
-                let mime = mime.or_else(|| magic.as_ref().map(String::as_str)).unwrap();
-
-                diesel::insert_into(files::table)
-                    .values(db::NewFile {
-                        mime,
-                        path: path.to_str().expect("invalid path"),
-                        hash: hash.as_bytes(),
-                    })
-                    .get_result::<db::File>(db)
-                    .map_err(Into::into)
-                    .map(|data| File { data })
-            },
-        }
+        File::from_file_with_hash(db, hash, mime, || {
+            let mut file = storage::temporary()?;
+            file.write_all(data.as_ref())?;
+            Ok(file)
+        })
     }
 
-    /// Create new file from any type implementing [`std::io::Write`].
-    pub fn from_read<P, R>(
-        db: &Connection,
-        storage_path: P,
-        mut read: R,
-        mime: Option<&str>,
-    ) -> Result<File, CreateFileError>
+    /// Create new file from any type implementing [`std::io::Read`].
+    pub fn from_read<R>(db: &Connection, mut read: R, mime: Option<&str>)
+    -> Result<File, CreateFileError>
     where
-        P: AsRef<Path>,
         R: Read,
     {
-        let mut tmp = NamedTempFile::new_in(storage_path.as_ref())?;
+        let mut tmp = storage::temporary()?;
 
         let digest = {
             let mut hash = HashingWriter::new(64, &mut tmp);
@@ -163,19 +312,15 @@ impl File {
             hash.finalize()
         };
 
-        File::from_file_with_hash(db, storage_path.as_ref(), tmp, digest, mime)
+        File::from_file_with_hash(db, digest, mime, || Ok(tmp))
     }
 
     /// Create a new file from a temporary file.
-    pub fn from_temporary<P>(
+    pub fn from_temporary(
         db: &Connection,
-        storage_path: P,
         mut file: NamedTempFile,
         mime: Option<&str>,
-    ) -> Result<File, CreateFileError>
-    where
-        P: AsRef<Path>,
-    {
+    ) -> Result<File, CreateFileError> {
         let digest = {
             let mut sink = io::sink();
             let mut hash = HashingWriter::new(64, &mut sink);
@@ -184,66 +329,270 @@ impl File {
             hash.finalize()
         };
 
-        File::from_file_with_hash(db, storage_path, file, digest, mime)
+        File::from_file_with_hash(db, digest, mime, || Ok(file))
     }
 
-    /// Create new file from a temporary file and hash.
+    /// Create new file from a hash, and a temporary file with the same
+    /// contents, which is only obtained if there is no file with this hash yet.
     ///
     /// This is an internal constructor.
-    fn from_file_with_hash<P>(
+    fn from_file_with_hash<F>(
         db: &Connection,
-        storage_path: P,
-        file: NamedTempFile,
         hash: Blake2bResult,
         mime: Option<&str>,
+        file: F,
     ) -> Result<File, CreateFileError>
     where
-        P: AsRef<Path>,
+        F: FnOnce() -> Result<NamedTempFile, io::Error>,
     {
-        match files::table
-            .filter(files::hash.eq(hash.as_bytes()))
-            .get_result::<db::File>(db)
-            .optional()?
-        {
-            // There already is a file with this hash.
-            Some(data) => Ok(File { data }),
-            // It's a new file; we need to create database entry for it.
-            None => {
-                let name = bytes_to_hex(hash.as_bytes());
-                let path = storage_path.as_ref().join(name);
-                let _ = file.persist(&path)?;
-
-                let magic = match mime {
-                    Some(_) => None,
-                    None => Some(MAGIC.with(|magic| magic.file(&path))
-                        .expect("libmagic to work")),
-                };
-
-                let mime = mime.or_else(|| magic.as_ref().map(String::as_str)).unwrap();
-
-                diesel::insert_into(files::table)
-                    .values(db::NewFile {
-                        mime,
-                        path: path.to_str().expect("invalid path"),
-                        hash: hash.as_bytes(),
-                    })
-                    .get_result::<db::File>(db)
-                    .map_err(Into::into)
-                    .map(|data| File { data })
+        db.transaction(|| {
+            // Wait for File::delete_orphans to finish removing contents.
+            sql_query("SELECT pg_advisory_xact_lock_shared($1)")
+                .bind::<BigInt, _>(CONTENTS_LOCK)
+                .execute(db)?;
+
+            // Contents were uploaded again, which postpones them being
+            // considered orphans.
+            match diesel::update(files::table
+                .filter(files::hash.eq(hash.as_bytes())))
+                .set(files::uploaded.eq(Utc::now()))
+                .get_result::<db::File>(db)
+                .optional()?
+            {
+                // There already is a file with this hash.
+                Some(data) => Ok(File { data }),
+                // It's a new file; we need to create database entry for it.
+                None => File::store(db, file()?, hash, mime),
             }
-        }
+        })
+    }
+
+    /// Move a temporary file into storage and create a database entry for it.
+    ///
+    /// This is an internal constructor.
+    fn store(
+        db: &Connection,
+        file: NamedTempFile,
+        hash: Blake2bResult,
+        mime: Option<&str>,
+    ) -> Result<File, CreateFileError> {
+        let magic = match mime {
+            Some(_) => None,
+            None => Some(MAGIC.with(|magic| magic.file(file.path()))
+                .expect("libmagic to work")),
+        };
+
+        let mime = mime.or_else(|| magic.as_ref().map(String::as_str)).unwrap();
+
+        let dimensions = match image_format(mime) {
+            Some(format) => read_dimensions(file.reopen()?, format),
+            None => None,
+        };
+
+        let name = bytes_to_hex(hash.as_bytes());
+        let backend = storage::backend();
+        backend.store(&name, file)?;
+
+        diesel::insert_into(files::table)
+            .values(db::NewFile {
+                mime,
+                path: &backend.locate(&name),
+                hash: hash.as_bytes(),
+                width: dimensions.map(|(width, _)| width),
+                height: dimensions.map(|(_, height)| height),
+            })
+            .get_result::<db::File>(db)
+            .map_err(Into::into)
+            .map(|data| File { data })
+    }
+
+    /// Name under which contents of this file are kept in storage.
+    fn name(&self) -> String {
+        bytes_to_hex(&self.data.hash)
     }
 
     /// Read contents of this file into memory as a [`String`].
     pub fn read_to_string(&self) -> Result<String, io::Error> {
-        fs::read_to_string(&self.data.path)
+        let mut content = String::new();
+        self.open()?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    /// Open contents of this file for reading.
+    pub fn open(&self) -> Result<Object, io::Error> {
+        storage::backend().open(&self.name())
+    }
+
+    /// Get size of this file, in bytes.
+    pub fn size(&self) -> Result<u64, io::Error> {
+        storage::backend().size(&self.name())
+    }
+
+    /// Get dimensions (width and height) of the image in this file, or `None`
+    /// if this file is not an image, or its dimensions could not be read.
+    ///
+    /// Dimensions are read when a file is created. Files created before that
+    /// was the case can have them read with [`File::read_dimensions()`].
+    pub fn dimensions(&self) -> Option<(i32, i32)> {
+        match (self.data.width, self.data.height) {
+            (Some(width), Some(height)) => Some((width, height)),
+            _ => None,
+        }
     }
 
-    pub fn open(&self) -> Result<impl Read, io::Error> {
-        std::fs::File::open(&self.data.path)
+    /// Read dimensions of the image in this file and save them, returning
+    /// `None` if this file is not an image or its dimensions could not be
+    /// read.
+    pub fn read_dimensions(&mut self, db: &Connection)
+    -> Result<Option<(i32, i32)>, DbError> {
+        let format = match image_format(&self.data.mime) {
+            Some(format) => format,
+            None => return Ok(None),
+        };
+
+        let dimensions = match self.open() {
+            Ok(file) => read_dimensions(file, format),
+            Err(err) => {
+                error!("Could not open file {}: {}", self.data.id, err);
+                None
+            }
+        };
+
+        if let Some((width, height)) = dimensions {
+            self.data = diesel::update(&self.data)
+                .set((files::width.eq(width), files::height.eq(height)))
+                .get_result(db)?;
+        }
+
+        Ok(dimensions)
+    }
+
+    /// Get a copy of the image in this file scaled down to `width`, preserving
+    /// its aspect ratio.
+    ///
+    /// Scaled images are kept in storage as any other file, and reused for all
+    /// files with the same contents. JPEG images are scaled into JPEG images,
+    /// and all other formats into PNG images. WebP images are also scaled into
+    /// PNG images, as the `image` crate can't encode WebP. If `width` is not
+    /// smaller than image's own width, this file is returned instead.
+    ///
+    /// Only widths listed in [`DERIVATIVE_WIDTHS`] are allowed. Images larger
+    /// than [`MAX_DERIVATIVE_SOURCE_PIXELS`] can't be scaled.
+    ///
+    /// Scaling an image takes a while, so this method should not be called
+    /// on an Actix worker thread.
+    pub fn derivative(&self, db: &Connection, width: u32)
+    -> Result<File, DerivativeError> {
+        let format = image_format(&self.data.mime)
+            .ok_or(DerivativeError::NotAnImage)?;
+
+        if !DERIVATIVE_WIDTHS.contains(&width) {
+            return Err(DerivativeError::BadWidth);
+        }
+
+        if self.data.width.map_or(false, |own| width >= own as u32) {
+            return Ok(File { data: self.data.clone() });
+        }
+
+        let existing = file_derivatives::table
+            .inner_join(files::table)
+            .filter(file_derivatives::source.eq(&self.data.hash)
+                .and(file_derivatives::width.eq(width as i32)))
+            .select(files::all_columns)
+            .get_result::<db::File>(db)
+            .optional()?;
+
+        if let Some(data) = existing {
+            return Ok(File { data });
+        }
+
+        // Don't trust stored dimensions, they are only informative.
+        let (own_width, own_height) = decode_dimensions(
+            BufReader::new(self.open()?), format)?;
+
+        if u64::from(width) >= own_width {
+            return Ok(File { data: self.data.clone() });
+        }
+
+        if own_width * own_height > MAX_DERIVATIVE_SOURCE_PIXELS {
+            return Err(DerivativeError::TooLarge);
+        }
+
+        let image = image::load(BufReader::new(self.open()?), format)?;
+        let height = own_height * u64::from(width) / own_width;
+        let scaled = image.resize_exact(
+            width, height.max(1) as u32, FilterType::Lanczos3);
+
+        let (output, mime) = match format {
+            ImageFormat::JPEG => (ImageOutputFormat::JPEG(90), "image/jpeg"),
+            _ => (ImageOutputFormat::PNG, "image/png"),
+        };
+
+        let mut tmp = storage::temporary()?;
+        scaled.write_to(&mut tmp, output)?;
+        let file = File::from_temporary(db, tmp, Some(mime))?;
+
+        diesel::insert_into(file_derivatives::table)
+            .values(&db::NewFileDerivative {
+                source: &self.data.hash,
+                width: width as i32,
+                file: file.data.id,
+            })
+            .on_conflict_do_nothing()
+            .execute(db)?;
+
+        Ok(file)
+    }
+}
+
+/// Get format of images of a given MIME type, if it is a format we can decode.
+fn image_format(mime: &str) -> Option<ImageFormat> {
+    Some(match mime {
+        "image/bmp" => ImageFormat::BMP,
+        "image/gif" => ImageFormat::GIF,
+        "image/jpeg" => ImageFormat::JPEG,
+        "image/png" => ImageFormat::PNG,
+        "image/tiff" => ImageFormat::TIFF,
+        "image/webp" => ImageFormat::WEBP,
+        _ => return None,
+    })
+}
+
+/// Read dimensions of an image.
+///
+/// Errors are logged, but otherwise ignored, as they should not prevent files
+/// from being used.
+fn read_dimensions<R>(file: R, format: ImageFormat) -> Option<(i32, i32)>
+where
+    R: Read + Seek,
+{
+    match decode_dimensions(BufReader::new(file), format) {
+        Ok((width, height)) => Some((width as i32, height as i32)),
+        Err(err) => {
+            warn!("Could not read image dimensions: {}", err);
+            None
+        }
     }
 }
 
+/// Read dimensions of an image from its header, without decoding it.
+fn decode_dimensions<R>(reader: R, format: ImageFormat)
+-> ImageResult<(u64, u64)>
+where
+    R: BufRead + Seek,
+{
+    Ok(match format {
+        ImageFormat::BMP => BMPDecoder::new(reader)?.dimensions(),
+        ImageFormat::GIF => GIFDecoder::new(reader)?.dimensions(),
+        ImageFormat::JPEG => JPEGDecoder::new(reader)?.dimensions(),
+        ImageFormat::PNG => PNGDecoder::new(reader)?.dimensions(),
+        ImageFormat::TIFF => TIFFDecoder::new(reader)?.dimensions(),
+        ImageFormat::WEBP => WebpDecoder::new(reader)?.dimensions(),
+        _ => return Err(ImageError::UnsupportedError(
+            format!("Unsupported image format {:?}", format))),
+    })
+}
+
 impl std::ops::Deref for File {
     type Target = db::File;
 
@@ -268,6 +617,37 @@ pub enum CreateFileError {
     System(#[cause] #[from] io::Error),
 }
 
+#[derive(ApiError, Debug, Fail, From)]
+pub enum DerivativeError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// File is not an image, or is an image of an unsupported type.
+    #[fail(display = "File is not an image")]
+    #[api(code = "file:derivative:not-an-image", status = "BAD_REQUEST")]
+    NotAnImage,
+    /// Requested width is not one of [`DERIVATIVE_WIDTHS`].
+    #[fail(display = "Requested width is not supported")]
+    #[api(code = "file:derivative:bad-width", status = "BAD_REQUEST")]
+    BadWidth,
+    /// Image is larger than [`MAX_DERIVATIVE_SOURCE_PIXELS`].
+    #[fail(display = "Image is too large to be scaled")]
+    #[api(code = "file:derivative:too-large", status = "BAD_REQUEST")]
+    TooLarge,
+    /// Image could not be decoded or encoded.
+    #[fail(display = "Could not process image: {}", _0)]
+    #[api(code = "file:derivative:invalid-image", status = "BAD_REQUEST")]
+    Image(#[cause] #[from] ImageError),
+    /// Could not store scaled image.
+    #[fail(display = "Could not store scaled image: {}", _0)]
+    CreateFile(#[cause] #[from] CreateFileError),
+    /// System error.
+    #[fail(display = "System error: {}", _0)]
+    #[api(internal)]
+    System(#[cause] #[from] io::Error),
+}
+
 impl From<tempfile::PersistError> for CreateFileError {
     fn from(e: tempfile::PersistError) -> Self {
         CreateFileError::System(e.error)
@@ -334,3 +714,90 @@ where
         self.inner.flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, RgbImage};
+
+    use crate::db;
+    use super::*;
+
+    /// Make a file old enough to be considered an orphan.
+    fn age(db: &Connection, file: &File) {
+        diesel::update(&file.data)
+            .set(files::uploaded.eq(
+                Utc::now() - Duration::hours(ORPHAN_MIN_AGE_HOURS + 1)))
+            .execute(db)
+            .unwrap();
+    }
+
+    fn is_orphan(db: &Connection, file: &File) -> bool {
+        File::orphans(db).unwrap().iter().any(|orphan| orphan.id == file.id)
+    }
+
+    #[test]
+    #[ignore]
+    fn recent_files_are_not_orphans() {
+        let db = db::test_connection();
+
+        let file = File::from_data(&db, b"recent_files_are_not_orphans", None)
+            .unwrap();
+        assert!(!is_orphan(&db, &file));
+
+        age(&db, &file);
+        assert!(is_orphan(&db, &file));
+    }
+
+    #[test]
+    #[ignore]
+    fn derivatives_are_orphans_only_without_source() {
+        let db = db::test_connection();
+
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(400, 200))
+            .write_to(&mut png, ImageOutputFormat::PNG)
+            .unwrap();
+        let source = File::from_data(&db, &png, Some("image/png")).unwrap();
+        let scaled = source.derivative(&db, 160).unwrap();
+
+        assert_eq!(scaled.dimensions(), Some((160, 80)));
+
+        age(&db, &source);
+        age(&db, &scaled);
+        assert!(is_orphan(&db, &source));
+        assert!(!is_orphan(&db, &scaled));
+
+        diesel::delete(&source.data).execute(&db).unwrap();
+        assert!(is_orphan(&db, &scaled));
+    }
+
+    #[test]
+    #[ignore]
+    fn delete_orphans_removes_contents() {
+        let db = db::test_connection();
+
+        let used = File::from_data(&db, b"delete_orphans_keeps_used", None)
+            .unwrap();
+        let orphan = File::from_data(&db, b"delete_orphans_removes", None)
+            .unwrap();
+        diesel::insert_into(documents::table)
+            .values(&db::models::NewDocument {
+                title: "Document",
+                language: "en",
+                index: used.id,
+            })
+            .execute(&db)
+            .unwrap();
+
+        age(&db, &used);
+        age(&db, &orphan);
+
+        let deleted = File::delete_orphans(&db).unwrap();
+
+        assert!(deleted.iter().any(|file| file.id == orphan.id));
+        assert!(deleted.iter().all(|file| file.id != used.id));
+        assert_eq!(used.verify().unwrap(), Integrity::Ok);
+        assert_eq!(orphan.verify().unwrap(), Integrity::Missing);
+        assert!(File::by_id(&db, orphan.id).is_err());
+    }
+}
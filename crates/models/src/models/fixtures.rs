@@ -0,0 +1,109 @@
+//! Fixtures for tests of models which need a database.
+//!
+//! Many operations on models notify users about events, which requires
+//! an Actix system. Tests using them should create one with
+//! `actix::System::new`, even though it will never run.
+
+use uuid::Uuid;
+
+use crate::{
+    db::{Connection, types::SlotPermission},
+    permissions::TeamPermissions,
+};
+use super::{
+    Draft,
+    File,
+    Module,
+    Team,
+    User,
+    editing::{Process, Version, structure},
+};
+
+/// Contents of `index.cnxml` of modules created by [`module`].
+pub const INDEX: &str = r#"<document xmlns="http://cnx.rice.edu/cnxml"
+    xml:lang="en" cnxml-version="0.7" id="test" module-id="test">
+<title>Test</title>
+<content>
+<para id="p1">Test content.</para>
+</content>
+</document>"#;
+
+/// Create a new team.
+pub fn team(db: &Connection) -> Team {
+    Team::create(db, "Test team").unwrap()
+}
+
+/// Create a new user and add them to `team` with all permissions.
+pub fn member(db: &Connection, team: &mut Team) -> User {
+    let email = format!("{}@adaptarr.test", Uuid::new_v4());
+    let user = User::create(
+        db, None, &email, "Test user", "test", false, "en").unwrap();
+
+    team.add_member(db, &user, TeamPermissions::all(), None).unwrap();
+
+    user
+}
+
+/// Create a new module with [`INDEX`] as its content.
+pub fn module(db: &Connection, team: &Team, title: &str) -> Module {
+    let index = File::from_data(db, INDEX, None).unwrap();
+
+    Module::create::<&str, _>(db, team, title, "en", index, vec![]).unwrap()
+}
+
+/// Create a new editing process with a single slot, which can edit drafts in
+/// the first step and move them to the second, final step.
+pub fn process(db: &Connection, team: &Team) -> Version {
+    let (version, _) = Process::create(db, team, &structure::Process {
+        name: "Test process".into(),
+        start: 0,
+        slots: vec![
+            structure::Slot {
+                id: 0,
+                name: "Editor".into(),
+                roles: Vec::new(),
+                autofill: false,
+            },
+        ],
+        steps: vec![
+            structure::Step {
+                id: 0,
+                name: "Edit".into(),
+                duration: None,
+                slots: vec![
+                    structure::StepSlot {
+                        slot: 0,
+                        permission: SlotPermission::Edit,
+                    },
+                ],
+                links: vec![
+                    structure::Link {
+                        name: "Finish".into(),
+                        to: 1,
+                        slot: 0,
+                        guards: Vec::new(),
+                    },
+                ],
+            },
+            structure::Step {
+                id: 1,
+                name: "Done".into(),
+                duration: None,
+                slots: vec![],
+                links: vec![],
+            },
+        ],
+    }).unwrap();
+
+    version
+}
+
+/// Begin `version` of a process for `module`, assigning `user` to all slots.
+pub fn draft(db: &Connection, module: &Module, version: &Version, user: &User)
+-> Draft {
+    let slots = version.get_slots(db).unwrap()
+        .into_iter()
+        .map(|slot| (slot, user.clone()));
+
+    module.begin_process(db, version, slots).unwrap()
+}
@@ -0,0 +1,343 @@
+use adaptarr_error::ApiError;
+use chrono::{DateTime, Duration, Utc};
+use diesel::{Connection as _, prelude::*, result::Error as DbError};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::{
+    Connection,
+    models as db,
+    schema::{import_jobs, import_job_modules},
+    types::{ImportKind, ImportState},
+};
+use super::{FindModelResult, Model, Team, TeamResource};
+
+/// How many minutes can pass since a job's owner last reported it's alive
+/// before the job is considered interrupted (see [`ImportJob::heartbeat`]).
+pub const JOB_TIMEOUT_MINUTES: i64 = 5;
+
+lazy_static::lazy_static! {
+    /// ID of this server instance, used to mark jobs it will process.
+    static ref INSTANCE: Uuid = Uuid::new_v4();
+}
+
+/// A request to import contents of a ZIP archive, processed in background.
+#[derive(Debug)]
+pub struct ImportJob {
+    data: db::ImportJob,
+}
+
+/// A subset of import job's data that can safely be publicly exposed.
+#[derive(Debug, Serialize)]
+pub struct Public {
+    pub id: i32,
+    pub kind: ImportKind,
+    pub state: ImportState,
+    pub team: i32,
+    pub title: String,
+    pub module: Option<Uuid>,
+    pub book: Option<Uuid>,
+    pub error: Option<String>,
+    pub message: Option<String>,
+    pub created: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modules: Option<Vec<ModuleProgress>>,
+}
+
+/// Progress of importing a single module.
+#[derive(Debug, Serialize)]
+pub struct ModuleProgress {
+    pub title: String,
+    pub done: bool,
+}
+
+impl Model for ImportJob {
+    const ERROR_CATEGORY: &'static str = "import";
+
+    type Id = i32;
+    type Database = db::ImportJob;
+    type Public = Public;
+    type PublicParams = ();
+
+    fn by_id(db: &Connection, id: i32) -> FindModelResult<Self> {
+        import_jobs::table
+            .filter(import_jobs::id.eq(id))
+            .get_result::<db::ImportJob>(db)
+            .map(Self::from_db)
+            .map_err(From::from)
+    }
+
+    fn from_db(data: Self::Database) -> Self {
+        ImportJob { data }
+    }
+
+    fn into_db(self) -> Self::Database {
+        self.data
+    }
+
+    fn id(&self) -> i32 {
+        self.data.id
+    }
+
+    fn get_public(&self) -> Public {
+        let db::ImportJob {
+            id, kind, state, team, ref title, module, book, ref error,
+            ref message, created, finished, ..
+        } = self.data;
+
+        Public {
+            id, kind, state, team, module, book, created, finished,
+            title: title.clone(),
+            error: error.clone(),
+            message: message.clone(),
+            modules: None,
+        }
+    }
+
+    fn get_public_full(&self, db: &Connection, _: &()) -> Result<Public, DbError> {
+        let modules = import_job_modules::table
+            .filter(import_job_modules::job.eq(self.data.id))
+            .order_by(import_job_modules::index.asc())
+            .get_results::<db::ImportJobModule>(db)?
+            .into_iter()
+            .map(|module| ModuleProgress {
+                title: module.title,
+                done: module.done,
+            })
+            .collect();
+
+        Ok(Public {
+            modules: Some(modules),
+            .. self.get_public()
+        })
+    }
+}
+
+impl TeamResource for ImportJob {
+    fn team_id(&self) -> <Team as Model>::Id {
+        self.data.team
+    }
+}
+
+impl ImportJob {
+    /// Create a new job.
+    ///
+    /// For jobs replacing contents of an existing module or book, `module` or
+    /// `book` should name it.
+    ///
+    /// The job is owned by this server instance, and should be sent to its
+    /// [`Importer`](crate::processing::import::Importer).
+    pub fn create(
+        db: &Connection,
+        kind: ImportKind,
+        team: i32,
+        user: Option<i32>,
+        title: &str,
+        module: Option<Uuid>,
+        book: Option<Uuid>,
+    ) -> Result<ImportJob, DbError> {
+        diesel::insert_into(import_jobs::table)
+            .values(&db::NewImportJob {
+                kind, team, user, title, module, book,
+                owner: *INSTANCE,
+            })
+            .get_result::<db::ImportJob>(db)
+            .map(Self::from_db)
+    }
+
+    /// Mark this job as running.
+    pub fn start(&mut self, db: &Connection) -> Result<(), DbError> {
+        self.data = diesel::update(&self.data)
+            .set(import_jobs::state.eq(ImportState::Running))
+            .get_result(db)?;
+        Ok(())
+    }
+
+    /// Set list of modules this job will import.
+    pub fn set_modules<T>(&self, db: &Connection, titles: &[T])
+    -> Result<(), DbError>
+    where
+        T: AsRef<str>,
+    {
+        db.transaction(|| {
+            diesel::delete(import_job_modules::table
+                .filter(import_job_modules::job.eq(self.data.id)))
+                .execute(db)?;
+
+            diesel::insert_into(import_job_modules::table)
+                .values(titles.iter()
+                    .enumerate()
+                    .map(|(index, title)| db::NewImportJobModule {
+                        job: self.data.id,
+                        index: index as i32,
+                        title: title.as_ref(),
+                    })
+                    .collect::<Vec<_>>())
+                .execute(db)?;
+
+            Ok(())
+        })
+    }
+
+    /// Mark a module as imported.
+    pub fn module_done(&self, db: &Connection, index: i32)
+    -> Result<(), DbError> {
+        diesel::update(import_job_modules::table
+            .filter(import_job_modules::job.eq(self.data.id)
+                .and(import_job_modules::index.eq(index))))
+            .set(import_job_modules::done.eq(true))
+            .execute(db)?;
+        Ok(())
+    }
+
+    /// Mark this job as successfully finished.
+    pub fn finish(
+        &mut self,
+        db: &Connection,
+        module: Option<Uuid>,
+        book: Option<Uuid>,
+    ) -> Result<(), DbError> {
+        self.data = diesel::update(&self.data)
+            .set((
+                import_jobs::state.eq(ImportState::Done),
+                import_jobs::module.eq(module),
+                import_jobs::book.eq(book),
+                import_jobs::finished.eq(Utc::now()),
+            ))
+            .get_result(db)?;
+        Ok(())
+    }
+
+    /// Mark this job as failed.
+    ///
+    /// Only the code of `error` is saved, and its message only if it's not an
+    /// internal error, as it will be shown to users.
+    pub fn fail(&mut self, db: &Connection, error: &dyn ApiError)
+    -> Result<(), DbError> {
+        let code = error.code();
+        let message = code.as_ref().map(|_| error.to_string());
+
+        self.data = diesel::update(&self.data)
+            .set((
+                import_jobs::state.eq(ImportState::Failed),
+                import_jobs::error.eq(code.as_ref().map_or("internal", |c| &**c)),
+                import_jobs::message.eq(message),
+                import_jobs::finished.eq(Utc::now()),
+            ))
+            .get_result(db)?;
+        Ok(())
+    }
+
+    /// Report that this server instance is still processing jobs it owns.
+    ///
+    /// This function should be called more often than every
+    /// [`JOB_TIMEOUT_MINUTES`], or other instances will consider jobs owned
+    /// by this one interrupted.
+    pub fn heartbeat(db: &Connection) -> Result<usize, DbError> {
+        diesel::update(import_jobs::table
+            .filter(import_jobs::owner.eq(*INSTANCE)
+                .and(import_jobs::state.eq_any(
+                    vec![ImportState::Queued, ImportState::Running]))))
+            .set(import_jobs::heartbeat.eq(Utc::now()))
+            .execute(db)
+    }
+
+    /// Mark all queued or running jobs whose owners are no longer alive as
+    /// failed.
+    ///
+    /// Jobs are only queued in memory of the server instance which owns them,
+    /// and they can't be resumed once that instance was stopped. An instance
+    /// is considered stopped when it didn't report a heartbeat in the last
+    /// [`JOB_TIMEOUT_MINUTES`].
+    pub fn fail_interrupted(db: &Connection) -> Result<usize, DbError> {
+        let timeout = Utc::now() - Duration::minutes(JOB_TIMEOUT_MINUTES);
+
+        diesel::update(import_jobs::table
+            .filter(import_jobs::state.eq_any(
+                vec![ImportState::Queued, ImportState::Running]))
+            .filter(import_jobs::owner.ne(*INSTANCE))
+            .filter(import_jobs::heartbeat.lt(timeout)))
+            .set((
+                import_jobs::state.eq(ImportState::Failed),
+                import_jobs::error.eq("import:interrupted"),
+                import_jobs::message.eq(
+                    "Server was restarted before this job finished"),
+                import_jobs::finished.eq(Utc::now()),
+            ))
+            .execute(db)
+    }
+}
+
+impl std::ops::Deref for ImportJob {
+    type Target = db::ImportJob;
+
+    fn deref(&self) -> &db::ImportJob {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db;
+    use super::*;
+    use super::super::fixtures;
+
+    /// Create a job owned by `owner`, which last reported a heartbeat
+    /// `minutes` ago.
+    fn job(db: &Connection, team: &Team, owner: Uuid, minutes: i64)
+    -> ImportJob {
+        let job = ImportJob::create(
+            db, ImportKind::Module, team.id(), None, "Test", None, None)
+            .unwrap();
+
+        diesel::update(&job.data)
+            .set((
+                import_jobs::owner.eq(owner),
+                import_jobs::heartbeat.eq(
+                    Utc::now() - Duration::minutes(minutes)),
+            ))
+            .get_result(db)
+            .map(ImportJob::from_db)
+            .unwrap()
+    }
+
+    fn state(db: &Connection, job: &ImportJob) -> ImportState {
+        ImportJob::by_id(db, job.id()).unwrap().state
+    }
+
+    #[test]
+    #[ignore]
+    fn only_jobs_of_stopped_instances_fail() {
+        let db = db::test_connection();
+        let team = fixtures::team(&db);
+        let timeout = JOB_TIMEOUT_MINUTES + 1;
+
+        let own = job(&db, &team, *INSTANCE, timeout);
+        let stopped = job(&db, &team, Uuid::new_v4(), timeout);
+        let running = job(&db, &team, Uuid::new_v4(), 0);
+
+        ImportJob::fail_interrupted(&db).unwrap();
+
+        assert_eq!(state(&db, &own), ImportState::Queued);
+        assert_eq!(state(&db, &stopped), ImportState::Failed);
+        assert_eq!(state(&db, &running), ImportState::Queued);
+    }
+
+    #[test]
+    #[ignore]
+    fn heartbeat_keeps_own_jobs_alive() {
+        let db = db::test_connection();
+        let team = fixtures::team(&db);
+        let timeout = JOB_TIMEOUT_MINUTES + 1;
+
+        let own = job(&db, &team, *INSTANCE, timeout);
+        let other = job(&db, &team, Uuid::new_v4(), timeout);
+
+        ImportJob::heartbeat(&db).unwrap();
+
+        let cutoff = Utc::now() - Duration::minutes(JOB_TIMEOUT_MINUTES);
+        assert!(ImportJob::by_id(&db, own.id()).unwrap().heartbeat > cutoff);
+        assert!(ImportJob::by_id(&db, other.id()).unwrap().heartbeat < cutoff);
+    }
+}
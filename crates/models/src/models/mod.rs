@@ -8,12 +8,17 @@ use crate::db::Connection;
 
 mod book;
 mod bookpart;
+mod comment;
 mod document;
 mod draft;
+mod draft_change;
+mod draft_revision;
 mod event;
 mod file;
+mod import_job;
 mod invite;
 mod module;
+mod module_version;
 mod password;
 mod resource;
 mod role;
@@ -22,18 +27,51 @@ mod team_member;
 mod user;
 mod xref_target;
 
+#[cfg(test)]
+pub(crate) mod fixtures;
+
 pub mod conversation;
 pub mod editing;
 
 pub use self::{
-    book::Book,
+    book::{BeginBookProcessError, Book, BookXrefTarget},
     bookpart::{BookPart, CreatePartError, NewTree, ReparentPartError, Tree},
+    comment::{
+        Comment,
+        CreateCommentError,
+        ResolveCommentError,
+        Target as CommentTarget,
+    },
     document::Document,
-    draft::{AdvanceResult, Draft},
+    draft::{
+        AdvanceResult,
+        Draft,
+        ResolveChangesError,
+        RestoreRevisionError,
+        WriteFileError,
+    },
+    draft_change::DraftChange,
+    draft_revision::DraftRevision,
     event::Event,
-    file::{CNXML_MIME, CreateFileError, File},
+    file::{
+        CNXML_MIME,
+        DERIVATIVE_WIDTHS,
+        MAX_DERIVATIVE_SOURCE_PIXELS,
+        ORPHAN_MIN_AGE_HOURS,
+        CreateFileError,
+        DerivativeError,
+        File,
+        Integrity,
+    },
+    import_job::{JOB_TIMEOUT_MINUTES, ImportJob},
     invite::Invite,
-    module::{Module, ReplaceModuleError},
+    module::{
+        GetXrefTargetsError,
+        Module,
+        ReplaceModuleError,
+        RestoreVersionError,
+    },
+    module_version::ModuleVersion,
     password::PasswordResetToken,
     resource::{Resource, ResourceFileError},
     role::Role,
@@ -1,5 +1,6 @@
 use adaptarr_error::ApiError;
 use adaptarr_macros::From;
+use bytes::Bytes;
 use diesel::{
     Connection as _,
     expression::dsl::any,
@@ -24,6 +25,7 @@ use crate::{
             edit_process_steps,
             edit_process_versions,
             modules,
+            team_members,
             xref_targets,
         },
     },
@@ -31,16 +33,21 @@ use crate::{
     processing::TargetProcessor,
 };
 use super::{
+    AssertExists,
+    Comment,
+    CommentTarget,
+    CreateCommentError,
     Document,
     Draft,
     File,
     FindModelResult,
     Model,
+    ModuleVersion,
     Team,
     TeamResource,
     User,
     XrefTarget,
-    editing::{Slot, Version},
+    editing::{Slot, Step, Version},
 };
 
 /// A module is a version of Document that can be part of a Book.
@@ -230,7 +237,38 @@ impl Module {
             .map(Model::from_db)
     }
 
+    /// Get all comments made on this module, oldest first.
+    pub fn get_comments(&self, db: &Connection)
+    -> Result<Vec<Comment>, DbError> {
+        Comment::by_target(db, CommentTarget::Module(self.data.id))
+    }
+
+    /// Comment on this module.
+    ///
+    /// See [`Comment`] for details.
+    pub fn add_comment(
+        &self,
+        db: &Connection,
+        user: i32,
+        parent: Option<i32>,
+        element: Option<&str>,
+        body: &Bytes,
+    ) -> Result<Comment, CreateCommentError> {
+        Comment::create(
+            db,
+            CommentTarget::Module(self.data.id),
+            &self.document,
+            user,
+            parent,
+            element,
+            body,
+        )
+    }
+
     /// Begin a new editing process for this module.
+    ///
+    /// The process must belong to the same team as this module, and all
+    /// assigned users must be members of that team.
     pub fn begin_process<S>(
         &self,
         db: &Connection,
@@ -240,7 +278,26 @@ impl Module {
     where
         S: IntoIterator<Item = (Slot, User)>,
     {
+        if version.process().team_id() != self.data.team {
+            return Err(BeginProcessError::BadProcess(version.process().id));
+        }
+
         db.transaction(|| {
+            let slots = slots.into_iter().collect::<Vec<_>>();
+
+            let users = slots.iter()
+                .map(|(_, user)| user.id)
+                .collect::<Vec<_>>();
+            let members = team_members::table
+                .filter(team_members::team.eq(self.data.team)
+                    .and(team_members::user.eq(any(&users))))
+                .select(team_members::user)
+                .get_results::<i32>(db)?;
+
+            if let Some(&user) = users.iter().find(|id| !members.contains(*id)) {
+                return Err(BeginProcessError::NotAMember(user));
+            }
+
             let slots = slots.into_iter()
                 .map(|(slot, user)| {
                     if slot.process != version.id {
@@ -256,12 +313,15 @@ impl Module {
                 })
                 .collect::<Result<Vec<_>, _>>()?;
 
+            let start = Step::by_id(db, version.start).assert_exists()?;
+
             let draft = diesel::insert_into(drafts::table)
                 .values((
                     drafts::module.eq(self.data.id),
                     drafts::document.eq(duplicate_document(self.document.id)),
                     drafts::step.eq(version.start),
                     drafts::team.eq(self.data.team),
+                    drafts::deadline.eq(start.deadline()),
                 ))
                 .get_result::<db::Draft>(db)?;
 
@@ -340,6 +400,56 @@ impl Module {
 
         Ok(())
     }
+
+    /// Make an older version of this module current again.
+    ///
+    /// Version's document is duplicated, so that restoring it is recorded as
+    /// a new version of this module.
+    pub fn restore(&mut self, db: &Connection, version: &ModuleVersion)
+    -> Result<(), RestoreVersionError> {
+        let (module, source) = version.id();
+
+        if module != self.data.id {
+            return Err(RestoreVersionError::NotFound);
+        }
+
+        db.transaction(|| {
+            let count: i64 = drafts::table
+                .filter(drafts::module.eq(self.data.id))
+                .count()
+                .get_result(db)?;
+
+            if count > 0 {
+                return Err(RestoreVersionError::HasDraft);
+            }
+
+            let id = diesel::select(duplicate_document(source))
+                .get_result::<i32>(db)?;
+            let document = documents::table
+                .filter(documents::id.eq(id))
+                .get_result::<db::Document>(db)?;
+
+            diesel::update(modules::table.filter(modules::id.eq(self.data.id)))
+                .set(modules::document.eq(document.id))
+                .execute(db)?;
+
+            audit::log_db(db, "documents", document.id, "clone-from", source);
+
+            audit::log_db(
+                db, "modules", self.data.id, "restore-version", LogRestore {
+                    version: source,
+                    document: document.id,
+                });
+
+            self.document = Document::from_db(document);
+
+            Ok(())
+        })?;
+
+        TargetProcessor::process(self.document.clone());
+
+        Ok(())
+    }
 }
 
 impl std::ops::Deref for Module {
@@ -374,6 +484,22 @@ pub enum ReplaceModuleError {
     HasDrafts,
 }
 
+#[derive(ApiError, Debug, Fail, From)]
+pub enum RestoreVersionError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// Version is not a version of this module.
+    #[fail(display = "No such version")]
+    #[api(code = "module:version:not-found", status = "NOT_FOUND")]
+    NotFound,
+    /// Module has a draft.
+    #[fail(display = "Module with a draft cannot be restored")]
+    #[api(code = "module:restore:has-draft", status = "BAD_REQUEST")]
+    HasDraft,
+}
+
 #[derive(ApiError, Debug, Fail)]
 pub enum BeginProcessError {
     /// Database error.
@@ -388,6 +514,14 @@ pub enum BeginProcessError {
     #[fail(display = "Slot {} is not part of process {}", _0, _1)]
     #[api(code = "draft:create:bad-slot", status = "BAD_REQUEST")]
     BadSlot(i32, i32),
+    /// The process specified belongs to a different team than this module.
+    #[fail(display = "Process {} belongs to a different team", _0)]
+    #[api(code = "draft:create:bad-process", status = "BAD_REQUEST")]
+    BadProcess(i32),
+    /// One of the users specified is not a member of module's team.
+    #[fail(display = "User {} is not a member of module's team", _0)]
+    #[api(code = "draft:create:not-a-member", status = "BAD_REQUEST")]
+    NotAMember(i32),
 }
 
 impl From<DbError> for BeginProcessError {
@@ -418,3 +552,91 @@ struct LogNewDraft {
     /// Document containing the first version of this draft.
     document: i32,
 }
+
+#[derive(Serialize)]
+struct LogRestore {
+    /// Document of the version which was restored.
+    version: i32,
+    /// Document containing the restored content.
+    document: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db;
+    use super::*;
+    use super::super::fixtures;
+
+    /// Create a module and replace its contents, returning it and its first
+    /// version.
+    fn replaced_module(db: &Connection, team: &Team)
+    -> (Module, ModuleVersion) {
+        let mut module = fixtures::module(db, team, "Restored");
+        let index = File::from_data(
+            db, fixtures::INDEX.replace("Test content", "Changed"), None)
+            .unwrap();
+        module.replace::<&str, _>(db, index, vec![]).unwrap();
+
+        let version = ModuleVersion::by_module(db, module.id()).unwrap()
+            .into_iter()
+            .find(|version| version.id().1 != module.document.id)
+            .unwrap();
+
+        (module, version)
+    }
+
+    #[test]
+    #[ignore]
+    fn restore_version() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let team = fixtures::team(&db);
+        let (mut module, version) = replaced_module(&db, &team);
+
+        module.restore(&db, &version).unwrap();
+
+        assert_eq!(module.index, version.index);
+        assert_ne!(module.document.id, version.id().1);
+
+        let stored = Module::by_id(&db, module.id()).unwrap();
+        let versions = ModuleVersion::by_module(&db, module.id()).unwrap();
+
+        assert_eq!(stored.index, version.index);
+        assert_eq!(versions.len(), 3);
+    }
+
+    #[test]
+    #[ignore]
+    fn cannot_restore_version_of_another_module() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let team = fixtures::team(&db);
+        let (_, version) = replaced_module(&db, &team);
+        let mut other = fixtures::module(&db, &team, "Other");
+
+        match other.restore(&db, &version) {
+            Err(RestoreVersionError::NotFound) => (),
+            result => panic!("expected NotFound, got {:?}", result),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn cannot_restore_module_with_draft() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let mut team = fixtures::team(&db);
+        let user = fixtures::member(&db, &mut team);
+        let process = fixtures::process(&db, &team);
+        let (mut module, version) = replaced_module(&db, &team);
+        fixtures::draft(&db, &module, &process, &user);
+
+        match module.restore(&db, &version) {
+            Err(RestoreVersionError::HasDraft) => (),
+            result => panic!("expected HasDraft, got {:?}", result),
+        }
+    }
+}
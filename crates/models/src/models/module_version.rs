@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error as DbError};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::{
+    Connection,
+    models as db,
+    schema::{documents, module_versions},
+};
+use super::{Document, FindModelResult, Model};
+
+/// A historical version of a module.
+///
+/// A new version is recorded each time module's content changes, that is each
+/// time an editing process finishes, or the module is replaced or restored.
+#[derive(Debug)]
+pub struct ModuleVersion {
+    data: db::ModuleVersion,
+    document: Document,
+}
+
+/// A subset of version's data that can safely be publicly exposed.
+#[derive(Debug, Serialize)]
+pub struct Public {
+    pub id: i32,
+    pub version: DateTime<Utc>,
+    #[serde(flatten)]
+    pub document: <Document as Model>::Public,
+}
+
+impl Model for ModuleVersion {
+    const ERROR_CATEGORY: &'static str = "module:version";
+
+    type Id = (Uuid, i32);
+    type Database = (db::ModuleVersion, db::Document);
+    type Public = Public;
+    type PublicParams = ();
+
+    fn by_id(db: &Connection, (module, document): Self::Id)
+    -> FindModelResult<Self> {
+        module_versions::table
+            .filter(module_versions::module.eq(module)
+                .and(module_versions::document.eq(document)))
+            .inner_join(documents::table)
+            .get_result::<(db::ModuleVersion, db::Document)>(db)
+            .map(Self::from_db)
+            .map_err(From::from)
+    }
+
+    fn from_db((data, document): Self::Database) -> Self {
+        ModuleVersion {
+            data,
+            document: Document::from_db(document),
+        }
+    }
+
+    fn into_db(self) -> Self::Database {
+        (self.data, self.document.into_db())
+    }
+
+    fn id(&self) -> Self::Id {
+        (self.data.module, self.data.document)
+    }
+
+    fn get_public(&self) -> Public {
+        Public {
+            id: self.data.document,
+            version: self.data.version,
+            document: self.document.get_public(),
+        }
+    }
+}
+
+impl ModuleVersion {
+    /// Get all versions of a module, oldest first.
+    pub fn by_module(db: &Connection, module: Uuid)
+    -> Result<Vec<ModuleVersion>, DbError> {
+        module_versions::table
+            .filter(module_versions::module.eq(module))
+            .order_by(module_versions::version.asc())
+            .inner_join(documents::table)
+            .get_results::<(db::ModuleVersion, db::Document)>(db)
+            .map(|v| v.into_iter().map(Self::from_db).collect())
+    }
+
+    /// Find version of a module which was current at a particular time.
+    pub fn at(db: &Connection, module: Uuid, time: DateTime<Utc>)
+    -> FindModelResult<ModuleVersion> {
+        module_versions::table
+            .filter(module_versions::module.eq(module)
+                .and(module_versions::version.le(time)))
+            .order_by(module_versions::version.desc())
+            .inner_join(documents::table)
+            .first::<(db::ModuleVersion, db::Document)>(db)
+            .map(Self::from_db)
+            .map_err(From::from)
+    }
+
+    /// Get date and time at which this version was created.
+    pub fn version(&self) -> DateTime<Utc> {
+        self.data.version
+    }
+}
+
+impl std::ops::Deref for ModuleVersion {
+    type Target = Document;
+
+    fn deref(&self) -> &Document {
+        &self.document
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::db;
+    use super::*;
+    use super::super::{File, fixtures};
+
+    #[test]
+    #[ignore]
+    fn versions_of_module() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let team = fixtures::team(&db);
+        let mut module = fixtures::module(&db, &team, "Versioned");
+        let first = module.index;
+        let index = File::from_data(
+            &db, fixtures::INDEX.replace("Test content", "Changed"), None)
+            .unwrap();
+        module.replace::<&str, _>(&db, index, vec![]).unwrap();
+        let second = module.index;
+
+        // All versions created in a single transaction have the same time.
+        let now = Utc::now();
+        for (index, hours) in &[(first, 2), (second, 1)] {
+            diesel::update(module_versions::table
+                .filter(module_versions::module.eq(module.id()))
+                .filter(module_versions::document.eq_any(documents::table
+                    .filter(documents::index.eq(*index))
+                    .select(documents::id))))
+                .set(module_versions::version.eq(now - Duration::hours(*hours)))
+                .execute(&db)
+                .unwrap();
+        }
+
+        let versions = ModuleVersion::by_module(&db, module.id()).unwrap();
+        assert_eq!(versions.iter().map(|v| v.index).collect::<Vec<_>>(),
+            [first, second]);
+
+        let at = |hours| ModuleVersion::at(
+            &db, module.id(), now - Duration::minutes(hours * 60 + 30));
+        assert_eq!(at(0).unwrap().index, second);
+        assert_eq!(at(1).unwrap().index, first);
+        assert!(at(2).is_err());
+    }
+}
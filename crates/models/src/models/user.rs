@@ -44,7 +44,7 @@ static ARGON2_CONFIG: argon2::Config = argon2::Config {
 };
 
 /// A single user in the system.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct User {
     data: db::User,
 }
@@ -0,0 +1,506 @@
+//! Structural comparison of documents.
+
+use adaptarr_error::ApiError;
+use adaptarr_macros::From;
+use diesel::result::Error as DbError;
+use failure::Fail;
+use minidom::{Element, Node};
+use serde::Serialize;
+use std::{collections::{BTreeMap, HashMap, HashSet}, io, str::FromStr};
+
+use crate::{
+    db::Connection,
+    models::{AssertExists, Document, File},
+};
+
+/// Differences between two documents.
+#[derive(Debug, Serialize)]
+pub struct DocumentDiff {
+    /// Changes to elements of `index.cnxml`.
+    pub elements: Vec<ElementChange>,
+    /// Changes to media files.
+    pub media: Vec<MediaChange>,
+}
+
+/// A change to a single element, identified by its `id` attribute.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+pub enum ElementChange {
+    /// Element was added.
+    Added {
+        id: String,
+        element: String,
+        at: Position,
+    },
+    /// Element was removed.
+    Removed {
+        id: String,
+        element: String,
+        at: Position,
+    },
+    /// Element was moved into another parent, or reordered within the same
+    /// parent.
+    Moved {
+        id: String,
+        element: String,
+        from: Position,
+        to: Position,
+    },
+    /// Text content of an element was changed.
+    Text {
+        id: String,
+        element: String,
+        from: String,
+        to: String,
+    },
+    /// Attributes of an element were changed.
+    Attributes {
+        id: String,
+        element: String,
+        changes: Vec<AttributeChange>,
+    },
+}
+
+/// Position of an element within a document.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Position {
+    /// ID of the closest ancestor which has an ID, or `None` if there is no
+    /// such ancestor.
+    pub parent: Option<String>,
+    /// Index of this element among elements with IDs in the same parent.
+    pub index: usize,
+}
+
+/// A change to a single attribute.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AttributeChange {
+    pub name: String,
+    /// Previous value, or `None` if the attribute was added.
+    pub from: Option<String>,
+    /// New value, or `None` if the attribute was removed.
+    pub to: Option<String>,
+}
+
+/// A change to a single media file.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MediaChange {
+    pub name: String,
+    pub change: MediaChangeKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MediaChangeKind {
+    Added,
+    Removed,
+    /// Content of the file was changed.
+    Changed,
+}
+
+/// Compare two documents.
+///
+/// Elements of `index.cnxml` are matched by their IDs. Elements without an ID
+/// are not compared on their own, and are only considered as part of text
+/// content of their parents. Media files are matched by name and compared by
+/// their hashes.
+pub fn diff_documents(db: &Connection, from: &Document, to: &Document)
+-> Result<DocumentDiff, DiffError> {
+    let old = File::by_id(db, from.index).assert_exists()?.read_to_string()?;
+    let new = File::by_id(db, to.index).assert_exists()?.read_to_string()?;
+
+    let elements = diff_cnxml(&Element::from_str(&old)?, &Element::from_str(&new)?);
+
+    let old = media_hashes(db, from)?;
+    let new = media_hashes(db, to)?;
+
+    let mut media = Vec::new();
+
+    for (name, hash) in &old {
+        let change = match new.get(name) {
+            None => MediaChangeKind::Removed,
+            Some(new) if new != hash => MediaChangeKind::Changed,
+            Some(_) => continue,
+        };
+
+        media.push(MediaChange { name: name.clone(), change });
+    }
+
+    for name in new.keys().filter(|name| !old.contains_key(*name)) {
+        media.push(MediaChange {
+            name: name.clone(),
+            change: MediaChangeKind::Added,
+        });
+    }
+
+    media.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(DocumentDiff { elements, media })
+}
+
+/// Get hashes of all media files in a document.
+fn media_hashes(db: &Connection, document: &Document)
+-> Result<BTreeMap<String, Vec<u8>>, DbError> {
+    Ok(document.get_files(db)?
+        .into_iter()
+        .map(|(name, file)| (name, file.into_db().hash))
+        .collect())
+}
+
+/// Compare two CNXML documents.
+///
+/// Removed elements are listed first, in the order in which they appeared in
+/// `from`, followed by all other changes, in the order in which elements
+/// appear in `to`.
+pub fn diff_cnxml(from: &Element, to: &Element) -> Vec<ElementChange> {
+    let old = Index::new(from);
+    let new = Index::new(to);
+    let mut changes = Vec::new();
+
+    for &id in &old.order {
+        if !new.elements.contains_key(id) {
+            let entry = &old.elements[id];
+            changes.push(ElementChange::Removed {
+                id: id.to_string(),
+                element: entry.element.name().to_string(),
+                at: old.position(id),
+            });
+        }
+    }
+
+    let reordered = reordered(&old, &new);
+
+    for &id in &new.order {
+        let entry = &new.elements[id];
+        let name = entry.element.name();
+
+        let previous = match old.elements.get(id) {
+            Some(previous) => previous,
+            None => {
+                changes.push(ElementChange::Added {
+                    id: id.to_string(),
+                    element: name.to_string(),
+                    at: new.position(id),
+                });
+                continue;
+            }
+        };
+
+        if previous.parent != entry.parent || reordered.contains(id) {
+            changes.push(ElementChange::Moved {
+                id: id.to_string(),
+                element: name.to_string(),
+                from: old.position(id),
+                to: new.position(id),
+            });
+        }
+
+        let attributes = diff_attributes(previous.element, entry.element);
+        if !attributes.is_empty() {
+            changes.push(ElementChange::Attributes {
+                id: id.to_string(),
+                element: name.to_string(),
+                changes: attributes,
+            });
+        }
+
+        let old_text = own_text(previous.element);
+        let new_text = own_text(entry.element);
+        if old_text != new_text {
+            changes.push(ElementChange::Text {
+                id: id.to_string(),
+                element: name.to_string(),
+                from: old_text.unwrap_or_default(),
+                to: new_text.unwrap_or_default(),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Elements with IDs in a document.
+struct Index<'a> {
+    /// Elements by their IDs.
+    elements: HashMap<&'a str, Entry<'a>>,
+    /// IDs of elements, grouped by IDs of their parents, in document order.
+    children: HashMap<Option<&'a str>, Vec<&'a str>>,
+    /// IDs of all elements, in document order.
+    order: Vec<&'a str>,
+}
+
+struct Entry<'a> {
+    element: &'a Element,
+    /// ID of the closest ancestor which has an ID.
+    parent: Option<&'a str>,
+}
+
+impl<'a> Index<'a> {
+    fn new(root: &'a Element) -> Index<'a> {
+        let mut index = Index {
+            elements: HashMap::new(),
+            children: HashMap::new(),
+            order: Vec::new(),
+        };
+        index.add(root, None);
+        index
+    }
+
+    fn add(&mut self, element: &'a Element, parent: Option<&'a str>) {
+        let parent = match element.attr("id") {
+            Some(id) if !self.elements.contains_key(id) => {
+                self.elements.insert(id, Entry { element, parent });
+                self.children.entry(parent).or_default().push(id);
+                self.order.push(id);
+                Some(id)
+            }
+            _ => parent,
+        };
+
+        for child in element.children() {
+            self.add(child, parent);
+        }
+    }
+
+    fn position(&self, id: &str) -> Position {
+        let parent = self.elements[id].parent;
+        let index = self.children[&parent]
+            .iter()
+            .position(|&child| child == id)
+            .unwrap();
+
+        Position {
+            parent: parent.map(str::to_string),
+            index,
+        }
+    }
+}
+
+/// Find elements which remained in the same parent, but were reordered.
+///
+/// For each parent the longest common subsequence of its children is assumed
+/// to have remained in place, and all other common children to have moved.
+fn reordered<'a>(old: &Index<'a>, new: &Index<'a>) -> HashSet<&'a str> {
+    let mut moved = HashSet::new();
+
+    for (parent, children) in &new.children {
+        let old_children = match old.children.get(parent) {
+            Some(children) => children,
+            None => continue,
+        };
+
+        let stayed = |id: &&str| old.elements.get(id)
+            .map_or(false, |entry| entry.parent == *parent)
+            && new.elements[id].parent == *parent;
+
+        let a = old_children.iter().copied().filter(stayed).collect::<Vec<_>>();
+        let b = children.iter().copied().filter(stayed).collect::<Vec<_>>();
+        let common = longest_common_subsequence(&a, &b);
+
+        moved.extend(b.into_iter().filter(|id| !common.contains(id)));
+    }
+
+    moved
+}
+
+fn longest_common_subsequence<'a>(a: &[&'a str], b: &[&'a str])
+-> HashSet<&'a str> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut common = HashSet::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            common.insert(a[i]);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    common
+}
+
+/// Compare attributes of two elements, ignoring their IDs.
+fn diff_attributes(from: &Element, to: &Element) -> Vec<AttributeChange> {
+    let old = from.attrs()
+        .filter(|&(name, _)| name != "id")
+        .collect::<BTreeMap<_, _>>();
+    let new = to.attrs()
+        .filter(|&(name, _)| name != "id")
+        .collect::<BTreeMap<_, _>>();
+
+    let mut changes = Vec::new();
+
+    for (&name, &value) in &old {
+        match new.get(name) {
+            Some(&new) if new == value => {}
+            new => changes.push(AttributeChange {
+                name: name.to_string(),
+                from: Some(value.to_string()),
+                to: new.map(|value| value.to_string()),
+            }),
+        }
+    }
+
+    for (&name, &value) in &new {
+        if !old.contains_key(name) {
+            changes.push(AttributeChange {
+                name: name.to_string(),
+                from: None,
+                to: Some(value.to_string()),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Get text content of an element, with whitespace collapsed, if it contains
+/// text directly (as is the case with paragraphs, titles, etc.).
+fn own_text(element: &Element) -> Option<String> {
+    let has_text = element.nodes().any(|node| match node {
+        Node::Text(text) => !text.trim().is_empty(),
+        _ => false,
+    });
+
+    if !has_text {
+        return None;
+    }
+
+    let mut text = String::new();
+    collect_text(element, &mut text);
+
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn collect_text(element: &Element, text: &mut String) {
+    for node in element.nodes() {
+        match node {
+            Node::Text(t) => text.push_str(t),
+            Node::Element(e) => collect_text(e, text),
+            Node::Comment(_) => (),
+        }
+    }
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum DiffError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// One of the documents is not valid XML.
+    #[fail(display = "Invalid XML: {}", _0)]
+    #[api(code = "document:diff:invalid-xml", status = "BAD_REQUEST")]
+    InvalidXml(#[cause] #[from] minidom::Error),
+    /// An operating system error.
+    #[fail(display = "System error: {}", _0)]
+    #[api(internal)]
+    System(#[cause] #[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Element {
+        Element::from_str(&format!(
+            r#"<document xmlns="http://cnx.rice.edu/cnxml" id="d">{}</document>"#,
+            content,
+        )).unwrap()
+    }
+
+    #[test]
+    fn detects_added_and_removed_elements() {
+        let changes = diff_cnxml(
+            &parse(r#"<para id="a">A</para><para id="b">B</para>"#),
+            &parse(r#"<para id="a">A</para><para id="c">C</para>"#),
+        );
+
+        assert_eq!(changes, [
+            ElementChange::Removed {
+                id: "b".into(),
+                element: "para".into(),
+                at: Position { parent: Some("d".into()), index: 1 },
+            },
+            ElementChange::Added {
+                id: "c".into(),
+                element: "para".into(),
+                at: Position { parent: Some("d".into()), index: 1 },
+            },
+        ]);
+    }
+
+    #[test]
+    fn detects_moved_elements() {
+        let changes = diff_cnxml(
+            &parse(r#"<para id="a"/><para id="b"/><para id="c"/>
+                <section id="s"/>"#),
+            &parse(r#"<para id="b"/><para id="c"/><para id="a"/>
+                <section id="s"><para id="x"/></section>"#),
+        );
+
+        assert_eq!(changes, [
+            ElementChange::Moved {
+                id: "a".into(),
+                element: "para".into(),
+                from: Position { parent: Some("d".into()), index: 0 },
+                to: Position { parent: Some("d".into()), index: 2 },
+            },
+            ElementChange::Added {
+                id: "x".into(),
+                element: "para".into(),
+                at: Position { parent: Some("s".into()), index: 0 },
+            },
+        ]);
+    }
+
+    #[test]
+    fn detects_text_and_attribute_changes() {
+        let changes = diff_cnxml(
+            &parse(r#"<para id="a" class="x">Some <emphasis>old</emphasis>
+                text</para>"#),
+            &parse(r#"<para id="a" effect="y">Some <emphasis>new</emphasis>
+                text</para>"#),
+        );
+
+        assert_eq!(changes, [
+            ElementChange::Attributes {
+                id: "a".into(),
+                element: "para".into(),
+                changes: vec![
+                    AttributeChange {
+                        name: "class".into(),
+                        from: Some("x".into()),
+                        to: None,
+                    },
+                    AttributeChange {
+                        name: "effect".into(),
+                        from: None,
+                        to: Some("y".into()),
+                    },
+                ],
+            },
+            ElementChange::Text {
+                id: "a".into(),
+                element: "para".into(),
+                from: "Some old text".into(),
+                to: "Some new text".into(),
+            },
+        ]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Exporting modules and collections as ZIP archives.
+
+use adaptarr_error::ApiError;
+use adaptarr_macros::From;
+use diesel::result::Error as DbError;
+use failure::Fail;
+use std::{collections::HashSet, fmt::Write as _, io::{self, Seek, Write}};
+use zip::{CompressionMethod, ZipWriter, result::ZipError, write::FileOptions};
+
+use crate::{
+    db::Connection,
+    models::{AssertExists, Book, Document, File, Model, Module, Tree},
+};
+
+const COL_NS: &str = "http://cnx.rice.edu/collxml";
+const MDML_NS: &str = "http://cnx.rice.edu/mdml";
+
+/// Export a module as a ZIP archive.
+///
+/// The archive has the same layout as ZIPs exported from CNX: all files are
+/// placed in a single directory, named after the module's ID, with the CNXML
+/// document stored as `index.cnxml`. Archives produced by this function can be
+/// imported back with [`super::import::ImportModule`] and
+/// [`super::import::ReplaceModule`].
+pub fn export_module<W>(db: &Connection, module: &Module, output: W)
+-> Result<W, ExportError>
+where
+    W: Write + Seek,
+{
+    let mut zip = ZipWriter::new(output);
+    let base = module.id().to_string();
+
+    write_document(db, &mut zip, &base, module)?;
+
+    zip.finish().map_err(From::from)
+}
+
+/// Export a book as a ZIP archive.
+///
+/// The archive has the same layout as collection ZIPs exported from CNX:
+/// a single directory, named after the book's ID, containing
+/// `collection.xml` describing the book's structure, and one directory for
+/// each module in the book, laid out as in [`export_module`]. Archives
+/// produced by this function can be imported back with
+/// [`super::import::ImportBook`] and [`super::import::ReplaceBook`].
+pub fn export_book<W>(db: &Connection, book: &Book, output: W)
+-> Result<W, ExportError>
+where
+    W: Write + Seek,
+{
+    let tree = book.root_part(db)?.get_tree(db)?;
+
+    let mut zip = ZipWriter::new(output);
+    let base = book.id().to_string();
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated);
+
+    zip.add_directory(format!("{}/", base), options)?;
+
+    zip.start_file(format!("{}/collection.xml", base), options)?;
+    zip.write_all(render_collection(&book.title, &tree).as_bytes())?;
+
+    // The same module can be included in a book multiple times, but there is
+    // no need to export it more than once.
+    let mut exported = HashSet::new();
+    let mut queue = vec![&tree];
+
+    while let Some(part) = queue.pop() {
+        if let Some(module) = part.module() {
+            if exported.insert(module.id) {
+                let module = Module::by_id(db, module.id).assert_exists()?;
+                write_document(
+                    db, &mut zip, &format!("{}/{}", base, module.id()), &module)?;
+            }
+        }
+
+        if let Some(parts) = part.parts() {
+            queue.extend(parts);
+        }
+    }
+
+    zip.finish().map_err(From::from)
+}
+
+/// Render `collection.xml` describing structure of a book.
+fn render_collection(title: &str, tree: &Tree) -> String {
+    let mut xml = String::new();
+
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    let _ = writeln!(xml,
+        r#"<col:collection xmlns="{0}" xmlns:col="{0}" xmlns:md="{1}">"#,
+        COL_NS, MDML_NS);
+    let _ = writeln!(xml, "  <col:metadata>");
+    let _ = writeln!(xml, "    <md:title>{}</md:title>", tera::escape_html(title));
+    let _ = writeln!(xml, "  </col:metadata>");
+
+    render_content(&mut xml, 1, tree.parts().unwrap_or(&[]));
+
+    xml.push_str("</col:collection>\n");
+    xml
+}
+
+/// Render `<col:content>` element listing `parts`.
+fn render_content(xml: &mut String, depth: usize, parts: &[Tree]) {
+    let indent = "  ".repeat(depth);
+
+    let _ = writeln!(xml, "{}<col:content>", indent);
+
+    for part in parts {
+        let title = tera::escape_html(part.title());
+
+        if let Some(module) = part.module() {
+            let _ = writeln!(xml, "{}  <col:module document=\"{}\">", indent, module.id);
+            let _ = writeln!(xml, "{}    <md:title>{}</md:title>", indent, title);
+            let _ = writeln!(xml, "{}  </col:module>", indent);
+        } else {
+            let _ = writeln!(xml, "{}  <col:subcollection>", indent);
+            let _ = writeln!(xml, "{}    <md:title>{}</md:title>", indent, title);
+            render_content(xml, depth + 2, part.parts().unwrap_or(&[]));
+            let _ = writeln!(xml, "{}  </col:subcollection>", indent);
+        }
+    }
+
+    let _ = writeln!(xml, "{}</col:content>", indent);
+}
+
+/// Write all files of a document into directory `base` of a ZIP archive.
+pub(crate) fn write_document<W>(
+    db: &Connection,
+    zip: &mut ZipWriter<W>,
+    base: &str,
+    document: &Document,
+) -> Result<(), ExportError>
+where
+    W: Write + Seek,
+{
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated);
+
+    zip.add_directory(format!("{}/", base), options)?;
+
+    let index = File::by_id(db, document.index).assert_exists()?;
+    write_file(zip, &format!("{}/index.cnxml", base), &index, options)?;
+
+    for (name, file) in document.get_files(db)? {
+        write_file(zip, &format!("{}/{}", base, name), &file, options)?;
+    }
+
+    Ok(())
+}
+
+/// Copy contents of a file into a ZIP archive.
+fn write_file<W>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    file: &File,
+    options: FileOptions,
+) -> Result<(), ExportError>
+where
+    W: Write + Seek,
+{
+    zip.start_file(name, options)?;
+    io::copy(&mut file.open()?, zip)?;
+    Ok(())
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum ExportError {
+    /// There was a problem writing the ZIP archive.
+    #[fail(display = "Cannot write archive: {}", _0)]
+    #[api(internal)]
+    Archive(#[cause] #[from] ZipError),
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// An operating system error.
+    #[fail(display = "System error: {}", _0)]
+    #[api(internal)]
+    System(#[cause] #[from] io::Error),
+}
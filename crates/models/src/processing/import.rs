@@ -1,24 +1,44 @@
 //! File upload and importing ZIPs of modules and collections.
 
-use actix::{Actor, Addr, Handler, SyncArbiter, SyncContext, Message};
+use actix::{
+    Actor,
+    Addr,
+    AsyncContext,
+    Context,
+    Handler,
+    Message,
+    SyncArbiter,
+    SyncContext,
+};
 use adaptarr_error::ApiError;
 use adaptarr_macros::From;
 use diesel::{Connection as _Connection, result::Error as DbError};
 use failure::Fail;
+use log::{error, warn};
 use minidom::Element as XmlElement;
-use std::{path::PathBuf, io::Read, str::FromStr};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 use tempfile::NamedTempFile;
+use uuid::Uuid;
 use zip::{ZipArchive, result::ZipError};
 
 use crate::{
     audit,
     db::{Connection, Pool},
+    events::{EventManager, ImportFinished},
     models::{
         CNXML_MIME,
         Book,
         CreateFileError,
         CreatePartError,
         File,
+        ImportJob,
+        Model,
         Module,
         ReplaceModuleError,
         Team,
@@ -26,6 +46,15 @@ use crate::{
     processing::TargetProcessor,
 };
 
+mod validate;
+
+pub use self::validate::{
+    CnxmlError,
+    ModuleReport,
+    ValidationReport,
+    check_cnxml,
+};
+
 /// CNX includes in its ZIP exports a number of artefacts which we have no use
 /// for, and which may cause problems when importing back into CNX. This array
 /// contains names of such artefacts.
@@ -34,12 +63,17 @@ const SKIP_FILES: &[&str] = &[
     "index_auto_generated.cnxml",
 ];
 
+/// Interval between two heartbeats of import jobs (see
+/// [`ImportJob::heartbeat`]).
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Request a new module to be created from contents of a ZIP file
 pub struct ImportModule {
     pub title: String,
     pub file: NamedTempFile,
     pub actor: audit::Actor,
     pub team: Team,
+    pub job: ImportJob,
 }
 
 impl Message for ImportModule {
@@ -52,6 +86,7 @@ pub struct ReplaceModule {
     pub module: Module,
     pub file: NamedTempFile,
     pub actor: audit::Actor,
+    pub job: ImportJob,
 }
 
 impl Message for ReplaceModule {
@@ -64,6 +99,7 @@ pub struct ImportBook {
     pub file: NamedTempFile,
     pub actor: audit::Actor,
     pub team: Team,
+    pub job: ImportJob,
 }
 
 impl Message for ImportBook {
@@ -77,35 +113,51 @@ pub struct ReplaceBook {
     pub file: NamedTempFile,
     pub actor: audit::Actor,
     pub team: Team,
+    pub job: ImportJob,
 }
 
 impl Message for ReplaceBook {
     type Result = Result<Book, ImportError>;
 }
 
+/// Request contents of a ZIP archive of a module to be validated, without
+/// importing it.
+pub struct ValidateModule {
+    pub file: NamedTempFile,
+}
+
+impl Message for ValidateModule {
+    type Result = Result<ValidationReport, ImportError>;
+}
+
+/// Request contents of a ZIP archive of a collection to be validated, without
+/// importing it.
+pub struct ValidateBook {
+    pub file: NamedTempFile,
+}
+
+impl Message for ValidateBook {
+    type Result = Result<ValidationReport, ImportError>;
+}
+
 /// Actix actor processing ZIPs in a background worker.
 pub struct Importer {
     pool: Pool,
-    storage_path: PathBuf,
 }
 
 impl Importer {
-    pub fn new(
-        pool: Pool,
-        storage_path: PathBuf,
-    ) -> Importer {
-        Importer {
-            pool,
-            storage_path,
-        }
+    pub fn new(pool: Pool) -> Importer {
+        Importer { pool }
     }
 
-    pub fn start(
-        pool: Pool,
-        storage_path: PathBuf,
-    ) -> Addr<Importer> {
-        SyncArbiter::start(1, move || Importer::new(
-            pool.clone(), storage_path.clone()))
+    /// Start the importer.
+    ///
+    /// Jobs left unfinished by server instances which are no longer running
+    /// are marked as failed.
+    pub fn start(pool: Pool) -> Addr<Importer> {
+        JobMonitor { pool: pool.clone() }.start();
+
+        SyncArbiter::start(1, move || Importer::new(pool.clone()))
     }
 
     /// Process a zipped module and extract index.cnxml and other media files
@@ -113,95 +165,26 @@ impl Importer {
     fn process_module_zip(&mut self, db: &Connection, mut file: NamedTempFile)
     -> Result<ModuleZip, ImportError> {
         let mut zip = ZipArchive::new(file.as_file_mut())?;
+        let (_, base_path) = find_file(&mut zip, "index.cnxml")?
+            .ok_or(ImportError::IndexMissing)?;
 
-        // NOTE: Looking for index imperatively because rustc doesn't seem able
-        // to infer types for iterator combinators (“consider giving this
-        // closure parameter a type” on a parameter that already has a type).
-        let mut index = None;
-
-        for inx in 0..zip.len() {
-            let file = zip.by_index(inx)?;
-            let path = file.sanitized_name();
-            let name = path
-                .file_name()
-                .unwrap();
-
-            if name == "index.cnxml" {
-                index = Some((
-                    inx,
-                    path.parent().map_or_else(PathBuf::new, ToOwned::to_owned),
-                ));
-                break;
-            }
-        }
-
-        let (index, base_path) = index.ok_or(ImportError::IndexMissing)?;
-
-        let mut data = String::new();
-        zip.by_index(index)?.read_to_string(&mut data)?;
-        let data = XmlElement::from_str(&data)?;
-        let data = Document::from_xml(&data)
-            .map_err(|e| ImportError::MalformedIndexCnxml(
-                "index.cnxml".to_string(), e))?;
-
-        let index_file = File::from_read(
-            db,
-            &self.storage_path,
-            zip.by_index(index)?,
-            Some(&*CNXML_MIME),
-        )?;
-
-        let mut files = Vec::new();
-
-        for inx in 0..zip.len() {
-            // Don't import index twice.
-            if inx == index {
-                continue;
-            }
-
-            let file = zip.by_index(inx)?;
-
-            // Don't import directories.
-            if file.size() == 0 {
-                continue;
-            }
-
-            let path = file.sanitized_name();
-
-            if !path.starts_with(&base_path) {
-                continue;
-            }
-
-            let name = path
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_owned();
-
-            if SKIP_FILES.contains(&name.as_str()) {
-                continue;
-            }
-
-            let file = File::from_read(db, &self.storage_path, file, None)?;
-
-            files.push((name, file));
-        }
-
-        Ok(ModuleZip {
-            title: data.title,
-            language: data.language,
-            index: index_file,
-            files,
-        })
+        read_module(&mut zip, &base_path, &mut Store(db))?.parse()
     }
 
     /// Create a new module from a ZIP of its contents.
-    fn create_module(&mut self, team: &Team, title: String, file: NamedTempFile)
-    -> Result<Module, ImportError> {
+    fn create_module(
+        &mut self,
+        job: &mut ImportJob,
+        team: &Team,
+        title: String,
+        file: NamedTempFile,
+    ) -> Result<Module, ImportError> {
         let db = self.pool.get()?;
 
-        db.transaction(|| {
+        job.start(&*db)?;
+        job.set_modules(&*db, &[&title])?;
+
+        let module = db.transaction::<_, ImportError, _>(|| {
             let ModuleZip {
                 language, index, files, ..
             } = self.process_module_zip(&*db, file)?;
@@ -209,53 +192,46 @@ impl Importer {
             let module = Module::create(&*db, team, &title, &language, index, files)?;
 
             Ok(module)
-        })
+        })?;
+
+        job.module_done(&*db, 0)?;
+
+        Ok(module)
     }
 
     /// Import a zipped module onto an existing one.
-    fn replace_module(&mut self, mut module: Module, file: NamedTempFile)
-    -> Result<Module, ImportError> {
+    fn replace_module(
+        &mut self,
+        job: &mut ImportJob,
+        mut module: Module,
+        file: NamedTempFile,
+    ) -> Result<Module, ImportError> {
         let db = self.pool.get()?;
 
-        db.transaction(|| {
+        job.start(&*db)?;
+        job.set_modules(&*db, &[&module.title])?;
+
+        let module = db.transaction::<_, ImportError, _>(|| {
             let ModuleZip { index, files, .. } = self.process_module_zip(&*db, file)?;
 
             module.replace(&*db, index, files)?;
 
             Ok(module)
-        })
+        })?;
+
+        job.module_done(&*db, 0)?;
+
+        Ok(module)
     }
 
     /// Process a zipped collection and extract from it collection.xml, list
     /// of modules, and their structure.
     fn preprocess_collection_zip<'c, 'f>(&'c mut self, file: &'f mut NamedTempFile)
-    -> Result<(ZipArchive<&'f mut std::fs::File>, Collection, PathBuf), ImportError> {
+    -> Result<(ZipArchive<&'f mut fs::File>, Collection, PathBuf), ImportError> {
         let mut zip = ZipArchive::new(file.as_file_mut())?;
 
-        // NOTE: Looking for collection.xml imperatively because rustc doesn't
-        // seem able to infer types for iterator combinators (“consider giving
-        // this closure parameter a type” on a parameter that already has
-        // a type).
-        let mut colxml = None;
-
-        for inx in 0..zip.len() {
-            let file = zip.by_index(inx)?;
-            let path = file.sanitized_name();
-            let name = path
-                .file_name()
-                .unwrap();
-
-            if name == "collection.xml" {
-                println!("collection.xml found at {}", file.name());
-                colxml = Some((
-                    inx,
-                    path.parent().map_or_else(PathBuf::new, ToOwned::to_owned),
-                ));
-                break;
-            }
-        }
-
-        let (colxml_inx, base_path) = colxml.ok_or(ImportError::ColxmlMissing)?;
+        let (colxml_inx, base_path) = find_file(&mut zip, "collection.xml")?
+            .ok_or(ImportError::ColxmlMissing)?;
 
         let mut coldata = String::new();
         zip.by_index(colxml_inx)?.read_to_string(&mut coldata)?;
@@ -265,89 +241,27 @@ impl Importer {
         Ok((zip, coldata, base_path))
     }
 
-    /// Import a single module from a collection ZIP.
-    fn load_collection_module(
-        &mut self,
-        db: &Connection,
-        zip: &mut ZipArchive<&mut std::fs::File>,
-        base_path: PathBuf,
-    ) -> Result<ModuleZip, ImportError> {
-        let index_path = base_path.join("index.cnxml");
-        let index_path = index_path.to_str().unwrap();
-
-        let mut data = String::new();
-        zip.by_name(index_path)?.read_to_string(&mut data)?;
-        let data = XmlElement::from_str(&data)?;
-        let data = Document::from_xml(&data)
-            .map_err(|e| ImportError::MalformedIndexCnxml(
-                index_path.to_string(), e))?;
-
-        let index_file = {
-            let index = zip.by_name(index_path)?;
-            File::from_read(
-                db,
-                &self.storage_path,
-                index,
-                Some(&*CNXML_MIME),
-            )?
-        };
-
-        let mut files = Vec::new();
-
-        for inx in 0..zip.len() {
-            let file = zip.by_index(inx)?;
-
-            // Don't import directories.
-            if file.size() == 0 {
-                continue;
-            }
-
-            let path = file.sanitized_name();
-
-            if !path.starts_with(&base_path) {
-                continue;
-            }
-
-            let name = path
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_owned();
-
-            // Don't import index.cnxml twice.
-            if name == "index.cnxml" {
-                continue;
-            }
-
-            if SKIP_FILES.contains(&name.as_str()) {
-                continue;
-            }
-
-            let file = File::from_read(db, &self.storage_path, file, None)?;
-            files.push((name, file));
-        }
-
-        Ok(ModuleZip {
-            title: data.title,
-            language: data.language,
-            index: index_file,
-            files,
-        })
-    }
-
     /// Load contents of a book from a collection ZIP.
+    ///
+    /// Progress is recorded in `job` using a separate database connection
+    /// `progress`, so that it is visible before the import is committed.
+    #[allow(clippy::too_many_arguments)]
     fn load_collection_zip(
         &mut self,
         db: &Connection,
+        progress: &Connection,
+        job: &ImportJob,
         team: &Team,
         book: &mut Book,
-        mut zip: ZipArchive<&mut std::fs::File>,
+        mut zip: ZipArchive<&mut fs::File>,
         coldata: Collection,
         base: PathBuf,
     ) -> Result<(), ImportError> {
         let root = book.root_part(db)?;
 
+        job.set_modules(progress, &coldata.module_titles())?;
+
+        let mut imported = 0;
         let mut queue = vec![(root, &coldata.content)];
 
         while let Some((group, content)) = queue.pop() {
@@ -357,8 +271,8 @@ impl Importer {
                         let path = base.join(document);
                         let ModuleZip {
                             language, index, files, ..
-                        } = self.load_collection_module(
-                            db, &mut zip, path)?;
+                        } = read_module(&mut zip, &path, &mut Store(db))?
+                            .parse()?;
                         let module = Module::create(
                             db, team, &title, &language, index, files)?;
                         group.insert_module(db, inx as i32, &title, &module)
@@ -366,6 +280,8 @@ impl Importer {
                                 CreatePartError::Database(e) => e,
                                 CreatePartError::IsAModule => unreachable!(),
                             })?;
+                        job.module_done(progress, imported)?;
+                        imported += 1;
                     }
                     Element::Subcollection(Subcollection { title, content }) => {
                         let new = group.create_group(db, inx as i32, &title)
@@ -383,8 +299,16 @@ impl Importer {
     }
 
     /// Create a new book for a ZIP of its contents.
-    fn create_book(&mut self, team: &Team, title: String, mut file: NamedTempFile)
-    -> Result<Book, ImportError> {
+    fn create_book(
+        &mut self,
+        job: &mut ImportJob,
+        team: &Team,
+        title: String,
+        mut file: NamedTempFile,
+    ) -> Result<Book, ImportError> {
+        let progress = self.pool.get()?;
+        job.start(&*progress)?;
+
         let (zip, coldata, base) = self.preprocess_collection_zip(&mut file)?;
 
         let db = self.pool.get()?;
@@ -393,7 +317,7 @@ impl Importer {
         let book = db.transaction::<_, ImportError, _>(|| {
             let mut book = Book::create(db, team, &title)?;
             self.load_collection_zip(
-                db, team, &mut book, zip, coldata, base)?;
+                db, &*progress, job, team, &mut book, zip, coldata, base)?;
             Ok(book)
         })?;
 
@@ -403,8 +327,16 @@ impl Importer {
     }
 
     /// Replace contents of a book from a collection ZIP.
-    fn replace_book(&mut self, team: &Team, mut book: Book, mut file: NamedTempFile)
-    -> Result<Book, ImportError> {
+    fn replace_book(
+        &mut self,
+        job: &mut ImportJob,
+        team: &Team,
+        mut book: Book,
+        mut file: NamedTempFile,
+    ) -> Result<Book, ImportError> {
+        let progress = self.pool.get()?;
+        job.start(&*progress)?;
+
         let (zip, coldata, base) = self.preprocess_collection_zip(&mut file)?;
 
         let db = self.pool.get()?;
@@ -412,7 +344,8 @@ impl Importer {
 
         let book = db.transaction::<_, ImportError, _>(|| {
             book.root_part(db)?.clear(db)?;
-            self.load_collection_zip(db, team, &mut book, zip, coldata, base)?;
+            self.load_collection_zip(
+                db, &*progress, job, team, &mut book, zip, coldata, base)?;
             Ok(book)
         })?;
 
@@ -420,6 +353,77 @@ impl Importer {
 
         Ok(book)
     }
+
+    /// Record outcome of an import job and notify the user who requested it.
+    fn finish_job(
+        &self,
+        mut job: ImportJob,
+        result: Result<(Option<Uuid>, Option<Uuid>), &ImportError>,
+    ) {
+        let db = match self.pool.get() {
+            Ok(db) => db,
+            Err(err) => {
+                error!("Cannot record outcome of import job {}: {}",
+                    job.id(), err);
+                return;
+            }
+        };
+
+        let success = result.is_ok();
+        let saved = match result {
+            Ok((module, book)) => job.finish(&*db, module, book),
+            Err(err) => job.fail(&*db, err),
+        };
+
+        if let Err(err) = saved {
+            error!("Cannot record outcome of import job {}: {}", job.id(), err);
+        }
+
+        if let Some(user) = job.user {
+            EventManager::notify(user, ImportFinished {
+                job: job.id(),
+                success,
+            });
+        }
+    }
+}
+
+/// Actix actor periodically reporting that jobs owned by this server instance
+/// are still being processed, and failing jobs left unfinished by instances
+/// which are no longer running.
+struct JobMonitor {
+    pool: Pool,
+}
+
+impl JobMonitor {
+    fn on_interval(&mut self, _: &mut Context<Self>) {
+        if let Err(err) = self.check() {
+            error!("Could not monitor import jobs: {}", err);
+        }
+    }
+
+    fn check(&mut self) -> Result<(), ImportError> {
+        let db = self.pool.get()?;
+
+        ImportJob::heartbeat(&*db)?;
+
+        let count = ImportJob::fail_interrupted(&*db)?;
+
+        if count > 0 {
+            warn!("Marked {} interrupted import jobs as failed", count);
+        }
+
+        Ok(())
+    }
+}
+
+impl Actor for JobMonitor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.on_interval(ctx);
+        ctx.run_interval(HEARTBEAT_INTERVAL, Self::on_interval);
+    }
 }
 
 #[derive(Debug)]
@@ -430,6 +434,140 @@ struct ModuleZip {
     files: Vec<(String, File)>,
 }
 
+/// Files of a module read from a ZIP archive (see [`read_module`]).
+struct ModuleFiles<F> {
+    /// Path of module's `index.cnxml` within the archive.
+    index_path: PathBuf,
+    /// Contents of module's `index.cnxml`.
+    cnxml: Vec<u8>,
+    index: F,
+    files: Vec<(String, F)>,
+}
+
+impl ModuleFiles<File> {
+    /// Parse `index.cnxml` of a module.
+    fn parse(self) -> Result<ModuleZip, ImportError> {
+        let data = String::from_utf8(self.cnxml)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let data = XmlElement::from_str(&data)?;
+        let data = Document::from_xml(&data)
+            .map_err(|e| ImportError::MalformedIndexCnxml(
+                self.index_path.to_string_lossy().into_owned(), e))?;
+
+        Ok(ModuleZip {
+            title: data.title,
+            language: data.language,
+            index: self.index,
+            files: self.files,
+        })
+    }
+}
+
+/// Destination of files read from a ZIP archive of a module.
+///
+/// Archives are walked the same way when they are imported and when they are
+/// only validated (see [`read_module`]), and sinks decide what is done with
+/// files found along the way.
+trait ModuleSink {
+    /// Result of processing a single file.
+    type File;
+
+    /// Process a file which is a part of the module.
+    fn file<R: Read>(&mut self, file: R, mime: Option<&str>)
+    -> Result<Self::File, ImportError>;
+
+    /// Note a file which was skipped as a CNX-specific artefact.
+    fn skipped(&mut self, _: &Path) {}
+
+    /// Note a file which was skipped as it lies outside of module's directory.
+    fn outside(&mut self, _: &Path) {}
+}
+
+/// A [`ModuleSink`] storing files in the database.
+struct Store<'c>(&'c Connection);
+
+impl ModuleSink for Store<'_> {
+    type File = File;
+
+    fn file<R: Read>(&mut self, file: R, mime: Option<&str>)
+    -> Result<File, ImportError> {
+        Ok(File::from_read(self.0, file, mime)?)
+    }
+}
+
+/// Find a file with a given name in a ZIP archive, returning its index and
+/// path of the directory containing it.
+fn find_file(zip: &mut ZipArchive<&mut fs::File>, name: &str)
+-> Result<Option<(usize, PathBuf)>, ImportError> {
+    for inx in 0..zip.len() {
+        let path = zip.by_index(inx)?.sanitized_name();
+
+        if path.file_name().map_or(false, |file| file == name) {
+            let base = path.parent()
+                .map_or_else(PathBuf::new, ToOwned::to_owned);
+            return Ok(Some((inx, base)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Read `index.cnxml` and media files of a module located in `base_path`
+/// within a ZIP archive, passing them to `sink`.
+fn read_module<S: ModuleSink>(
+    zip: &mut ZipArchive<&mut fs::File>,
+    base_path: &Path,
+    sink: &mut S,
+) -> Result<ModuleFiles<S::File>, ImportError> {
+    let index_path = base_path.join("index.cnxml");
+    let mut index = None;
+    let mut files = Vec::new();
+
+    for inx in 0..zip.len() {
+        let mut file = zip.by_index(inx)?;
+
+        // Don't import directories.
+        if file.size() == 0 {
+            continue;
+        }
+
+        let path = file.sanitized_name();
+
+        if !path.starts_with(base_path) {
+            sink.outside(&path);
+            continue;
+        }
+
+        let name = path.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| ImportError::InvalidFileName(
+                path.to_string_lossy().into_owned()))?
+            .to_owned();
+
+        // Don't import index.cnxml twice, or index.cnxml of another module.
+        if name == "index.cnxml" {
+            if path == index_path {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                let stored = sink.file(data.as_slice(), Some(CNXML_MIME))?;
+                index = Some((data, stored));
+            }
+            continue;
+        }
+
+        if SKIP_FILES.contains(&name.as_str()) {
+            sink.skipped(&path);
+            continue;
+        }
+
+        files.push((name, sink.file(file, None)?));
+    }
+
+    let (cnxml, index) = index.ok_or(ImportError::IndexMissing)?;
+
+    Ok(ModuleFiles { index_path, cnxml, index, files })
+}
+
 impl Actor for Importer {
     type Context = SyncContext<Self>;
 }
@@ -438,9 +576,12 @@ impl Handler<ImportModule> for Importer {
     type Result = Result<Module, ImportError>;
 
     fn handle(&mut self, msg: ImportModule, _: &mut Self::Context) -> Self::Result {
-        let ImportModule { title, file, actor, team } = msg;
+        let ImportModule { title, file, actor, team, mut job } = msg;
 
-        audit::with_actor(actor, || self.create_module(&team, title, file))
+        let result = audit::with_actor(
+            actor, || self.create_module(&mut job, &team, title, file));
+        self.finish_job(job, result.as_ref().map(|module| (Some(module.id()), None)));
+        result
     }
 }
 
@@ -448,9 +589,12 @@ impl Handler<ReplaceModule> for Importer {
     type Result = Result<Module, ImportError>;
 
     fn handle(&mut self, msg: ReplaceModule, _: &mut Self::Context) -> Self::Result {
-        let ReplaceModule { module, file, actor } = msg;
+        let ReplaceModule { module, file, actor, mut job } = msg;
 
-        audit::with_actor(actor, || self.replace_module(module, file))
+        let result = audit::with_actor(
+            actor, || self.replace_module(&mut job, module, file));
+        self.finish_job(job, result.as_ref().map(|module| (Some(module.id()), None)));
+        result
     }
 }
 
@@ -458,9 +602,12 @@ impl Handler<ImportBook> for Importer {
     type Result = Result<Book, ImportError>;
 
     fn handle(&mut self, msg: ImportBook, _: &mut Self::Context) -> Self::Result {
-        let ImportBook { title, file, actor, team } = msg;
+        let ImportBook { title, file, actor, team, mut job } = msg;
 
-        audit::with_actor(actor, || self.create_book(&team, title, file))
+        let result = audit::with_actor(
+            actor, || self.create_book(&mut job, &team, title, file));
+        self.finish_job(job, result.as_ref().map(|book| (None, Some(book.id()))));
+        result
     }
 }
 
@@ -468,9 +615,28 @@ impl Handler<ReplaceBook> for Importer {
     type Result = Result<Book, ImportError>;
 
     fn handle(&mut self, msg: ReplaceBook, _: &mut Self::Context) -> Self::Result {
-        let ReplaceBook { book, file, actor, team } = msg;
+        let ReplaceBook { book, file, actor, team, mut job } = msg;
 
-        audit::with_actor(actor, || self.replace_book(&team, book, file))
+        let result = audit::with_actor(
+            actor, || self.replace_book(&mut job, &team, book, file));
+        self.finish_job(job, result.as_ref().map(|book| (None, Some(book.id()))));
+        result
+    }
+}
+
+impl Handler<ValidateModule> for Importer {
+    type Result = Result<ValidationReport, ImportError>;
+
+    fn handle(&mut self, msg: ValidateModule, _: &mut Self::Context) -> Self::Result {
+        self.validate_module_zip(msg.file)
+    }
+}
+
+impl Handler<ValidateBook> for Importer {
+    type Result = Result<ValidationReport, ImportError>;
+
+    fn handle(&mut self, msg: ValidateBook, _: &mut Self::Context) -> Self::Result {
+        self.validate_collection_zip(msg.file)
     }
 }
 
@@ -488,6 +654,10 @@ pub enum ImportError {
     #[fail(display = "Archive is missing collection.xml")]
     #[api(code = "import:zip:collection-xml-missing", status = "BAD_REQUEST")]
     ColxmlMissing,
+    /// A file in the ZIP archive has an invalid name.
+    #[fail(display = "Invalid file name in archive: {}", _0)]
+    #[api(code = "import:zip:invalid-file-name", status = "BAD_REQUEST")]
+    InvalidFileName(String),
     /// There was a problem obtaining database connection.
     #[fail(display = "Cannot obtain database connection: {}", _0)]
     #[api(internal)]
@@ -627,6 +797,34 @@ impl Collection {
 
         Ok(Collection { title, content })
     }
+
+    /// List all modules in this collection, in the order in which they are
+    /// imported by [`Importer::load_collection_zip`].
+    fn modules(&self) -> Vec<&ModuleElement> {
+        let mut modules = Vec::new();
+        let mut queue = vec![&self.content];
+
+        while let Some(content) = queue.pop() {
+            for element in content {
+                match element {
+                    Element::Module(module) => modules.push(module),
+                    Element::Subcollection(Subcollection { content, .. }) =>
+                        queue.push(content),
+                }
+            }
+        }
+
+        modules
+    }
+
+    /// List titles of all modules in this collection, in the order in which
+    /// they are imported by [`Importer::load_collection_zip`].
+    fn module_titles(&self) -> Vec<&str> {
+        self.modules()
+            .into_iter()
+            .map(|module| module.title.as_str())
+            .collect()
+    }
 }
 
 impl Element {
@@ -684,3 +882,98 @@ impl Subcollection {
         Ok(Subcollection { title, content })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{db, models::fixtures, processing::export};
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn exported_module_round_trips() {
+        let db = db::test_connection();
+
+        let team = fixtures::team(&db);
+        let index = File::from_data(&db, fixtures::INDEX, None).unwrap();
+        let media = File::from_data(&db, b"exported_module_round_trips", None)
+            .unwrap();
+        let module = Module::create(
+            &db, &team, "Exported", "en", index, vec![("media.txt", media)])
+            .unwrap();
+
+        let mut output = export::export_module(
+            &db, &module, tempfile::tempfile().unwrap()).unwrap();
+        let mut zip = ZipArchive::new(&mut output).unwrap();
+        let (_, base) = find_file(&mut zip, "index.cnxml").unwrap().unwrap();
+
+        assert_eq!(base, Path::new(&module.id().to_string()));
+
+        let ModuleZip { title, language, index, files } =
+            read_module(&mut zip, &base, &mut Store(&db)).unwrap()
+                .parse().unwrap();
+
+        assert_eq!(title, "Test");
+        assert_eq!(language, "en");
+        assert_eq!(index.id, module.index);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "media.txt");
+        assert_eq!(files[0].1.read_to_string().unwrap(),
+            "exported_module_round_trips");
+    }
+
+    #[test]
+    #[ignore]
+    fn exported_book_round_trips() {
+        let db = db::test_connection();
+
+        let team = fixtures::team(&db);
+        let first = fixtures::module(&db, &team, "First");
+        let second = fixtures::module(&db, &team, "Second");
+        let book = Book::create(&db, &team, "Exported").unwrap();
+        let root = book.root_part(&db).unwrap();
+        root.insert_module(&db, 0, "Introduction", &first).unwrap();
+        root.create_group(&db, 1, "Group").unwrap()
+            .insert_module(&db, 0, "Chapter", &second).unwrap();
+
+        let mut output = export::export_book(
+            &db, &book, tempfile::tempfile().unwrap()).unwrap();
+        let mut zip = ZipArchive::new(&mut output).unwrap();
+        let (inx, base) = find_file(&mut zip, "collection.xml").unwrap()
+            .unwrap();
+
+        assert_eq!(base, Path::new(&book.id().to_string()));
+
+        let mut colxml = String::new();
+        zip.by_index(inx).unwrap().read_to_string(&mut colxml).unwrap();
+        let collection = Collection::from_xml(
+            &XmlElement::from_str(&colxml).unwrap()).unwrap();
+
+        assert_eq!(collection.title, "Exported");
+
+        let modules = collection.modules();
+        let mut titles = modules.iter()
+            .map(|module| module.title.as_str())
+            .collect::<Vec<_>>();
+        titles.sort();
+
+        assert_eq!(titles, ["Chapter", "Introduction"]);
+
+        for element in modules {
+            let expected = if element.title == "Chapter" {
+                &second
+            } else {
+                &first
+            };
+
+            assert_eq!(element.document, expected.id().to_string());
+
+            let ModuleZip { index, .. } = read_module(
+                &mut zip, &base.join(&element.document), &mut Store(&db))
+                .unwrap()
+                .parse()
+                .unwrap();
+
+            assert_eq!(index.id, expected.index);
+        }
+    }
+}
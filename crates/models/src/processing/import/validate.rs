@@ -0,0 +1,341 @@
+//! Validating ZIPs of modules and collections without importing them.
+
+use minidom::Element as XmlElement;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::{fs, io::Read, path::{Path, PathBuf}};
+use tempfile::NamedTempFile;
+use zip::ZipArchive;
+
+use crate::processing::media::{MEDIA_ELEMENTS, is_local};
+use super::{
+    CNXML_NS,
+    Document,
+    ImportError,
+    Importer,
+    ModuleElement,
+    ModuleSink,
+    find_file,
+    read_module,
+};
+
+/// Result of validating a ZIP archive.
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    /// Modules found in the archive.
+    pub modules: Vec<ModuleReport>,
+    /// Files which would be skipped during import as CNX-specific artefacts.
+    pub skipped: Vec<String>,
+    /// Files which would be ignored during import as they lie outside of
+    /// the archive's base path.
+    pub outside: Vec<String>,
+}
+
+/// Result of validating a single module in a ZIP archive.
+#[derive(Debug, Default, Serialize)]
+pub struct ModuleReport {
+    /// Path of the module's directory within the archive.
+    pub path: String,
+    /// Module's title, if it could be determined.
+    pub title: Option<String>,
+    /// Module's language, if it could be determined.
+    pub language: Option<String>,
+    /// Names of media files which would be imported with this module.
+    pub media: Vec<String>,
+    /// Names of media files referenced from `index.cnxml` which are not
+    /// present in the archive.
+    pub missing_media: Vec<String>,
+    /// Problems found in `index.cnxml`.
+    pub errors: Vec<CnxmlError>,
+}
+
+/// A problem found in a CNXML document.
+#[derive(Debug, Serialize)]
+pub struct CnxmlError {
+    /// Line at which the problem was found, if known.
+    pub line: Option<usize>,
+    /// Column at which the problem was found, if known.
+    pub column: Option<usize>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl ValidationReport {
+    /// Would importing this archive succeed?
+    pub fn is_valid(&self) -> bool {
+        self.modules.iter().all(ModuleReport::is_valid)
+    }
+}
+
+impl ModuleReport {
+    /// Would importing this module succeed?
+    ///
+    /// Note that missing media do not prevent a module from being imported.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error<M: ToString>(&mut self, message: M) {
+        self.errors.push(CnxmlError {
+            line: None,
+            column: None,
+            message: message.to_string(),
+        });
+    }
+}
+
+impl Importer {
+    /// Validate a zipped module, reading it the same way as
+    /// [`Importer::process_module_zip`] but without creating any files.
+    pub(super) fn validate_module_zip(&mut self, mut file: NamedTempFile)
+    -> Result<ValidationReport, ImportError> {
+        let mut zip = ZipArchive::new(file.as_file_mut())?;
+        let (_, base_path) = find_file(&mut zip, "index.cnxml")?
+            .ok_or(ImportError::IndexMissing)?;
+
+        let mut sink = DryRun::new(&base_path);
+        let module = validate_module(&mut zip, &base_path, None, &mut sink)?;
+
+        Ok(sink.into_report(vec![module]))
+    }
+
+    /// Validate a zipped collection, reading it the same way as
+    /// [`Importer::load_collection_zip`] but without creating any files or
+    /// database entries.
+    pub(super) fn validate_collection_zip(&mut self, mut file: NamedTempFile)
+    -> Result<ValidationReport, ImportError> {
+        let (mut zip, coldata, base) =
+            self.preprocess_collection_zip(&mut file)?;
+
+        let mut sink = DryRun::new(&base);
+        let mut modules = Vec::new();
+
+        for ModuleElement { title, document } in coldata.modules() {
+            let path = base.join(document);
+            modules.push(validate_module(
+                &mut zip, &path, Some(title.clone()), &mut sink)?);
+        }
+
+        Ok(sink.into_report(modules))
+    }
+}
+
+/// A [`ModuleSink`] which only records which files were skipped, without
+/// storing anything.
+struct DryRun {
+    /// Base path of the archive. Files outside of it are reported as ignored.
+    base: PathBuf,
+    skipped: Vec<String>,
+    outside: Vec<String>,
+}
+
+impl DryRun {
+    fn new(base: &Path) -> DryRun {
+        DryRun {
+            base: base.to_owned(),
+            skipped: Vec::new(),
+            outside: Vec::new(),
+        }
+    }
+
+    fn into_report(self, modules: Vec<ModuleReport>) -> ValidationReport {
+        ValidationReport {
+            modules,
+            skipped: self.skipped,
+            outside: self.outside,
+        }
+    }
+}
+
+impl ModuleSink for DryRun {
+    type File = ();
+
+    fn file<R: Read>(&mut self, _: R, _: Option<&str>)
+    -> Result<(), ImportError> {
+        Ok(())
+    }
+
+    fn skipped(&mut self, path: &Path) {
+        push_path(&mut self.skipped, path);
+    }
+
+    // In collections modules are read one at a time, and files of other
+    // modules are only outside of the module being read.
+    fn outside(&mut self, path: &Path) {
+        if !path.starts_with(&self.base) {
+            push_path(&mut self.outside, path);
+        }
+    }
+}
+
+/// Add a path to a list, unless it's already there.
+fn push_path(list: &mut Vec<String>, path: &Path) {
+    let path = path.to_string_lossy();
+
+    if !list.iter().any(|item| *item == path) {
+        list.push(path.into_owned());
+    }
+}
+
+/// Validate a single module located in `path` within a ZIP archive.
+///
+/// Modules in collections take their titles from `collection.xml`, and for
+/// them `title` should be specified.
+fn validate_module(
+    zip: &mut ZipArchive<&mut fs::File>,
+    path: &Path,
+    title: Option<String>,
+    sink: &mut DryRun,
+) -> Result<ModuleReport, ImportError> {
+    let mut report = ModuleReport {
+        path: path.to_string_lossy().into_owned(),
+        title,
+        .. ModuleReport::default()
+    };
+
+    let module = match read_module(zip, path, sink) {
+        Ok(module) => module,
+        Err(ImportError::IndexMissing) => {
+            report.error(ImportError::IndexMissing);
+            return Ok(report);
+        }
+        Err(err) => return Err(err),
+    };
+
+    report.media = module.files.into_iter().map(|(name, ())| name).collect();
+
+    match String::from_utf8(module.cnxml) {
+        Ok(data) => validate_cnxml(&mut report, &data),
+        Err(err) => report.error(err),
+    }
+
+    Ok(report)
+}
+
+/// Check that a CNXML document can be parsed and describes a valid module.
+pub fn check_cnxml(data: &str) -> Vec<CnxmlError> {
+    let mut report = ModuleReport::default();
+    validate_cnxml(&mut report, data);
+    report.errors
+}
+
+/// Validate contents of `index.cnxml`, recording results in `report`.
+fn validate_cnxml(report: &mut ModuleReport, data: &str) {
+    let mut reader = Reader::from_str(data);
+
+    let root = match XmlElement::from_reader(&mut reader) {
+        Ok(root) => root,
+        Err(err) => {
+            let (line, column) = position(data, reader.buffer_position());
+            report.errors.push(CnxmlError {
+                line: Some(line),
+                column: Some(column),
+                message: err.to_string(),
+            });
+            return;
+        }
+    };
+
+    match Document::from_xml(&root) {
+        Ok(Document { title, language }) => {
+            // Title of a module in a collection is taken from collection.xml.
+            if report.title.is_none() {
+                report.title = Some(title);
+            }
+            report.language = Some(language);
+        }
+        Err(err) => report.error(err),
+    }
+
+    for src in referenced_media(&root) {
+        if !report.media.iter().any(|name| name == src)
+        && !report.missing_media.iter().any(|name| name == src) {
+            report.missing_media.push(src.to_string());
+        }
+    }
+}
+
+/// Find all local media files referenced from a CNXML document.
+fn referenced_media(root: &XmlElement) -> Vec<&str> {
+    let mut media = Vec::new();
+    let mut queue = vec![root];
+
+    while let Some(element) = queue.pop() {
+        if element.is("media", CNXML_NS) {
+            media.extend(element.children()
+                .filter(|child| child.has_ns(CNXML_NS)
+                    && MEDIA_ELEMENTS.contains(&child.name()))
+                .filter_map(|child| child.attr("src"))
+                .filter(|src| is_local(src)));
+        }
+
+        queue.extend(element.children());
+    }
+
+    media
+}
+
+/// Convert a byte offset into a line and a column number, both starting at 1.
+fn position(data: &str, offset: usize) -> (usize, usize) {
+    let before = &data.as_bytes()[..offset.min(data.len())];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    let column = before.iter().rev().take_while(|&&b| b != b'\n').count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_missing_media() {
+        let mut report = ModuleReport {
+            media: vec!["present.png".to_string()],
+            .. ModuleReport::default()
+        };
+
+        validate_cnxml(&mut report, r#"<?xml version="1.0"?>
+<document xmlns="http://cnx.rice.edu/cnxml" xml:lang="en">
+    <title>Test</title>
+    <content>
+        <figure id="f1">
+            <media alt="">
+                <image mime-type="image/png" src="present.png"/>
+            </media>
+        </figure>
+        <figure id="f2">
+            <media alt="">
+                <image mime-type="image/png" src="missing.png"/>
+                <image mime-type="image/png" src="https://example.com/a.png"/>
+            </media>
+        </figure>
+    </content>
+</document>"#);
+
+        assert!(report.is_valid());
+        assert_eq!(report.title.as_ref().unwrap(), "Test");
+        assert_eq!(report.language.as_ref().unwrap(), "en");
+        assert_eq!(report.missing_media, ["missing.png"]);
+    }
+
+    #[test]
+    fn reports_position_of_malformed_xml() {
+        let mut report = ModuleReport::default();
+
+        validate_cnxml(&mut report, "<document>\n  <title>Test</titel>\n</document>");
+
+        assert!(!report.is_valid());
+        assert_eq!(report.errors[0].line, Some(2));
+    }
+
+    #[test]
+    fn reports_schema_errors() {
+        let mut report = ModuleReport::default();
+
+        validate_cnxml(&mut report,
+            r#"<document xmlns="http://cnx.rice.edu/cnxml" xml:lang="en"/>"#);
+
+        assert!(!report.is_valid());
+        assert_eq!(report.errors[0].line, None);
+    }
+}
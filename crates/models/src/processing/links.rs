@@ -0,0 +1,335 @@
+//! Verification of links and references within documents.
+
+use adaptarr_error::ApiError;
+use adaptarr_macros::From;
+use diesel::result::Error as DbError;
+use failure::Fail;
+use minidom::Element;
+use serde::Serialize;
+use std::{collections::{HashMap, HashSet}, io, str::FromStr};
+use uuid::Uuid;
+
+use crate::{
+    db::Connection,
+    models::{AssertExists, Book, Document, Model, Module, Optional},
+};
+use super::media::{MEDIA_ELEMENTS, is_local};
+
+/// A single broken link or reference.
+#[derive(Debug, Serialize)]
+pub struct LinkProblem {
+    /// ID of the module containing the broken link.
+    pub module: Uuid,
+    /// ID of the closest element containing the broken link which has an ID.
+    pub element: Option<String>,
+    #[serde(flatten)]
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "problem", rename_all = "kebab-case")]
+pub enum ProblemKind {
+    /// Link points to an element which doesn't exist in the same document.
+    MissingTarget {
+        target: String,
+    },
+    /// Link points to a module which doesn't exist.
+    MissingModule {
+        document: Uuid,
+    },
+    /// Link points to a module using an identifier which is not a UUID, for
+    /// example a legacy CNX ID (`m12345`), and so can't be verified.
+    ExternalModule {
+        document: String,
+    },
+    /// Link points to an element which doesn't exist in another module.
+    MissingModuleTarget {
+        document: Uuid,
+        target: String,
+    },
+    /// Link points to a module which is not part of the book being checked.
+    ModuleNotInBook {
+        document: Uuid,
+    },
+    /// Link or media element references a file not present in the document.
+    MissingFile {
+        name: String,
+    },
+}
+
+impl LinkProblem {
+    /// Is this link broken, as opposed to only being impossible to verify?
+    pub fn is_broken(&self) -> bool {
+        match self.kind {
+            ProblemKind::ExternalModule { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+/// Check all links and media references in a module or a draft.
+///
+/// `module` is the ID of the module this document belongs to. Links to that
+/// module are treated as links within the same document.
+pub fn check_document(db: &Connection, module: Uuid, document: &Document)
+-> Result<Vec<LinkProblem>, CheckLinksError> {
+    let mut checker = Checker::new(db, None);
+    checker.check(module, document)?;
+    Ok(checker.problems)
+}
+
+/// Check all links and media references in all modules of a book.
+///
+/// In addition to checks done by [`check_document`] this will also report links
+/// to modules which are not part of the book.
+pub fn check_book(db: &Connection, book: &Book)
+-> Result<Vec<LinkProblem>, CheckLinksError> {
+    let modules = book.root_part(db)?.get_tree(db)?.modules();
+
+    let mut checker = Checker::new(db, Some(modules.iter().copied().collect()));
+
+    for id in modules {
+        let module = Module::by_id(db, id).assert_exists()?;
+        checker.check(id, &module)?;
+    }
+
+    Ok(checker.problems)
+}
+
+struct Checker<'db> {
+    db: &'db Connection,
+    /// Modules in the book being checked, if any.
+    book: Option<HashSet<Uuid>>,
+    /// IDs of elements in other modules, or `None` for modules which don't
+    /// exist.
+    ///
+    /// Links can point at any element with an ID, not only at those listed as
+    /// cross-reference targets, so we need to look at documents' content.
+    modules: HashMap<Uuid, Option<HashSet<String>>>,
+    problems: Vec<LinkProblem>,
+}
+
+/// Links and media references found in a document.
+#[derive(Default)]
+struct References<'a> {
+    /// IDs of all elements.
+    ids: HashSet<&'a str>,
+    /// Links, as (context, document, target-id) triples.
+    links: Vec<(Option<&'a str>, Option<&'a str>, Option<&'a str>)>,
+    /// References to files, as (context, name) pairs.
+    files: Vec<(Option<&'a str>, &'a str)>,
+}
+
+impl<'db> Checker<'db> {
+    fn new(db: &'db Connection, book: Option<HashSet<Uuid>>) -> Self {
+        Checker {
+            db,
+            book,
+            modules: HashMap::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn check(&mut self, module: Uuid, document: &Document)
+    -> Result<(), CheckLinksError> {
+        let content = document.get_file(self.db, "index.cnxml")
+            .assert_exists()?
+            .read_to_string()?;
+        let root = Element::from_str(&content)?;
+
+        let mut refs = References::default();
+        refs.collect(&root, None);
+
+        let files = document.get_files(self.db)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<HashSet<_>>();
+
+        for (context, name) in refs.files {
+            if !files.contains(name) {
+                self.report(module, context, ProblemKind::MissingFile {
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        for (context, target_document, target) in refs.links {
+            let kind = match target_document {
+                None => match target {
+                    Some(target) if !refs.ids.contains(target) =>
+                        ProblemKind::MissingTarget {
+                            target: target.to_string(),
+                        },
+                    _ => continue,
+                },
+                Some(other) => match Uuid::parse_str(other) {
+                    Ok(id) if id == module => match target {
+                        Some(target) if !refs.ids.contains(target) =>
+                            ProblemKind::MissingTarget {
+                                target: target.to_string(),
+                            },
+                        _ => continue,
+                    },
+                    Ok(id) => match self.check_module_link(id, target)? {
+                        Some(kind) => kind,
+                        None => continue,
+                    },
+                    Err(_) => ProblemKind::ExternalModule {
+                        document: other.to_string(),
+                    },
+                },
+            };
+
+            self.report(module, context, kind);
+        }
+
+        Ok(())
+    }
+
+    /// Check a link to another module.
+    fn check_module_link(&mut self, module: Uuid, target: Option<&str>)
+    -> Result<Option<ProblemKind>, CheckLinksError> {
+        let ids = match self.module_ids(module)? {
+            Some(ids) => ids,
+            None => return Ok(Some(ProblemKind::MissingModule {
+                document: module,
+            })),
+        };
+
+        if let Some(target) = target {
+            if !ids.contains(target) {
+                return Ok(Some(ProblemKind::MissingModuleTarget {
+                    document: module,
+                    target: target.to_string(),
+                }));
+            }
+        }
+
+        if let Some(ref book) = self.book {
+            if !book.contains(&module) {
+                return Ok(Some(ProblemKind::ModuleNotInBook {
+                    document: module,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get IDs of all elements in a module, or `None` if it doesn't exist.
+    fn module_ids(&mut self, id: Uuid)
+    -> Result<Option<&HashSet<String>>, CheckLinksError> {
+        if !self.modules.contains_key(&id) {
+            let ids = match Module::by_id(self.db, id).optional()? {
+                Some(module) => {
+                    let content = module.get_file(self.db, "index.cnxml")
+                        .assert_exists()?
+                        .read_to_string()?;
+                    let root = Element::from_str(&content)?;
+                    let mut refs = References::default();
+                    refs.collect(&root, None);
+                    Some(refs.ids.into_iter().map(str::to_string).collect())
+                }
+                None => None,
+            };
+
+            self.modules.insert(id, ids);
+        }
+
+        Ok(self.modules[&id].as_ref())
+    }
+
+    fn report(&mut self, module: Uuid, context: Option<&str>, kind: ProblemKind) {
+        self.problems.push(LinkProblem {
+            module,
+            element: context.map(str::to_string),
+            kind,
+        });
+    }
+}
+
+impl<'a> References<'a> {
+    fn collect(&mut self, element: &'a Element, context: Option<&'a str>) {
+        let context = match element.attr("id") {
+            Some(id) => {
+                self.ids.insert(id);
+                Some(id)
+            }
+            None => context,
+        };
+
+        if element.name() == "link" {
+            let document = element.attr("document");
+            let target = element.attr("target-id");
+
+            if document.is_some() || target.is_some() {
+                self.links.push((context, document, target));
+            }
+
+            if let Some(resource) = element.attr("resource") {
+                self.files.push((context, resource));
+            }
+        } else if MEDIA_ELEMENTS.contains(&element.name()) {
+            match element.attr("src") {
+                Some(src) if is_local(src) => self.files.push((context, src)),
+                _ => {}
+            }
+        }
+
+        for child in element.children() {
+            self.collect(child, context);
+        }
+    }
+}
+
+#[derive(ApiError, Debug, Fail, From)]
+pub enum CheckLinksError {
+    /// Database error.
+    #[fail(display = "Database error: {}", _0)]
+    #[api(internal)]
+    Database(#[cause] #[from] DbError),
+    /// Document is not valid XML.
+    #[fail(display = "Invalid XML: {}", _0)]
+    #[api(code = "document:links:invalid-xml", status = "BAD_REQUEST")]
+    InvalidXml(#[cause] #[from] minidom::Error),
+    /// An operating system error.
+    #[fail(display = "System error: {}", _0)]
+    #[api(internal)]
+    System(#[cause] #[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_references() {
+        let root = Element::from_str(r#"
+            <document xmlns="http://cnx.rice.edu/cnxml" id="d">
+                <para id="p">
+                    <link target-id="f"/>
+                    <link document="m" target-id="x"/>
+                    <link url="http://example.com/"/>
+                    <link resource="data.csv"/>
+                </para>
+                <figure id="f">
+                    <media alt=""><image src="a.png"/></media>
+                    <media alt=""><image src="http://example.com/b.png"/></media>
+                </figure>
+            </document>
+        "#).unwrap();
+
+        let mut refs = References::default();
+        refs.collect(&root, None);
+
+        assert_eq!(refs.ids, ["d", "p", "f"].iter().copied().collect());
+        assert_eq!(refs.links, [
+            (Some("p"), None, Some("f")),
+            (Some("p"), Some("m"), Some("x")),
+        ]);
+        assert_eq!(refs.files, [
+            (Some("p"), "data.csv"),
+            (Some("f"), "a.png"),
+        ]);
+    }
+}
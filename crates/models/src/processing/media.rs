@@ -0,0 +1,52 @@
+//! References to media files from CNXML documents.
+
+/// Names of CNXML elements which reference media files through their `src`
+/// attribute.
+pub const MEDIA_ELEMENTS: &[&str] = &[
+    "audio",
+    "download",
+    "flash",
+    "image",
+    "java-applet",
+    "labview",
+    "object",
+    "video",
+];
+
+/// Check whether a reference points to a file within the same module, as
+/// opposed to an external resource.
+///
+/// URLs with a scheme (including `data:` and `mailto:` URLs) and absolute
+/// paths are external.
+pub fn is_local(src: &str) -> bool {
+    !src.is_empty() && !src.starts_with('/') && !has_scheme(src)
+}
+
+/// Check whether a reference begins with a URL scheme.
+fn has_scheme(src: &str) -> bool {
+    let scheme = match src.find(':') {
+        Some(end) => &src[..end],
+        None => return false,
+    };
+
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric()
+            || c == '+' || c == '-' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_references() {
+        assert!(is_local("image.png"));
+        assert!(is_local("figures/image.png"));
+
+        assert!(!is_local(""));
+        assert!(!is_local("/image.png"));
+        assert!(!is_local("https://example.com/image.png"));
+        assert!(!is_local("data:image/png;base64,AAAA"));
+        assert!(!is_local("mailto:editor@example.com"));
+    }
+}
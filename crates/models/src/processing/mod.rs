@@ -1,7 +1,12 @@
 //! Routines dedicated to processing uploaded data.
 
+pub mod diff;
+pub mod export;
 pub mod import;
+pub mod links;
+pub mod search;
 
+mod media;
 mod xref_targets;
 
 pub use self::{
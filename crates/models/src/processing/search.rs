@@ -0,0 +1,236 @@
+//! Full-text search over modules, books, and drafts.
+
+use diesel::{expression::dsl::any, prelude::*, result::Error as DbError};
+use minidom::{Element, Node};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::{
+    Connection,
+    functions::{matches, search_query, search_vector, ts_rank},
+    models as db,
+    schema::{
+        books,
+        document_search,
+        documents,
+        draft_slots,
+        drafts,
+        modules,
+    },
+};
+
+const CNXML_NS: &str = "http://cnx.rice.edu/cnxml";
+
+/// Maximal number of results of each kind returned by [`search`].
+const LIMIT: i64 = 50;
+
+/// A single search result.
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub kind: ResultKind,
+    /// ID of the module, book, or draft.
+    pub id: Uuid,
+    pub title: String,
+    /// How well does this result match the query. Higher is better.
+    pub rank: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResultKind {
+    Module,
+    Book,
+    Draft,
+}
+
+/// Which drafts can be returned by [`search`].
+#[derive(Clone, Copy, Debug)]
+pub enum DraftScope<'a> {
+    /// All drafts in searched teams.
+    All,
+    /// Only drafts a user has access to, that is drafts in which they occupy
+    /// a slot, and all drafts in teams in which they can manage processes.
+    User {
+        user: i32,
+        /// Teams in which the user has the `TeamPermissions::MANAGE_PROCESS`
+        /// permission.
+        manage: &'a [i32],
+    },
+}
+
+/// Search for modules, books, and drafts in `teams`.
+///
+/// Modules are matched by their title and content, books and drafts only by
+/// their titles. Results are ordered by their rank, best first.
+pub fn search(db: &Connection, query: &str, teams: &[i32], scope: DraftScope)
+-> Result<Vec<SearchResult>, DbError> {
+    let modules = modules::table
+        .inner_join(documents::table.inner_join(document_search::table))
+        .filter(modules::team.eq(any(teams)))
+        .filter(matches(
+            search_vector(document_search::content), search_query(query)))
+        .select((
+            modules::id,
+            documents::title,
+            ts_rank(search_vector(document_search::content), search_query(query)),
+        ))
+        .order_by(ts_rank(
+            search_vector(document_search::content), search_query(query)).desc())
+        .limit(LIMIT)
+        .get_results::<(Uuid, String, f32)>(db)?;
+
+    let books = books::table
+        .filter(books::team.eq(any(teams)))
+        .filter(matches(search_vector(books::title), search_query(query)))
+        .select((
+            books::id,
+            books::title,
+            ts_rank(search_vector(books::title), search_query(query)),
+        ))
+        .order_by(ts_rank(
+            search_vector(books::title), search_query(query)).desc())
+        .limit(LIMIT)
+        .get_results::<(Uuid, String, f32)>(db)?;
+
+    let mut drafts = drafts::table
+        .inner_join(documents::table)
+        .filter(drafts::team.eq(any(teams)))
+        .filter(matches(search_vector(documents::title), search_query(query)))
+        .select((
+            drafts::module,
+            documents::title,
+            ts_rank(search_vector(documents::title), search_query(query)),
+        ))
+        .into_boxed();
+
+    if let DraftScope::User { user, manage } = scope {
+        drafts = drafts.filter(drafts::team.eq(any(manage))
+            .or(drafts::module.eq_any(draft_slots::table
+                .filter(draft_slots::user.eq(user))
+                .select(draft_slots::draft))));
+    }
+
+    let drafts = drafts
+        .order_by(ts_rank(
+            search_vector(documents::title), search_query(query)).desc())
+        .limit(LIMIT)
+        .get_results::<(Uuid, String, f32)>(db)?;
+
+    let mut results = Vec::with_capacity(modules.len() + books.len() + drafts.len());
+
+    for (kind, items) in vec![
+        (ResultKind::Module, modules),
+        (ResultKind::Book, books),
+        (ResultKind::Draft, drafts),
+    ] {
+        results.extend(items.into_iter().map(|(id, title, rank)| SearchResult {
+            kind, id, title, rank,
+        }));
+    }
+
+    results.sort_by(|a, b| b.rank.partial_cmp(&a.rank)
+        .unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(results)
+}
+
+/// Update search index for a document.
+pub(crate) fn index_document(
+    db: &Connection,
+    document: &db::Document,
+    root: &Element,
+) -> Result<(), DbError> {
+    let mut content = document.title.clone();
+    text_content(root, &mut content);
+
+    let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    diesel::insert_into(document_search::table)
+        .values(&db::NewDocumentSearch {
+            document: document.id,
+            content: &content,
+        })
+        .on_conflict(document_search::document)
+        .do_update()
+        .set(document_search::content.eq(&content))
+        .execute(db)?;
+
+    Ok(())
+}
+
+/// Collect text content of an element, separating text of different elements
+/// with spaces.
+fn text_content(element: &Element, text: &mut String) {
+    // Metadata contains no text meaningful to users.
+    if element.is("metadata", CNXML_NS) {
+        return;
+    }
+
+    for node in element.nodes() {
+        match node {
+            Node::Text(t) => text.push_str(t),
+            Node::Element(e) => {
+                text.push(' ');
+                text_content(e, text);
+                text.push(' ');
+            }
+            Node::Comment(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{db, models::{Model, User, fixtures}};
+    use super::*;
+
+    #[test]
+    fn text_content_skips_metadata() {
+        let root = Element::from_str(concat!(
+            r#"<document xmlns="http://cnx.rice.edu/cnxml">"#,
+            "<metadata>Hidden</metadata>",
+            "<title>Title</title>",
+            "<para>Some <emphasis>text</emphasis></para>",
+            "</document>",
+        )).unwrap();
+        let mut text = String::new();
+        text_content(&root, &mut text);
+
+        assert_eq!(text.split_whitespace().collect::<Vec<_>>(),
+            ["Title", "Some", "text"]);
+    }
+
+    #[test]
+    #[ignore]
+    fn only_accessible_drafts_are_found() {
+        let _system = actix::System::new("test");
+        let db = db::test_connection();
+
+        let mut team = fixtures::team(&db);
+        let editor = fixtures::member(&db, &mut team);
+        let other = fixtures::member(&db, &mut team);
+        let process = fixtures::process(&db, &team);
+        let module = fixtures::module(&db, &team, "Zymurgy");
+        fixtures::draft(&db, &module, &process, &editor);
+
+        let teams = [team.id()];
+        let found = |user: &User, manage: &[i32]| {
+            search(&db, "zymurgy", &teams, DraftScope::User {
+                user: user.id(),
+                manage,
+            })
+                .unwrap()
+                .into_iter()
+                .any(|result| match result.kind {
+                    ResultKind::Draft => result.id == module.id(),
+                    _ => false,
+                })
+        };
+
+        assert!(found(&editor, &[]));
+        assert!(!found(&other, &[]));
+        assert!(found(&other, &teams));
+    }
+}
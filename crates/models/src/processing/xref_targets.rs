@@ -27,7 +27,8 @@ use crate::{
 
 const CNXML_NS: &str = "http://cnx.rice.edu/cnxml";
 
-/// Process a document to create list of cross-reference targets within it.
+/// Process a document to create list of cross-reference targets within it,
+/// and to update its entry in the search index.
 ///
 /// This function will insert new records into database, but will do so without
 /// a transaction. You'll probably want to wrap it in one.
@@ -40,6 +41,8 @@ pub fn process_document(db: &Connection, document: &db::Document)
     let content = index.read_to_string()?;
     let root = Element::from_str(&content)?;
 
+    super::search::index_document(db, document, &root)?;
+
     let mut last_context = None;
     let mut counters = HashMap::with_capacity(8);
 
@@ -0,0 +1,67 @@
+use std::{fs, io, path::PathBuf};
+use tempfile::NamedTempFile;
+
+use super::{Backend, Object};
+
+/// Backend storing files in a directory on a local filesystem.
+#[derive(Debug)]
+pub struct Filesystem {
+    path: PathBuf,
+}
+
+impl Filesystem {
+    pub fn new(path: PathBuf) -> Filesystem {
+        Filesystem { path }
+    }
+}
+
+impl Backend for Filesystem {
+    fn locate(&self, name: &str) -> String {
+        self.path.join(name).to_str().expect("invalid path").to_string()
+    }
+
+    fn store(&self, name: &str, file: NamedTempFile) -> io::Result<()> {
+        file.persist(self.path.join(name))
+            .map(|_| ())
+            .map_err(|err| err.error)
+    }
+
+    fn open(&self, name: &str) -> io::Result<Object> {
+        fs::File::open(self.path.join(name)).map(Object::Local)
+    }
+
+    fn size(&self, name: &str) -> io::Result<u64> {
+        fs::metadata(self.path.join(name)).map(|metadata| metadata.len())
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        fs::remove_file(self.path.join(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn stores_and_removes_files() {
+        let dir = TempDir::new().unwrap();
+        let backend = Filesystem::new(dir.path().to_path_buf());
+
+        let mut file = NamedTempFile::new_in(dir.path()).unwrap();
+        file.write_all(b"contents").unwrap();
+        backend.store("abcd", file).unwrap();
+
+        let mut contents = String::new();
+        backend.open("abcd").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "contents");
+        assert_eq!(backend.size("abcd").unwrap(), 8);
+
+        backend.remove("abcd").unwrap();
+        assert_eq!(
+            backend.open("abcd").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
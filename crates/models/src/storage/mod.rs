@@ -0,0 +1,118 @@
+//! Backends storing contents of files.
+//!
+//! Contents of files are addressed by names, which are hex-encoded hashes of
+//! those contents. Since contents of a name never change once stored, backends
+//! don't need to concern themselves with updates or invalidation.
+
+use adaptarr_util::SingleInit;
+use std::{fmt, fs, io::{self, Read, Seek, SeekFrom}};
+use tempfile::NamedTempFile;
+
+use crate::config::Storage as Config;
+
+mod filesystem;
+mod s3;
+
+pub use self::{filesystem::Filesystem, s3::S3};
+
+static BACKEND: SingleInit<Box<dyn Backend>> = SingleInit::uninit();
+
+/// A backend storing contents of files.
+pub trait Backend: Send + Sync {
+    /// Describe location of a file in this backend, such as its path.
+    fn locate(&self, name: &str) -> String;
+
+    /// Store contents of a local file under a name.
+    fn store(&self, name: &str, file: NamedTempFile) -> io::Result<()>;
+
+    /// Open a stored file for reading.
+    ///
+    /// If there is no file with this name an error of kind
+    /// [`io::ErrorKind::NotFound`] is returned.
+    fn open(&self, name: &str) -> io::Result<Object>;
+
+    /// Get size of a stored file, in bytes.
+    fn size(&self, name: &str) -> io::Result<u64>;
+
+    /// Remove a stored file.
+    fn remove(&self, name: &str) -> io::Result<()>;
+}
+
+/// A reader which can also seek.
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Contents of a stored file, opened for reading.
+pub enum Object {
+    /// A file in the local filesystem.
+    Local(fs::File),
+    /// Contents streamed from a remote storage.
+    Remote {
+        reader: Box<dyn ReadSeek>,
+        /// Size of the contents, in bytes.
+        size: u64,
+    },
+}
+
+impl Read for Object {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Object::Local(file) => file.read(buf),
+            Object::Remote { reader, .. } => reader.read(buf),
+        }
+    }
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Local(file) =>
+                fmt.debug_tuple("Local").field(file).finish(),
+            Object::Remote { size, .. } => fmt.debug_struct("Remote")
+                .field("size", size)
+                .finish(),
+        }
+    }
+}
+
+impl Seek for Object {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Object::Local(file) => file.seek(pos),
+            Object::Remote { reader, .. } => reader.seek(pos),
+        }
+    }
+}
+
+/// Get the configured storage backend.
+///
+/// ## Panics
+///
+/// This function will panic if called before [`crate::Config::register`].
+pub fn backend() -> &'static dyn Backend {
+    &**BACKEND.get().expect("model configuration must be initialized before \
+        calling storage::backend")
+}
+
+/// Create and register backend described by `config` as the global backend.
+///
+/// ## Panics
+///
+/// This function will panic if the backend could not be created.
+pub(crate) fn register(config: &Config) {
+    BACKEND.get_or_init(|| match config.s3 {
+        Some(ref s3) => Box::new(S3::new(s3)
+            .expect("could not create S3 storage backend")),
+        None => Box::new(Filesystem::new(config.path.clone())),
+    });
+}
+
+/// Create a temporary file in which to write contents before they are stored.
+///
+/// ## Panics
+///
+/// This function will panic if called before [`crate::Config::register`].
+pub fn temporary() -> io::Result<NamedTempFile> {
+    NamedTempFile::new_in(&crate::Config::global().storage.path)
+}
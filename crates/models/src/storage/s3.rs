@@ -0,0 +1,274 @@
+use bytes::Bytes;
+use failure::Error;
+use futures::{Async, Poll, Stream};
+use rusoto_core::{ByteStream, HttpClient, Region, RusotoError};
+use rusoto_credential::StaticProvider;
+use rusoto_s3::{
+    DeleteObjectRequest,
+    GetObjectError,
+    GetObjectRequest,
+    HeadObjectRequest,
+    PutObjectRequest,
+    S3 as _,
+    S3Client,
+};
+use std::{fs, io::{self, Read, Seek, SeekFrom}};
+use tempfile::NamedTempFile;
+
+use crate::config::S3 as Config;
+use super::{Backend, Object};
+
+/// Size of chunks in which files are uploaded.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Backend storing files in an S3-compatible object storage.
+pub struct S3 {
+    client: S3Client,
+    bucket: String,
+}
+
+impl S3 {
+    pub fn new(config: &Config) -> Result<S3, Error> {
+        let region = match config.endpoint {
+            Some(ref endpoint) => Region::Custom {
+                name: config.region.clone(),
+                endpoint: endpoint.clone(),
+            },
+            None => config.region.parse()?,
+        };
+
+        let credentials = StaticProvider::new_minimal(
+            config.access_key.clone(), config.secret_key.clone());
+        let client = S3Client::new_with(HttpClient::new()?, credentials, region);
+
+        Ok(S3 {
+            client,
+            bucket: config.bucket.clone(),
+        })
+    }
+}
+
+impl Backend for S3 {
+    fn locate(&self, name: &str) -> String {
+        format!("s3://{}/{}", self.bucket, name)
+    }
+
+    fn store(&self, name: &str, file: NamedTempFile) -> io::Result<()> {
+        let file = file.reopen()?;
+        let size = file.metadata()?.len();
+
+        self.client.put_object(PutObjectRequest {
+            bucket: self.bucket.clone(),
+            key: name.to_string(),
+            content_length: Some(size as i64),
+            body: Some(ByteStream::new(FileStream { file })),
+            ..Default::default()
+        }).sync().map_err(to_io_error)?;
+
+        Ok(())
+    }
+
+    fn open(&self, name: &str) -> io::Result<Object> {
+        let size = self.size(name)?;
+
+        Ok(Object::Remote {
+            reader: Box::new(ObjectReader {
+                client: self.client.clone(),
+                bucket: self.bucket.clone(),
+                key: name.to_string(),
+                size,
+                position: 0,
+                body: None,
+            }),
+            size,
+        })
+    }
+
+    fn size(&self, name: &str) -> io::Result<u64> {
+        let head = self.client.head_object(HeadObjectRequest {
+            bucket: self.bucket.clone(),
+            key: name.to_string(),
+            ..Default::default()
+        }).sync().map_err(|err| match err {
+            // HEAD responses have no body, and so S3 can't tell us what kind
+            // of error it was, other than through the status code.
+            RusotoError::Unknown(ref rsp) if rsp.status.as_u16() == 404 =>
+                io::Error::new(io::ErrorKind::NotFound, err.to_string()),
+            err => to_io_error(err),
+        })?;
+
+        Ok(head.content_length.unwrap_or(0) as u64)
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        self.client.delete_object(DeleteObjectRequest {
+            bucket: self.bucket.clone(),
+            key: name.to_string(),
+            ..Default::default()
+        }).sync().map_err(to_io_error)?;
+
+        Ok(())
+    }
+}
+
+/// Stream of chunks of a local file, used as body of uploads.
+struct FileStream {
+    file: fs::File,
+}
+
+impl Stream for FileStream {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, io::Error> {
+        let mut buf = vec![0; CHUNK_SIZE];
+        let len = self.file.read(&mut buf)?;
+
+        if len == 0 {
+            return Ok(Async::Ready(None));
+        }
+
+        buf.truncate(len);
+        Ok(Async::Ready(Some(buf.into())))
+    }
+}
+
+/// Reader streaming contents of an object.
+///
+/// Contents are downloaded as they are read. Seeking drops the current
+/// download, and next read will request contents starting at the new position.
+struct ObjectReader {
+    client: S3Client,
+    bucket: String,
+    key: String,
+    size: u64,
+    position: u64,
+    body: Option<Box<dyn Read + Send>>,
+}
+
+impl ObjectReader {
+    /// Request contents starting at the current position.
+    fn request(&self) -> io::Result<Box<dyn Read + Send>> {
+        let object = self.client.get_object(GetObjectRequest {
+            bucket: self.bucket.clone(),
+            key: self.key.clone(),
+            range: Some(format!("bytes={}-", self.position)),
+            ..Default::default()
+        }).sync().map_err(|err| match err {
+            RusotoError::Service(GetObjectError::NoSuchKey(_)) =>
+                io::Error::new(io::ErrorKind::NotFound, err.to_string()),
+            err => to_io_error(err),
+        })?;
+
+        match object.body {
+            Some(body) => Ok(Box::new(body.into_blocking_read())),
+            None => Ok(Box::new(io::empty())),
+        }
+    }
+}
+
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.body.is_none() {
+            self.body = Some(self.request()?);
+        }
+
+        let len = self.body.as_mut().unwrap().read(buf)?;
+        self.position += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl Seek for ObjectReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => add_offset(self.size, offset),
+            SeekFrom::Current(offset) => add_offset(self.position, offset),
+        }.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+            "invalid seek to a negative position"))?;
+
+        if position != self.position {
+            self.body = None;
+            self.position = position;
+        }
+
+        Ok(position)
+    }
+}
+
+fn add_offset(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    }
+}
+
+fn to_io_error<E>(err: RusotoError<E>) -> io::Error
+where
+    E: std::error::Error + 'static,
+{
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, io::Write};
+
+    use super::*;
+
+    /// Create a backend for the bucket configured in `TEST_S3_*` environment
+    /// variables.
+    fn test_backend() -> S3 {
+        let var = |name| env::var(name).ok();
+        let required = |name| env::var(name)
+            .unwrap_or_else(|_| panic!("{} must be set to run S3 tests", name));
+
+        let config = Config {
+            bucket: required("TEST_S3_BUCKET"),
+            region: var("TEST_S3_REGION")
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            endpoint: var("TEST_S3_ENDPOINT"),
+            access_key: required("TEST_S3_ACCESS_KEY"),
+            secret_key: required("TEST_S3_SECRET_KEY"),
+        };
+
+        S3::new(&config).expect("invalid S3 configuration")
+    }
+
+    #[test]
+    #[ignore]
+    fn stores_reads_and_removes_objects() {
+        let backend = test_backend();
+
+        let data = (0..3 * CHUNK_SIZE).map(|x| x as u8).collect::<Vec<_>>();
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+        backend.store("adaptarr-test", file).unwrap();
+
+        assert_eq!(backend.size("adaptarr-test").unwrap(), data.len() as u64);
+
+        let mut object = backend.open("adaptarr-test").unwrap();
+        let mut contents = Vec::new();
+        object.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, data);
+
+        let offset = CHUNK_SIZE as u64 + 17;
+        object.seek(SeekFrom::Start(offset)).unwrap();
+        let mut buf = [0; 16];
+        object.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[offset as usize..][..16]);
+
+        backend.remove("adaptarr-test").unwrap();
+        assert_eq!(
+            backend.open("adaptarr-test").unwrap_err().kind(),
+            io::ErrorKind::NotFound,
+        );
+    }
+}
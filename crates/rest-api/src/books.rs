@@ -2,23 +2,43 @@ use actix::Addr;
 use actix_web::{
     HttpRequest,
     HttpResponse,
-    http::StatusCode,
+    http::{
+        StatusCode,
+        header::{ContentDisposition, DispositionParam, DispositionType},
+    },
     web::{self, Data, Json, Path, Payload, ServiceConfig},
 };
 use adaptarr_error::Error;
 use adaptarr_models::{
     Book,
     BookPart,
+    BookXrefTarget,
+    Draft,
     FindModelError,
+    ImportJob,
     Model,
     NewTree,
     Team,
     TeamResource,
     Tree,
-    permissions::{EditBook, PermissionBits, TeamPermissions},
-    processing::import::{Importer, ImportBook, ReplaceBook},
+    User,
+    db::types::ImportKind,
+    editing::Process,
+    permissions::{EditBook, ManageProcess, PermissionBits, TeamPermissions},
+    processing::{
+        export,
+        import::{
+            Importer,
+            ImportBook,
+            ReplaceBook,
+            ValidateBook,
+            ValidationReport,
+        },
+        links::{self, LinkProblem},
+    },
 };
 use adaptarr_web::{
+    Accepted,
     ContentType,
     Created,
     Database,
@@ -28,8 +48,8 @@ use adaptarr_web::{
 };
 use diesel::Connection as _;
 use futures::{Future, Stream, future};
-use serde::Deserialize;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Write};
 use tempfile::NamedTempFile;
 use uuid::Uuid;
 
@@ -42,8 +62,9 @@ pub fn configure(cfg: &mut ServiceConfig) {
             .route(web::post()
                 .guard(ContentType::from_mime(&mime::APPLICATION_JSON))
                 .to(create_book))
-            .route(web::post().to_async(create_book_from_zip))
+            .route(web::post().to(create_book_from_zip))
         )
+        .route("/books/validate", web::post().to_async(validate_book_zip))
         .service(web::resource("/books/{id}")
             .route(web::get().to(get_book))
             .route(web::put()
@@ -61,6 +82,10 @@ pub fn configure(cfg: &mut ServiceConfig) {
             .route(web::delete().to(delete_part))
             .route(web::put().to(update_part))
         )
+        .route("/books/{id}/process", web::post().to(begin_process))
+        .route("/books/{id}/export", web::get().to_async(export_book))
+        .route("/books/{id}/xref-targets", web::get().to(list_xref_targets))
+        .route("/books/{id}/broken-links", web::get().to(check_links))
     ;
 }
 
@@ -145,52 +170,63 @@ fn create_book_from_zip(
     importer: Data<Addr<Importer>>,
     session: Session,
     data: Multipart<NewBookZip>,
-) -> Box<dyn Future<Item = Created<String, Json<<Book as Model>::Public>>, Error = Error>> {
+) -> Result<Accepted<String, Json<<ImportJob as Model>::Public>>> {
     let NewBookZip { title, team, file } = data.into_inner();
+    let team = Team::by_id(&db, *team)?;
 
-    let team = match Team::by_id(&db, *team) {
-        Ok(team) => team,
-        Err(err) => return Box::new(future::err(err.into())),
-    };
-
-    match session.user(&db)
-        .map_err(Error::from)
-        .and_then(|user| {
-            if session.is_elevated {
-                Ok(TeamPermissions::all())
-            } else {
-                team.get_member(&db, &user)
-                    .map(|tm| tm.permissions())
-                    .map_err(|err| match err {
-                        FindModelError::NotFound(_) =>
-                            FindModelError::<Book>::not_found(),
-                        FindModelError::Database(_, err) =>
-                            FindModelError::<Book>::from(err),
-                    })
-                    .map_err(Error::from)
-            }
-        })
-        .and_then(|permissions| {
-            permissions
-                .require(TeamPermissions::EDIT_BOOK)
-                .map_err(Error::from)
-        })
-    {
-        Ok(()) => (),
-        Err(err) => return Box::new(future::err(err)),
+    if !session.is_elevated {
+        team.get_member(&db, &session.user(&db)?)
+            .map_err(|err| match err {
+                FindModelError::NotFound(_) =>
+                    FindModelError::<Book>::not_found(),
+                FindModelError::Database(_, err) =>
+                    FindModelError::<Book>::from(err),
+            })?
+            .permissions()
+            .require(TeamPermissions::EDIT_BOOK)?;
     }
 
-    Box::new(importer.send(ImportBook {
-        title, team, file,
+    let job = ImportJob::create(
+        &db, ImportKind::Book, team.id(), Some(session.user_id()), &title,
+        None, None)?;
+    let public = job.get_public_full(&db, &())?;
+    let location = format!("{}/api/v1/imports/{}",
+        req.app_config().host(), job.id());
+
+    importer.do_send(ImportBook {
+        title, team, file, job,
         actor: session.user_id().into(),
-    })
+    });
+
+    Ok(Accepted(location, Json(public)))
+}
+
+/// Validate a ZIP archive of a book, without importing it.
+///
+/// ## Method
+///
+/// ```text
+/// POST /books/validate
+/// ```
+fn validate_book_zip(
+    importer: Data<Addr<Importer>>,
+    _session: Session,
+    payload: Payload,
+) -> Box<dyn Future<Item = Json<ValidationReport>, Error = Error>> {
+    let file = match NamedTempFile::new() {
+        Ok(file) => file,
+        Err(err) => return Box::new(future::err(err.into())),
+    };
+
+    Box::new(payload
         .from_err::<Error>()
+        .fold(file, |mut file, chunk| match file.write_all(chunk.as_ref()) {
+            Ok(_) => future::ok(file),
+            Err(err) => future::err(err),
+        })
+        .and_then(move |file| importer.send(ValidateBook { file }).from_err())
         .and_then(|r| future::result(r).from_err())
-        .map(move |book| {
-            let location = format!("{}/api/v1/books/{}",
-                req.app_config().host(), book.id);
-            Created(location, Json(book.get_public()))
-        }))
+        .map(Json))
 }
 
 /// Get a book by ID.
@@ -237,12 +273,13 @@ fn update_book(
 /// PUT /books/:id
 /// ```
 fn replace_book(
+    req: HttpRequest,
     db: Database,
     importer: Data<Addr<Importer>>,
     scope: TeamScoped<Book, EditBook>,
     session: Session,
     payload: Payload,
-) -> Box<dyn Future<Item = Json<<Book as Model>::Public>, Error = Error>> {
+) -> Box<dyn Future<Item = Accepted<String, Json<<ImportJob as Model>::Public>>, Error = Error>> {
     let book = scope.into_resource();
     let file = match NamedTempFile::new() {
         Ok(file) => file,
@@ -259,13 +296,21 @@ fn replace_book(
             Ok(_) => future::ok(file),
             Err(err) => future::err(err),
         })
-        .and_then(move |file| importer.send(ReplaceBook {
-            team, book, file,
-            actor: session.user_id().into(),
-        }).from_err())
-        .and_then(|r| future::result(r).from_err())
-        .map(|book: Book| book.get_public())
-        .map(Json))
+        .and_then(move |file| -> Result<_> {
+            let job = ImportJob::create(
+                &db, ImportKind::Book, team.id(), Some(session.user_id()),
+                &book.title, None, Some(book.id()))?;
+            let public = job.get_public_full(&db, &())?;
+            let location = format!("{}/api/v1/imports/{}",
+                req.app_config().host(), job.id());
+
+            importer.do_send(ReplaceBook {
+                team, book, file, job,
+                actor: session.user_id().into(),
+            });
+
+            Ok(Accepted(location, Json(public)))
+        }))
 }
 
 /// Delete a book by ID.
@@ -409,3 +454,131 @@ fn update_part(
 
     Ok(HttpResponse::new(StatusCode::NO_CONTENT))
 }
+
+#[derive(Deserialize)]
+struct BeginProcess {
+    process: i32,
+    /// Version of the process to use. Defaults to the current version.
+    version: Option<i32>,
+    /// Mapping from slot IDs to user IDs.
+    slots: Vec<(i32, i32)>,
+    /// Modules for which to begin the process. Defaults to all modules in
+    /// the book.
+    modules: Option<Vec<Uuid>>,
+}
+
+#[derive(Serialize)]
+struct BeginProcessResult {
+    module: Uuid,
+    status: BeginProcessStatus,
+    draft: Option<<Draft as Model>::Public>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum BeginProcessStatus {
+    /// Process was started and a new draft created.
+    Started,
+    /// Module already had a draft.
+    Skipped,
+}
+
+/// Begin a new editing process for modules in a book.
+///
+/// ## Method
+///
+/// ```text
+/// POST /books/:id/process
+/// ```
+fn begin_process(
+    db: Database,
+    scope: TeamScoped<Book, ManageProcess>,
+    session: Session,
+    data: Json<BeginProcess>,
+) -> Result<Json<Vec<BeginProcessResult>>> {
+    let data = data.into_inner();
+    let process = Process::by_id(&db, data.process)?;
+    let version = match data.version {
+        Some(id) => process.get_version(&db, id)?,
+        None => process.get_current(&db)?,
+    };
+
+    let slots = data.slots.into_iter()
+        .map(|(slot, user)| Ok((
+            version.get_slot(&db, slot)?,
+            User::by_id(&db, user)?,
+        )))
+        .collect::<Result<Vec<_>>>()?;
+
+    let user = session.user_id();
+    let filter = data.modules.as_ref().map(Vec::as_slice);
+
+    Ok(Json(scope.resource()
+        .begin_process(&db, &version, &slots, filter)?
+        .into_iter()
+        .map(|(module, draft)| Ok(match draft {
+            Some(draft) => BeginProcessResult {
+                module,
+                status: BeginProcessStatus::Started,
+                draft: Some(draft.get_public_full(&db, &user)?),
+            },
+            None => BeginProcessResult {
+                module,
+                status: BeginProcessStatus::Skipped,
+                draft: None,
+            },
+        }))
+        .collect::<Result<Vec<_>>>()?))
+}
+
+/// Export a book as a ZIP archive.
+///
+/// ## Method
+///
+/// ```text
+/// GET /books/:id/export
+/// ```
+fn export_book(db: Database, scope: TeamScoped<Book>)
+-> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    let book = scope.into_resource();
+
+    // Contents of files may have to be downloaded from a remote storage.
+    Box::new(web::block(move || -> Result<_> {
+        let data = export::export_book(&db, &book, Cursor::new(Vec::new()))?;
+        Ok((book.id(), data))
+    })
+        .from_err()
+        .map(|(id, data)| HttpResponse::Ok()
+            .content_type("application/zip")
+            .set(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![
+                    DispositionParam::Filename(format!("{}.zip", id)),
+                ],
+            })
+            .body(data.into_inner())))
+}
+
+/// Get a list of all possible cross-reference targets within a book.
+///
+/// ## Method
+///
+/// ```text
+/// GET /books/:id/xref-targets
+/// ```
+fn list_xref_targets(db: Database, scope: TeamScoped<Book>)
+-> Result<Json<Vec<BookXrefTarget>>> {
+    Ok(Json(scope.resource().xref_targets(&db)?))
+}
+
+/// Check links and media references in all modules of a book.
+///
+/// ## Method
+///
+/// ```text
+/// GET /books/:id/broken-links
+/// ```
+fn check_links(db: Database, scope: TeamScoped<Book>)
+-> Result<Json<Vec<LinkProblem>>> {
+    Ok(Json(links::check_book(&db, scope.resource())?))
+}
@@ -0,0 +1,54 @@
+//! Types and helpers shared by comment endpoints of modules and drafts.
+
+use adaptarr_models::{
+    Comment,
+    CommentTarget,
+    FindModelError,
+    Model,
+    db::Connection,
+};
+use bytes::Bytes;
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+use crate::Result;
+
+#[derive(Deserialize)]
+pub struct NewComment {
+    /// Comment's body, encoded with base64.
+    #[serde(deserialize_with = "deserialize_base64")]
+    pub body: Bytes,
+    /// Comment to which this comment is a reply.
+    #[serde(default)]
+    pub parent: Option<i32>,
+    /// ID of the element to which to anchor this comment.
+    #[serde(default)]
+    pub element: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentUpdate {
+    pub resolved: bool,
+}
+
+/// Find a comment made on a specific module or draft.
+pub fn find_comment(db: &Connection, target: CommentTarget, id: i32)
+-> Result<Comment> {
+    let comment = Comment::by_id(db, id)?;
+
+    if comment.target() != target {
+        return Err(FindModelError::<Comment>::not_found().into());
+    }
+
+    Ok(comment)
+}
+
+fn deserialize_base64<'de, D>(de: D) -> std::result::Result<Bytes, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let data = String::deserialize(de)?;
+
+    base64::decode(&data)
+        .map(Bytes::from)
+        .map_err(D::Error::custom)
+}
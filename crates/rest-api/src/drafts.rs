@@ -1,24 +1,38 @@
 use actix_web::{
+    HttpRequest,
     HttpResponse,
     Responder,
-    http::{StatusCode, header::ETAG},
-    web::{self, Data, Json, Payload, Path, ServiceConfig},
+    http::{StatusCode, header::{ETAG, LOCATION}},
+    web::{self, Data, Json, Payload, Path, Query, ServiceConfig},
 };
 use adaptarr_error::{ApiError, Error};
 use adaptarr_models::{
     CNXML_MIME,
     AdvanceResult,
+    Comment,
+    CommentTarget,
     Draft,
+    DraftChange,
+    DraftRevision,
     File,
+    FindModelError,
     Model,
     Module,
+    Optional,
     User,
+    WriteFileError,
     db::{Connection, Pool, types::SlotPermission},
     editing::{Version, Slot},
     permissions::ManageProcess,
+    processing::{
+        diff::{self, DocumentDiff, ElementChange},
+        links::{self, LinkProblem},
+    },
 };
 use adaptarr_util::futures::void;
+use diesel::result::Error as DbError;
 use adaptarr_web::{
+    Created,
     Database,
     FileExt,
     FormOrJson,
@@ -26,12 +40,17 @@ use adaptarr_web::{
     TeamScoped,
     etag::IfMatch,
 };
+use chrono::{DateTime, Utc};
 use failure::Fail;
 use futures::{Future, Stream, future};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::Result;
+use crate::{
+    Result,
+    comments::{CommentUpdate, NewComment, find_comment},
+    files::{FileInfo, FileQuery, scale},
+};
 
 /// Configure routes.
 pub fn configure(app: &mut ServiceConfig) {
@@ -47,14 +66,45 @@ pub fn configure(app: &mut ServiceConfig) {
             .route(web::get().to(list_comments))
             .route(web::post().to(add_comment))
         )
+        .service(web::resource("/drafts/{id}/comments/{comment}")
+            .route(web::get().to(get_comment))
+            .route(web::put().to(update_comment))
+        )
         .route("/drafts/{id}/files", web::get().to(list_files))
         .service(web::resource("/drafts/{id}/files/{name}")
-            .route(web::get().to(get_file))
+            .route(web::get().to_async(get_file))
             .route(web::put().to_async(update_file))
             .route(web::delete().to(delete_file))
         )
+        .route("/drafts/{id}/changes", web::get().to(list_changes))
+        .route("/drafts/{id}/changes/{change}", web::get().to(get_change))
+        .route("/drafts/{id}/changes/{change}/content",
+            web::get().to(get_change_content))
+        .route("/drafts/{id}/changes/{change}/diff",
+            web::get().to(diff_change))
+        .route("/drafts/{id}/changes/{change}/accept",
+            web::post().to(accept_change))
+        .route("/drafts/{id}/changes/{change}/reject",
+            web::post().to(reject_change))
+        .service(web::resource("/drafts/{id}/revisions")
+            .route(web::get().to(list_revisions))
+            .route(web::post().to(save_revision))
+        )
+        .route("/drafts/{id}/revisions/{revision}",
+            web::get().to(get_revision))
+        .route("/drafts/{id}/revisions/{revision}/restore",
+            web::post().to(restore_revision))
+        .route("/drafts/{id}/revisions/{revision}/files",
+            web::get().to(list_revision_files))
+        .route("/drafts/{id}/revisions/{revision}/files/{name}",
+            web::get().to_async(get_revision_file))
         .route("/drafts/{id}/books", web::get().to(list_containing_books))
-        .route("/drafts/{id}/process", web::get().to(get_process_details))
+        .route("/drafts/{id}/diff", web::get().to(diff_draft))
+        .route("/drafts/{id}/broken-links", web::get().to(check_links))
+        .service(web::resource("/drafts/{id}/process")
+            .route(web::get().to(get_process_details))
+            .route(web::put().to(update_process))
+        )
         .route("/drafts/{id}/process/slots/{slot}", web::put().to(assign_slot))
     ;
 }
@@ -181,8 +231,9 @@ fn advance_draft(
 /// ```text
 /// GET /drafts/:id/comments
 /// ```
-fn list_comments() -> HttpResponse {
-    unimplemented!()
+fn list_comments(db: Database, scope: TeamScoped<Draft>)
+-> Result<Json<Vec<<Comment as Model>::Public>>> {
+    Ok(Json(scope.resource().get_comments(&db)?.get_public()))
 }
 
 /// Add a comment to a draft
@@ -191,15 +242,73 @@ fn list_comments() -> HttpResponse {
 ///
 /// ```text
 /// POST /drafts/:id/comments
+/// Content-Type: application/json
 /// ```
-fn add_comment() -> HttpResponse {
-    unimplemented!()
+fn add_comment(
+    req: HttpRequest,
+    db: Database,
+    session: Session,
+    scope: TeamScoped<Draft>,
+    data: Json<NewComment>,
+) -> Result<Created<String, Json<<Comment as Model>::Public>>> {
+    let draft = scope.resource();
+    let comment = draft.add_comment(
+        &db,
+        session.user,
+        data.parent,
+        data.element.as_ref().map(String::as_str),
+        &data.body,
+    )?;
+    let location = format!(
+        "{}/api/v1/drafts/{}/comments/{}",
+        req.app_config().host(),
+        draft.id(),
+        comment.id(),
+    );
+
+    Ok(Created(location, Json(comment.get_public())))
 }
 
-#[derive(Serialize)]
-struct FileInfo {
-    name: String,
-    mime: String,
+/// Get a comment on a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/comments/:comment
+/// ```
+fn get_comment(
+    db: Database,
+    _: TeamScoped<Draft>,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<<Comment as Model>::Public>> {
+    let (id, comment) = path.into_inner();
+    let comment = find_comment(&db, CommentTarget::Draft(id), comment)?;
+
+    Ok(Json(comment.get_public()))
+}
+
+/// Mark a comment on a draft as resolved or unresolved.
+///
+/// ## Method
+///
+/// ```text
+/// PUT /drafts/:id/comments/:comment
+/// Content-Type: application/json
+/// ```
+fn update_comment(
+    db: Database,
+    session: Session,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, i32)>,
+    update: Json<CommentUpdate>,
+) -> Result<Json<<Comment as Model>::Public>> {
+    let (id, comment) = path.into_inner();
+    let mut comment = find_comment(&db, CommentTarget::Draft(id), comment)?;
+
+    comment.set_resolved(
+        &db, session.user, scope.permissions(), update.resolved)?;
+
+    Ok(Json(comment.get_public()))
 }
 
 /// List files in a draft.
@@ -213,10 +322,7 @@ fn list_files(db: Database, scope: TeamScoped<Draft>)
 -> Result<Json<Vec<FileInfo>>> {
     Ok(Json(scope.resource().get_files(&db)?
         .into_iter()
-        .map(|(name, file)| FileInfo {
-            name,
-            mime: file.into_db().mime,
-        })
+        .map(|(name, file)| FileInfo::new(name, file))
         .collect()))
 }
 
@@ -225,13 +331,20 @@ fn list_files(db: Database, scope: TeamScoped<Draft>)
 /// ## Method
 ///
 /// ```text
-/// GET /drafts/:id/files/:name
+/// GET /drafts/:id/files/:name?width=:width
 /// ```
-fn get_file(db: Database, scope: TeamScoped<Draft>, path: Path<(Uuid, String)>)
--> Result<impl Responder> {
+fn get_file(
+    db: Database,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, String)>,
+    query: Query<FileQuery>,
+) -> impl Future<Item = impl Responder, Error = Error> {
     let (_, name) = path.into_inner();
-    let storage_path = &adaptarr_models::Config::global().storage.path;
-    Ok(scope.resource().get_file(&db, &name)?.stream(storage_path))
+    let file = scope.resource().get_file(&db, &name).map_err(Error::from);
+
+    future::result(file)
+        .and_then(move |file| scale(db, file, &query))
+        .map(|file| file.stream())
 }
 
 /// Update a file in a draft.
@@ -242,6 +355,7 @@ fn get_file(db: Database, scope: TeamScoped<Draft>, path: Path<(Uuid, String)>)
 /// PUT /drafts/:id/files/:name
 /// ```
 fn update_file(
+    req: HttpRequest,
     db: Database,
     pool: Data<Pool>,
     session: Session,
@@ -250,18 +364,19 @@ fn update_file(
     payload: Payload,
 ) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
     let (id, name) = path.into_inner();
+    let user = session.user_id();
 
     let draft = match Draft::by_id_and_user(&db, id, session.user) {
         Ok(draft) => draft,
         Err(err) => return Box::new(future::err(err.into())),
     };
 
-    match check_upload_permissions(&db, &draft, session.user_id(), &name) {
-        Ok(true) => (),
-        Ok(false) => return Box::new(future::err(
+    let upload = match upload_mode(&db, &draft, user, &name) {
+        Ok(Some(upload)) => upload,
+        Ok(None) => return Box::new(future::err(
             InsufficientSlotPermission(SlotPermission::Edit).into())),
         Err(err) => return Box::new(future::err(err)),
-    }
+    };
 
     let mime = if name == "index.cnxml" {
         Some(CNXML_MIME)
@@ -269,54 +384,122 @@ fn update_file(
         None
     };
 
-    if !if_match.is_any() {
-        let file = match draft.get_file(&*db, &name) {
-            Ok(file) => file,
-            Err(err) => return Box::new(future::err(err.into())),
-        };
-
-        if !if_match.test(&file.entity_tag()) {
-            return Box::new(payload.from_err()
-                .forward(void::<_, Error>())
-                .map(|_| HttpResponse::new(StatusCode::PRECONDITION_FAILED)));
-        }
+    // Proposed changes build upon previously proposed changes, and so that's
+    // the version proposers will have.
+    let precondition = check_if_match(&if_match, || match upload {
+        Upload::Write => draft.get_file(&db, &name).optional(),
+        Upload::Propose => draft.get_changes_base(&db).map(Some),
+    });
+
+    match precondition {
+        Ok(None) => (),
+        Ok(Some(response)) => return Box::new(payload.from_err()
+            .forward(void::<_, Error>())
+            .map(|_| response)),
+        Err(err) => return Box::new(future::err(err)),
     }
 
-    let storage_path = &adaptarr_models::Config::global().storage.path;
+    let changes = format!(
+        "{}/api/v1/drafts/{}/changes", req.app_config().host(), id);
 
-    Box::new(File::from_stream::<_, _, _, _>(
-            (*pool).clone(),
-            storage_path,
-            payload,
-            mime,
-        )
-        .and_then(move |file| {
-            draft.write_file(&db, &name, &file)
-                .map_err(Into::into)
-                .map(|_|
-                    HttpResponse::NoContent()
+    // The check above only saves us from receiving a file we won't write. To
+    // avoid lost updates it has to be repeated while the draft is locked.
+    let check = move |current: Option<&File>| test_if_match(&if_match, current);
+
+    Box::new(File::from_stream::<_, _, _>((*pool).clone(), payload, mime)
+        .and_then(move |file| -> Result<HttpResponse, Error> {
+            let result = match upload {
+                Upload::Write => draft
+                    .write_file_if(&db, &name, &file, check)
+                    .map(|_| HttpResponse::NoContent()
                         .header(ETAG, file.entity_tag())
-                        .finish()
-                )
+                        .finish()),
+                Upload::Propose => draft
+                    .propose_changes(&db, user, &file, check)
+                    .map(|change| HttpResponse::Accepted()
+                        .header(LOCATION,
+                            format!("{}/{}", changes, change.id()))
+                        .header(ETAG, file.entity_tag())
+                        .json(change.get_public())),
+            };
+
+            match result {
+                Ok(response) => Ok(response),
+                Err(WriteFileError::Precondition(current)) =>
+                    Ok(precondition_failed(current.as_ref())),
+                Err(err) => Err(err.into()),
+            }
         }))
 }
 
-fn check_upload_permissions(db: &Connection, draft: &Draft, user: i32, file: &str)
--> Result<bool, Error> {
+/// How a file uploaded by a user is handled.
+#[derive(Clone, Copy)]
+enum Upload {
+    /// File is written into the draft.
+    Write,
+    /// File is recorded as proposed changes, to be accepted later.
+    Propose,
+}
+
+fn upload_mode(db: &Connection, draft: &Draft, user: i32, file: &str)
+-> Result<Option<Upload>, Error> {
     if draft.check_permission(db, user, SlotPermission::Edit)? {
-        return Ok(true);
+        return Ok(Some(Upload::Write));
     }
 
     if file == "index.cnxml" {
-        if draft.check_permission(db, user, SlotPermission::ProposeChanges)? {
-            return Ok(true);
-        }
         if draft.check_permission(db, user, SlotPermission::AcceptChanges)? {
-            return Ok(true);
+            return Ok(Some(Upload::Write));
+        }
+        if draft.check_permission(db, user, SlotPermission::ProposeChanges)? {
+            return Ok(Some(Upload::Propose));
         }
     }
 
-    Ok(false)
+    Ok(None)
+}
+
+/// Check an `If-Match` header against the current version of a file, as
+/// returned by `current`.
+///
+/// Returns `None` if the precondition holds, or a response to send instead if
+/// it doesn't.
+fn check_if_match<F>(if_match: &IfMatch, current: F)
+-> Result<Option<HttpResponse>>
+where
+    F: FnOnce() -> Result<Option<File>, DbError>,
+{
+    if if_match.is_any() {
+        return Ok(None);
+    }
+
+    let file = current()?;
+
+    if test_if_match(if_match, file.as_ref()) {
+        Ok(None)
+    } else {
+        Ok(Some(precondition_failed(file.as_ref())))
+    }
+}
+
+/// Test an `If-Match` header against the current version of a file, if it
+/// exists.
+fn test_if_match(if_match: &IfMatch, current: Option<&File>) -> bool {
+    match current {
+        Some(file) => if_match.test(&file.entity_tag()),
+        None => if_match.is_any(),
+    }
+}
+
+/// Create a response to a request whose `If-Match` precondition failed. Such
+/// response will carry the file's current entity tag, if the file exists.
+fn precondition_failed(current: Option<&File>) -> HttpResponse {
+    match current {
+        Some(file) => HttpResponse::PreconditionFailed()
+            .header(ETAG, file.entity_tag())
+            .finish(),
+        None => HttpResponse::new(StatusCode::PRECONDITION_FAILED),
+    }
 }
 
 /// Delete a file from a draft.
@@ -330,6 +513,7 @@ fn delete_file(
     db: Database,
     session: Session,
     path: Path<(Uuid, String)>,
+    if_match: IfMatch,
 ) -> Result<HttpResponse> {
     let (id, name) = path.into_inner();
     let draft = Draft::by_id_and_user(&db, id, session.user)?;
@@ -338,9 +522,283 @@ fn delete_file(
         return Err(InsufficientSlotPermission(SlotPermission::Edit).into());
     }
 
-    draft.delete_file(&db, &name)?;
+    let check = |current: Option<&File>| test_if_match(&if_match, current);
 
-    Ok(HttpResponse::new(StatusCode::NO_CONTENT))
+    match draft.delete_file_if(&db, &name, check) {
+        Ok(()) => Ok(HttpResponse::new(StatusCode::NO_CONTENT)),
+        Err(WriteFileError::Precondition(current)) =>
+            Ok(precondition_failed(current.as_ref())),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// List changes proposed to a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/changes
+/// ```
+fn list_changes(db: Database, scope: TeamScoped<Draft>)
+-> Result<Json<Vec<<DraftChange as Model>::Public>>> {
+    Ok(Json(scope.resource().get_changes(&db)?.get_public()))
+}
+
+/// Get a set of changes proposed to a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/changes/:change
+/// ```
+fn get_change(
+    db: Database,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<<DraftChange as Model>::Public>> {
+    let (_, change) = path.into_inner();
+
+    Ok(Json(find_change(&db, scope.resource(), change)?.get_public()))
+}
+
+/// Get the version of `index.cnxml` proposed in a set of changes.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/changes/:change/content
+/// ```
+fn get_change_content(
+    db: Database,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, i32)>,
+) -> Result<impl Responder> {
+    let (_, change) = path.into_inner();
+
+    Ok(find_change(&db, scope.resource(), change)?.get_file(&db)?.stream())
+}
+
+/// Compare a set of proposed changes with the version of `index.cnxml` they
+/// were proposed against.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/changes/:change/diff
+/// ```
+fn diff_change(
+    db: Database,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<Vec<ElementChange>>> {
+    let (_, change) = path.into_inner();
+
+    Ok(Json(find_change(&db, scope.resource(), change)?.diff(&db)?))
+}
+
+/// Accept a set of proposed changes, applying them to the draft.
+///
+/// ## Method
+///
+/// ```text
+/// POST /drafts/:id/changes/:change/accept
+/// ```
+fn accept_change(
+    db: Database,
+    session: Session,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<<DraftChange as Model>::Public>> {
+    let (id, change) = path.into_inner();
+    let draft = Draft::by_id_and_user(&db, id, session.user)?;
+
+    if !draft.check_permission(&db, session.user, SlotPermission::AcceptChanges)? {
+        return Err(
+            InsufficientSlotPermission(SlotPermission::AcceptChanges).into());
+    }
+
+    let mut change = find_change(&db, &draft, change)?;
+
+    draft.accept_changes(&db, session.user, &mut change)?;
+
+    Ok(Json(change.get_public()))
+}
+
+/// Reject a set of proposed changes.
+///
+/// ## Method
+///
+/// ```text
+/// POST /drafts/:id/changes/:change/reject
+/// ```
+fn reject_change(
+    db: Database,
+    session: Session,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<<DraftChange as Model>::Public>> {
+    let (id, change) = path.into_inner();
+    let draft = Draft::by_id_and_user(&db, id, session.user)?;
+
+    if !draft.check_permission(&db, session.user, SlotPermission::AcceptChanges)? {
+        return Err(
+            InsufficientSlotPermission(SlotPermission::AcceptChanges).into());
+    }
+
+    let mut change = find_change(&db, &draft, change)?;
+
+    draft.reject_changes(&db, session.user, &mut change)?;
+
+    Ok(Json(change.get_public()))
+}
+
+/// Find a set of changes proposed to a draft.
+fn find_change(db: &Connection, draft: &Draft, id: i32) -> Result<DraftChange> {
+    let change = DraftChange::by_id(db, id)?;
+
+    if change.draft() != draft.id() {
+        return Err(FindModelError::<DraftChange>::not_found().into());
+    }
+
+    Ok(change)
+}
+
+/// List revisions of a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/revisions
+/// ```
+fn list_revisions(db: Database, scope: TeamScoped<Draft>)
+-> Result<Json<Vec<<DraftRevision as Model>::Public>>> {
+    Ok(Json(scope.resource().get_revisions(&db)?.get_public()))
+}
+
+/// Save current content of a draft as a new revision.
+///
+/// ## Method
+///
+/// ```text
+/// POST /drafts/:id/revisions
+/// ```
+fn save_revision(
+    req: HttpRequest,
+    db: Database,
+    session: Session,
+    id: Path<Uuid>,
+) -> Result<Created<String, Json<<DraftRevision as Model>::Public>>> {
+    let draft = Draft::by_id_and_user(&db, *id, session.user)?;
+
+    if !draft.check_permission(&db, session.user, SlotPermission::Edit)? {
+        return Err(InsufficientSlotPermission(SlotPermission::Edit).into());
+    }
+
+    let revision = draft.save_revision(&db, session.user)?;
+    let location = format!(
+        "{}/api/v1/drafts/{}/revisions/{}",
+        req.app_config().host(),
+        draft.id(),
+        revision.id(),
+    );
+
+    Ok(Created(location, Json(revision.get_public())))
+}
+
+/// Get a revision of a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/revisions/:revision
+/// ```
+fn get_revision(
+    db: Database,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<<DraftRevision as Model>::Public>> {
+    let (_, revision) = path.into_inner();
+
+    Ok(Json(find_revision(&db, scope.resource(), revision)?.get_public()))
+}
+
+/// Make content of an earlier revision of a draft current again.
+///
+/// ## Method
+///
+/// ```text
+/// POST /drafts/:id/revisions/:revision/restore
+/// ```
+fn restore_revision(
+    db: Database,
+    session: Session,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<<Draft as Model>::Public>> {
+    let (id, revision) = path.into_inner();
+    let mut draft = Draft::by_id_and_user(&db, id, session.user)?;
+
+    if !draft.check_permission(&db, session.user, SlotPermission::Edit)? {
+        return Err(InsufficientSlotPermission(SlotPermission::Edit).into());
+    }
+
+    let revision = find_revision(&db, &draft, revision)?;
+
+    draft.restore_revision(&db, session.user, &revision)?;
+
+    Ok(Json(draft.get_public_full(&db, &session.user_id())?))
+}
+
+/// List files in a revision of a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/revisions/:revision/files
+/// ```
+fn list_revision_files(
+    db: Database,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<Vec<FileInfo>>> {
+    let (_, revision) = path.into_inner();
+
+    Ok(Json(find_revision(&db, scope.resource(), revision)?
+        .get_files(&db)?
+        .into_iter()
+        .map(|(name, file)| FileInfo::new(name, file))
+        .collect()))
+}
+
+/// Get a file from a revision of a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/revisions/:revision/files/:name?width=:width
+/// ```
+fn get_revision_file(
+    db: Database,
+    scope: TeamScoped<Draft>,
+    path: Path<(Uuid, i32, String)>,
+    query: Query<FileQuery>,
+) -> impl Future<Item = impl Responder, Error = Error> {
+    let (_, revision, name) = path.into_inner();
+    let file = find_revision(&db, scope.resource(), revision)
+        .and_then(|revision| Ok(revision.get_file(&db, &name)?));
+
+    future::result(file)
+        .and_then(move |file| scale(db, file, &query))
+        .map(|file| file.stream())
+}
+
+/// Find a revision of a draft.
+fn find_revision(db: &Connection, draft: &Draft, id: i32)
+-> Result<DraftRevision> {
+    let revision = DraftRevision::by_id(db, id)?;
+
+    if revision.draft() != draft.id() {
+        return Err(FindModelError::<DraftRevision>::not_found().into());
+    }
+
+    Ok(revision)
 }
 
 /// Get a list of all books containing the module this draft was derived from.
@@ -355,6 +813,34 @@ fn list_containing_books(db: Database, scope: TeamScoped<Draft>)
     Ok(Json(scope.resource().get_books(&db)?))
 }
 
+/// Compare a draft with the module it was derived from.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/diff
+/// ```
+fn diff_draft(db: Database, scope: TeamScoped<Draft>)
+-> Result<Json<DocumentDiff>> {
+    let draft = scope.resource();
+    let module = Module::by_id(&db, draft.id())?;
+
+    Ok(Json(diff::diff_documents(&db, &module, draft)?))
+}
+
+/// Check links and media references in a draft.
+///
+/// ## Method
+///
+/// ```text
+/// GET /drafts/:id/broken-links
+/// ```
+fn check_links(db: Database, scope: TeamScoped<Draft>)
+-> Result<Json<Vec<LinkProblem>>> {
+    let draft = scope.resource();
+    Ok(Json(links::check_document(&db, draft.id(), draft)?))
+}
+
 #[derive(Serialize)]
 struct SlotSeating {
     #[serde(flatten)]
@@ -367,6 +853,7 @@ struct ProcessDetails {
     #[serde(flatten)]
     process: <Version as Model>::Public,
     slots: Vec<SlotSeating>,
+    deadline: Option<DateTime<Utc>>,
 }
 
 /// Get details of the process this draft follows.
@@ -396,9 +883,35 @@ fn get_process_details(
     Ok(Json(ProcessDetails {
         process: process.get_public(),
         slots,
+        deadline: draft.deadline(),
     }))
 }
 
+#[derive(Deserialize)]
+struct ProcessUpdate {
+    deadline: Option<DateTime<Utc>>,
+}
+
+/// Update details of the process this draft follows.
+///
+/// ## Method
+///
+/// ```text
+/// PUT /drafts/:id/process
+/// Content-Type: application/json
+/// ```
+fn update_process(
+    db: Database,
+    scope: TeamScoped<Draft, ManageProcess>,
+    update: Json<ProcessUpdate>,
+) -> Result<HttpResponse> {
+    let mut draft = scope.into_resource();
+
+    draft.set_deadline(&db, update.deadline)?;
+
+    Ok(HttpResponse::new(StatusCode::NO_CONTENT))
+}
+
 /// Assign a specific user to a slot.
 ///
 /// ## Method
@@ -0,0 +1,52 @@
+//! Types shared by file endpoints of modules and drafts.
+
+use actix_web::web;
+use adaptarr_error::Error;
+use adaptarr_models::File;
+use adaptarr_web::Database;
+use futures::{Future, future::{self, Either}};
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+#[derive(Debug, Serialize)]
+pub struct FileInfo {
+    name: String,
+    mime: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<i32>,
+}
+
+impl FileInfo {
+    pub fn new(name: String, file: File) -> FileInfo {
+        let dimensions = file.dimensions();
+
+        FileInfo {
+            name,
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            mime: file.into_db().mime,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FileQuery {
+    /// Width to which to scale an image.
+    pub width: Option<u32>,
+}
+
+/// Scale an image down to the width requested in `query`, if any.
+///
+/// Images are decoded and encoded on Actix's thread pool.
+pub fn scale(db: Database, file: File, query: &FileQuery)
+-> impl Future<Item = File, Error = Error> {
+    match query.width {
+        Some(width) => Either::A(web::block(move || -> Result<_> {
+            Ok(file.derivative(&db, width)?)
+        }).from_err()),
+        None => Either::B(future::ok(file)),
+    }
+}
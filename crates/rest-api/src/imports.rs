@@ -0,0 +1,24 @@
+use actix_web::web::{self, Json, ServiceConfig};
+use adaptarr_models::{ImportJob, Model};
+use adaptarr_web::{Database, TeamScoped};
+
+use crate::Result;
+
+/// Configure routes.
+pub fn configure(app: &mut ServiceConfig) {
+    app
+        .route("/imports/{id}", web::get().to(get_import))
+    ;
+}
+
+/// Get status of an import job.
+///
+/// ## Method
+///
+/// ```text
+/// GET /imports/:id
+/// ```
+fn get_import(db: Database, scope: TeamScoped<ImportJob>)
+-> Result<Json<<ImportJob as Model>::Public>> {
+    Ok(Json(scope.resource().get_public_full(&db, &())?))
+}
@@ -3,13 +3,17 @@
 use actix_web::web::{self, ServiceConfig};
 
 mod books;
+mod comments;
 mod config;
 mod conversations;
 mod drafts;
 mod events;
+mod files;
+mod imports;
 mod modules;
 mod process;
 mod resources;
+mod search;
 mod teams;
 mod users;
 
@@ -25,9 +29,11 @@ pub fn configure(cfg: &mut ServiceConfig) {
             .configure(conversations::configure)
             .configure(drafts::configure)
             .configure(events::configure)
+            .configure(imports::configure)
             .configure(modules::configure)
             .configure(process::configure)
             .configure(resources::configure)
+            .configure(search::configure)
             .configure(teams::configure)
             .configure(users::configure)
     );
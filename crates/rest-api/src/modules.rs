@@ -2,24 +2,46 @@ use actix::Addr;
 use actix_web::{
     HttpRequest,
     HttpResponse,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
     Responder,
-    web::{self, Data, Payload, Path, Json, ServiceConfig},
+    web::{self, Data, Payload, Path, Json, Query, ServiceConfig},
 };
 use adaptarr_error::Error;
 use adaptarr_models::{
     CNXML_MIME,
+    Comment,
+    CommentTarget,
     Draft,
     File,
+    FindModelError,
+    ImportJob,
     Model,
     Module,
+    ModuleVersion,
     Team,
+    TeamResource,
     User,
     XrefTarget,
+    audit,
+    db::{Connection, types::ImportKind},
     editing::Process,
     permissions::{EditModule, ManageProcess, PermissionBits, TeamPermissions},
-    processing::{TargetProcessor, import::{Importer, ImportModule, ReplaceModule}},
+    processing::{
+        TargetProcessor,
+        diff::{self, DocumentDiff},
+        export,
+        import::{
+            Importer,
+            ImportModule,
+            ReplaceModule,
+            ValidateModule,
+            ValidationReport,
+        },
+        links::{self, LinkProblem},
+    },
 };
 use adaptarr_web::{
+    Accepted,
     ContentType,
     Created,
     Database,
@@ -29,13 +51,18 @@ use adaptarr_web::{
     TeamScoped,
     multipart::{FromMultipart, FromStrField, Multipart},
 };
+use chrono::{DateTime, Utc};
 use futures::{Future, Stream, future};
-use serde::{Deserialize, Serialize};
-use std::io::Write;
+use serde::Deserialize;
+use std::io::{Cursor, Write};
 use tempfile::NamedTempFile;
 use uuid::Uuid;
 
-use crate::Result;
+use crate::{
+    Result,
+    comments::{CommentUpdate, NewComment, find_comment},
+    files::{FileInfo, FileQuery, scale},
+};
 
 /// Configure routes.
 pub fn configure(app: &mut ServiceConfig) {
@@ -44,10 +71,11 @@ pub fn configure(app: &mut ServiceConfig) {
             .route(web::get().to(list_modules))
             .route(web::post()
                 .guard(ContentType::from_mime(&mime::APPLICATION_JSON))
-                .to(create_module))
+                .to_async(create_module))
             .route(web::post()
-                .to_async(create_module_from_zip))
+                .to(create_module_from_zip))
         )
+        .route("/modules/validate", web::post().to_async(validate_module_zip))
         .service(web::resource("/modules/{id}")
             .route(web::get().to(get_module))
             .route(web::post().to(begin_process))
@@ -58,10 +86,25 @@ pub fn configure(app: &mut ServiceConfig) {
             .route(web::get().to(list_comments))
             .route(web::post().to(add_comment))
         )
+        .service(web::resource("/modules/{id}/comments/{comment}")
+            .route(web::get().to(get_comment))
+            .route(web::put().to(update_comment))
+        )
         .route("/modules/{id}/files", web::get().to(list_files))
-        .route("/modules/{id}/files/{name}", web::get().to(get_file))
+        .route("/modules/{id}/files/{name}", web::get().to_async(get_file))
         .route("/modules/{id}/xref-targets", web::get().to(list_xref_targets))
         .route("/modules/{id}/books", web::get().to(list_containing_books))
+        .route("/modules/{id}/export", web::get().to_async(export_module))
+        .route("/modules/{id}/diff", web::get().to(diff_versions))
+        .route("/modules/{id}/broken-links", web::get().to(check_links))
+        .route("/modules/{id}/versions", web::get().to(list_versions))
+        .route("/modules/{id}/versions/{version}", web::get().to(get_version))
+        .route("/modules/{id}/versions/{version}/restore",
+            web::post().to(restore_version))
+        .route("/modules/{id}/versions/{version}/files",
+            web::get().to(list_version_files))
+        .route("/modules/{id}/versions/{version}/files/{name}",
+            web::get().to_async(get_version_file))
     ;
 }
 
@@ -106,14 +149,26 @@ fn create_module(
     db: Database,
     session: Session,
     data: Json<NewModule>,
-) -> Result<Created<String, Json<<Module as Model>::Public>>> {
-    let team = Team::by_id(&db, data.team)?;
+) -> Box<dyn Future<
+    Item = Created<String, Json<<Module as Model>::Public>>,
+    Error = Error,
+>> {
+    let authorize = || -> Result<Team> {
+        let team = Team::by_id(&db, data.team)?;
+
+        if !session.is_elevated {
+            team.get_member(&db, &session.user(&db)?)?
+                .permissions()
+                .require(TeamPermissions::EDIT_MODULE)?;
+        }
+
+        Ok(team)
+    };
 
-    if !session.is_elevated {
-        team.get_member(&db, &session.user(&db)?)?
-            .permissions()
-            .require(TeamPermissions::EDIT_MODULE)?;
-    }
+    let team = match authorize() {
+        Ok(team) => team,
+        Err(err) => return Box::new(future::err(err)),
+    };
 
     let content = format!(
         r#"<?xml version="1.0" encoding="utf-8"?>
@@ -127,20 +182,27 @@ fn create_module(
         tera::escape_html(&data.title),
     );
 
-    let storage_path = &adaptarr_models::Config::global().storage.path;
-    let index = File::from_data(&db, storage_path, &content, Some(CNXML_MIME))?;
+    let host = req.app_config().host().to_string();
+    let actor = audit::get_actor();
 
-    let module = Module::create::<&str, _>(
-        &db, &team, &data.title, &data.language, index, std::iter::empty())?;
+    // Storing the file may require uploading it to a remote storage.
+    Box::new(web::block(move || audit::with_actor(actor, || -> Result<_> {
+        let index = File::from_data(&db, &content, Some(CNXML_MIME))?;
 
-    TargetProcessor::process(module.clone());
+        let module = Module::create::<&str, _>(
+            &db, &team, &data.title, &data.language, index,
+            std::iter::empty())?;
+        let public = module.get_public_full(&db, &())?;
 
-    let public = module.get_public_full(&db, &())?;
+        Ok((module, public))
+    }))
+        .from_err()
+        .map(move |(module, public)| {
+            TargetProcessor::process(module.clone());
 
-    let location = format!("{}/api/v1/modules/{}",
-        req.app_config().host(), module.id());
-
-    Ok(Created(location, Json(public)))
+            let location = format!("{}/api/v1/modules/{}", host, module.id());
+            Created(location, Json(public))
+        }))
 }
 
 #[derive(FromMultipart)]
@@ -164,51 +226,60 @@ fn create_module_from_zip(
     importer: Data<Addr<Importer>>,
     session: Session,
     data: Multipart<NewModuleZip>,
-) -> Box<dyn Future<Item = Created<String, Json<<Module as Model>::Public>>, Error = Error>> {
+) -> Result<Accepted<String, Json<<ImportJob as Model>::Public>>> {
     let NewModuleZip { title, team, file } = data.into_inner();
+    let team = Team::by_id(&db, *team)?;
 
-    let team = match Team::by_id(&db, *team) {
-        Ok(team) => team,
-        Err(err) => return Box::new(future::err(err.into())),
-    };
-
-    match session.user(&db)
-        .map_err(Error::from)
-        .and_then(|user| {
-            if session.is_elevated {
-                Ok(TeamPermissions::all())
-            } else {
-                team.get_member(&db, &user)
-                    .map(|tm| tm.permissions())
-                    .map_err(Error::from)
-            }
-        })
-        .and_then(|permissions| {
-            permissions
-                .require(TeamPermissions::EDIT_MODULE)
-                .map_err(Error::from)
-        })
-    {
-        Ok(()) => (),
-        Err(err) => return Box::new(future::err(err)),
+    if !session.is_elevated {
+        team.get_member(&db, &session.user(&db)?)?
+            .permissions()
+            .require(TeamPermissions::EDIT_MODULE)?;
     }
 
-    Box::new(importer.send(ImportModule {
+    let job = ImportJob::create(
+        &db, ImportKind::Module, team.id(), Some(session.user_id()), &title,
+        None, None)?;
+    let public = job.get_public_full(&db, &())?;
+    let location = format!("{}/api/v1/imports/{}",
+        req.app_config().host(), job.id());
+
+    importer.do_send(ImportModule {
         team,
         title,
         file,
         actor: session.user_id().into(),
-    })
+        job,
+    });
+
+    Ok(Accepted(location, Json(public)))
+}
+
+/// Validate a ZIP archive of a module, without importing it.
+///
+/// ## Method
+///
+/// ```text
+/// POST /modules/validate
+/// ```
+fn validate_module_zip(
+    importer: Data<Addr<Importer>>,
+    _session: Session,
+    payload: Payload,
+) -> Box<dyn Future<Item = Json<ValidationReport>, Error = Error>> {
+    let file = match NamedTempFile::new() {
+        Ok(file) => file,
+        Err(err) => return Box::new(future::err(err.into())),
+    };
+
+    Box::new(payload
         .from_err::<Error>()
-        .and_then(|r| future::result(r).from_err())
-        .and_then(move |module| -> Result<_> {
-            Ok(module.get_public_full(&db, &())?)
+        .fold(file, |mut file, chunk| match file.write_all(chunk.as_ref()) {
+            Ok(_) => future::ok(file),
+            Err(err) => future::err(err),
         })
-        .map(move |p| {
-            let location = format!("{}/api/v1/modules/{}",
-                req.app_config().host(), p.id);
-            Created(location, Json(p))
-        }))
+        .and_then(move |file| importer.send(ValidateModule { file }).from_err())
+        .and_then(|r| future::result(r).from_err())
+        .map(Json))
 }
 
 /// Get a module by ID.
@@ -272,12 +343,13 @@ fn begin_process(
 /// PUT /modules/:id
 /// ```
 fn replace_module(
+    req: HttpRequest,
     db: Database,
     importer: Data<Addr<Importer>>,
     scope: TeamScoped<Module, EditModule>,
     session: Session,
     payload: Payload,
-) -> Box<dyn Future<Item = Json<<Module as Model>::Public>, Error = Error>> {
+) -> Box<dyn Future<Item = Accepted<String, Json<<ImportJob as Model>::Public>>, Error = Error>> {
     let module = scope.into_resource();
     let file = match NamedTempFile::new() {
         Ok(file) => file,
@@ -290,16 +362,23 @@ fn replace_module(
             Ok(_) => future::ok(file),
             Err(err) => future::err(err),
         })
-        .and_then(move |file| importer.send(ReplaceModule {
-            module,
-            file,
-            actor: session.user_id().into(),
-        }).from_err())
-        .and_then(|r| future::result(r).from_err())
-        .and_then(move |module| {
-            module.get_public_full(&db, &()).map_err(Error::from)
-        })
-        .map(Json))
+        .and_then(move |file| -> Result<_> {
+            let job = ImportJob::create(
+                &db, ImportKind::Module, module.team_id(),
+                Some(session.user_id()), &module.title, Some(module.id()), None)?;
+            let public = job.get_public_full(&db, &())?;
+            let location = format!("{}/api/v1/imports/{}",
+                req.app_config().host(), job.id());
+
+            importer.do_send(ReplaceModule {
+                module,
+                file,
+                actor: session.user_id().into(),
+                job,
+            });
+
+            Ok(Accepted(location, Json(public)))
+        }))
 }
 
 /// Delete a module
@@ -320,8 +399,9 @@ fn delete_module() -> HttpResponse {
 /// ```text
 /// GET /modules/:id/comments
 /// ```
-fn list_comments() -> HttpResponse {
-    unimplemented!()
+fn list_comments(db: Database, scope: TeamScoped<Module>)
+-> Result<Json<Vec<<Comment as Model>::Public>>> {
+    Ok(Json(scope.resource().get_comments(&db)?.get_public()))
 }
 
 /// Add a comment to a module
@@ -330,15 +410,73 @@ fn list_comments() -> HttpResponse {
 ///
 /// ```text
 /// POST /modules/:id/comments
+/// Content-Type: application/json
 /// ```
-fn add_comment() -> HttpResponse {
-    unimplemented!()
+fn add_comment(
+    req: HttpRequest,
+    db: Database,
+    session: Session,
+    scope: TeamScoped<Module>,
+    data: Json<NewComment>,
+) -> Result<Created<String, Json<<Comment as Model>::Public>>> {
+    let module = scope.resource();
+    let comment = module.add_comment(
+        &db,
+        session.user,
+        data.parent,
+        data.element.as_ref().map(String::as_str),
+        &data.body,
+    )?;
+    let location = format!(
+        "{}/api/v1/modules/{}/comments/{}",
+        req.app_config().host(),
+        module.id(),
+        comment.id(),
+    );
+
+    Ok(Created(location, Json(comment.get_public())))
 }
 
-#[derive(Debug, Serialize)]
-struct FileInfo {
-    name: String,
-    mime: String,
+/// Get a comment on a module.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/comments/:comment
+/// ```
+fn get_comment(
+    db: Database,
+    _: TeamScoped<Module>,
+    path: Path<(Uuid, i32)>,
+) -> Result<Json<<Comment as Model>::Public>> {
+    let (id, comment) = path.into_inner();
+    let comment = find_comment(&db, CommentTarget::Module(id), comment)?;
+
+    Ok(Json(comment.get_public()))
+}
+
+/// Mark a comment on a module as resolved or unresolved.
+///
+/// ## Method
+///
+/// ```text
+/// PUT /modules/:id/comments/:comment
+/// Content-Type: application/json
+/// ```
+fn update_comment(
+    db: Database,
+    session: Session,
+    scope: TeamScoped<Module>,
+    path: Path<(Uuid, i32)>,
+    update: Json<CommentUpdate>,
+) -> Result<Json<<Comment as Model>::Public>> {
+    let (id, comment) = path.into_inner();
+    let mut comment = find_comment(&db, CommentTarget::Module(id), comment)?;
+
+    comment.set_resolved(
+        &db, session.user, scope.permissions(), update.resolved)?;
+
+    Ok(Json(comment.get_public()))
 }
 
 /// List files in a module.
@@ -353,10 +491,7 @@ fn list_files(db: Database, scope: TeamScoped<Module>)
     Ok(Json(scope.resource()
         .get_files(&db)?
         .into_iter()
-        .map(|(name, file)| FileInfo {
-            name,
-            mime: file.into_db().mime,
-        })
+        .map(|(name, file)| FileInfo::new(name, file))
         .collect()))
 }
 
@@ -365,14 +500,20 @@ fn list_files(db: Database, scope: TeamScoped<Module>)
 /// ## Method
 ///
 /// ```text
-/// GET /modules/:id/files/:name
+/// GET /modules/:id/files/:name?width=:width
 /// ```
-fn get_file(db: Database, scope: TeamScoped<Module>, path: Path<(Uuid, String)>)
--> Result<impl Responder> {
+fn get_file(
+    db: Database,
+    scope: TeamScoped<Module>,
+    path: Path<(Uuid, String)>,
+    query: Query<FileQuery>,
+) -> impl Future<Item = impl Responder, Error = Error> {
     let (_, name) = path.into_inner();
+    let file = scope.resource().get_file(&db, &name).map_err(Error::from);
 
-    let storage_path = &adaptarr_models::Config::global().storage.path;
-    Ok(scope.resource().get_file(&db, &name)?.stream(storage_path))
+    future::result(file)
+        .and_then(move |file| scale(db, file, &query))
+        .map(|file| file.stream())
 }
 
 /// Get a list of all possible cross-reference targets within a module.
@@ -398,3 +539,183 @@ fn list_containing_books(db: Database, scope: TeamScoped<Module>)
 -> Result<Json<Vec<Uuid>>> {
     Ok(Json(scope.resource().get_books(&db)?))
 }
+
+/// Export a module as a ZIP archive.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/export
+/// ```
+fn export_module(db: Database, scope: TeamScoped<Module>)
+-> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    let module = scope.into_resource();
+
+    // Contents of files may have to be downloaded from a remote storage.
+    Box::new(web::block(move || -> Result<_> {
+        let data = export::export_module(
+            &db, &module, Cursor::new(Vec::new()))?;
+        Ok((module.id(), data))
+    })
+        .from_err()
+        .map(|(id, data)| HttpResponse::Ok()
+            .content_type("application/zip")
+            .set(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![
+                    DispositionParam::Filename(format!("{}.zip", id)),
+                ],
+            })
+            .body(data.into_inner())))
+}
+
+#[derive(Deserialize)]
+struct DiffQuery {
+    from: String,
+    to: Option<String>,
+}
+
+/// Compare two versions of a module.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/diff?from=:version&to=:version
+/// ```
+fn diff_versions(
+    db: Database,
+    scope: TeamScoped<Module>,
+    query: Query<DiffQuery>,
+) -> Result<Json<DocumentDiff>> {
+    let module = scope.resource();
+    let from = find_version(&db, module, &query.from)?;
+
+    let diff = match query.to {
+        Some(ref to) => {
+            let to = find_version(&db, module, to)?;
+            diff::diff_documents(&db, &from, &to)?
+        }
+        None => diff::diff_documents(&db, &from, module)?,
+    };
+
+    Ok(Json(diff))
+}
+
+/// Check links and media references in a module.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/broken-links
+/// ```
+fn check_links(db: Database, scope: TeamScoped<Module>)
+-> Result<Json<Vec<LinkProblem>>> {
+    let module = scope.resource();
+    Ok(Json(links::check_document(&db, module.id(), module)?))
+}
+
+/// List all versions of a module.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/versions
+/// ```
+fn list_versions(db: Database, scope: TeamScoped<Module>)
+-> Result<Json<Vec<<ModuleVersion as Model>::Public>>> {
+    Ok(Json(ModuleVersion::by_module(&db, scope.resource().id())?.get_public()))
+}
+
+/// Get a particular version of a module.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/versions/:version
+/// ```
+fn get_version(
+    db: Database,
+    scope: TeamScoped<Module>,
+    path: Path<(Uuid, String)>,
+) -> Result<Json<<ModuleVersion as Model>::Public>> {
+    let (_, version) = path.into_inner();
+    Ok(Json(find_version(&db, scope.resource(), &version)?.get_public()))
+}
+
+/// Make an older version of a module current again.
+///
+/// ## Method
+///
+/// ```text
+/// POST /modules/:id/versions/:version/restore
+/// ```
+fn restore_version(
+    db: Database,
+    scope: TeamScoped<Module, EditModule>,
+    path: Path<(Uuid, String)>,
+) -> Result<Json<<Module as Model>::Public>> {
+    let (_, version) = path.into_inner();
+    let mut module = scope.into_resource();
+    let version = find_version(&db, &module, &version)?;
+
+    module.restore(&db, &version)?;
+
+    Ok(Json(module.get_public_full(&db, &())?))
+}
+
+/// List files in a particular version of a module.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/versions/:version/files
+/// ```
+fn list_version_files(
+    db: Database,
+    scope: TeamScoped<Module>,
+    path: Path<(Uuid, String)>,
+) -> Result<Json<Vec<FileInfo>>> {
+    let (_, version) = path.into_inner();
+
+    Ok(Json(find_version(&db, scope.resource(), &version)?
+        .get_files(&db)?
+        .into_iter()
+        .map(|(name, file)| FileInfo::new(name, file))
+        .collect()))
+}
+
+/// Get a file from a particular version of a module.
+///
+/// ## Method
+///
+/// ```text
+/// GET /modules/:id/versions/:version/files/:name?width=:width
+/// ```
+fn get_version_file(
+    db: Database,
+    scope: TeamScoped<Module>,
+    path: Path<(Uuid, String, String)>,
+    query: Query<FileQuery>,
+) -> impl Future<Item = impl Responder, Error = Error> {
+    let (_, version, name) = path.into_inner();
+    let file = find_version(&db, scope.resource(), &version)
+        .and_then(|version| Ok(version.get_file(&db, &name)?));
+
+    future::result(file)
+        .and_then(move |file| scale(db, file, &query))
+        .map(|file| file.stream())
+}
+
+/// Find a version of a module, specified either by its ID, or by a timestamp,
+/// in which case the version which was current at that time is returned.
+fn find_version(db: &Connection, module: &Module, version: &str)
+-> Result<ModuleVersion> {
+    if let Ok(id) = version.parse() {
+        return Ok(ModuleVersion::by_id(db, (module.id(), id))?);
+    }
+
+    let time = DateTime::parse_from_rfc3339(version)
+        .map_err(|_| FindModelError::<ModuleVersion>::not_found())?;
+
+    Ok(ModuleVersion::at(db, module.id(), time.with_timezone(&Utc))?)
+}
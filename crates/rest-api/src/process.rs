@@ -2,17 +2,28 @@ use actix_web::{
     HttpRequest,
     HttpResponse,
     http::StatusCode,
-    web::{self, Json, Path, ServiceConfig},
+    web::{self, Json, Path, Query, ServiceConfig},
 };
 use adaptarr_error::Error;
 use adaptarr_models::{
     Draft,
+    FindModelError,
     Model,
     Role,
     Team,
+    TeamResource,
     db::Connection,
-    editing::{Link, Process, Slot, Step, Version, structure},
-    permissions::{EditProcess, PermissionBits, TeamPermissions},
+    editing::{
+        Link,
+        Process,
+        Slot,
+        Step,
+        Version,
+        diagram,
+        statistics::{self, Statistics},
+        structure,
+    },
+    permissions::{EditProcess, ManageProcess, PermissionBits, TeamPermissions},
 };
 use adaptarr_web::{Created, Database, FormOrJson, Session, TeamScoped};
 use diesel::Connection as _;
@@ -56,6 +67,9 @@ pub fn configure(app: &mut ServiceConfig) {
             .service(web::resource("/{id}/structure")
                 .route(web::get().to(get_process_structure))
             )
+            .service(web::resource("/{id}/statistics")
+                .route(web::get().to(get_process_statistics))
+            )
             .service(web::resource("/{id}/versions")
                 .route(web::get().to(list_process_versions))
                 .route(web::post().to(create_version))
@@ -63,6 +77,10 @@ pub fn configure(app: &mut ServiceConfig) {
             .service(web::resource("/{id}/versions/{version}")
                 .route(web::get().to(get_process_version))
             )
+            .route("/{id}/versions/{version}/migrate",
+                web::post().to(migrate_drafts))
+            .route("/{id}/versions/{version}/diagram",
+                web::get().to(get_version_diagram))
             .route("/{id}/versions/{version}/slots",
                 web::get().to(list_slots_in_version))
             .service(web::resource("/{id}/versions/{version}/slots/{slot}")
@@ -110,6 +128,15 @@ struct NewProcess {
     structure: structure::Process,
 }
 
+/// A newly created object, together with warnings found while validating
+/// structure of its process.
+#[derive(Serialize)]
+struct Validated<T> {
+    #[serde(flatten)]
+    data: T,
+    warnings: Vec<structure::Warning>,
+}
+
 /// Create a new editing process.
 ///
 /// ## Method
@@ -122,7 +149,7 @@ fn create_process(
     db: Database,
     session: Session,
     data: Json<NewProcess>,
-) -> Result<Created<String, Json<<Process as Model>::Public>>> {
+) -> Result<Created<String, Json<Validated<<Process as Model>::Public>>>> {
     let team = Team::by_id(&db, data.team)?;
 
     if !session.is_elevated {
@@ -131,11 +158,14 @@ fn create_process(
             .require(TeamPermissions::EDIT_PROCESS)?;
     }
 
-    let process = Process::create(&db, &team, &data.structure)?;
+    let (process, validation) = Process::create(&db, &team, &data.structure)?;
     let location = format!("{}/api/v1/processes/{}",
         req.app_config().host(), process.process().id);
 
-    Ok(Created(location, Json(process.process().get_public())))
+    Ok(Created(location, Json(Validated {
+        data: process.process().get_public(),
+        warnings: validation.warnings,
+    })))
 }
 
 #[derive(Deserialize)]
@@ -431,6 +461,55 @@ fn get_process_structure(db: Database, scope: TeamScoped<Process>)
     Ok(Json(scope.resource().get_current(&db)?.get_structure(&db)?))
 }
 
+#[derive(Deserialize)]
+pub(crate) struct StatisticsQuery {
+    #[serde(default)]
+    pub format: StatisticsFormat,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum StatisticsFormat {
+    Json,
+    Csv,
+}
+
+impl Default for StatisticsFormat {
+    fn default() -> Self {
+        StatisticsFormat::Json
+    }
+}
+
+/// Render process statistics as a CSV document.
+pub(crate) fn statistics_csv(stats: &[Statistics]) -> Result<HttpResponse> {
+    let mut data = Vec::new();
+    statistics::write_csv(&mut data, stats)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .body(data))
+}
+
+/// Get statistics of an editing process.
+///
+/// ## Method
+///
+/// ```text
+/// GET /processes/:id/statistics?format=:format
+/// ```
+fn get_process_statistics(
+    db: Database,
+    scope: TeamScoped<Process, ManageProcess>,
+    query: Query<StatisticsQuery>,
+) -> Result<HttpResponse> {
+    let stats = scope.resource().get_statistics(&db)?;
+
+    match query.format {
+        StatisticsFormat::Json => Ok(HttpResponse::Ok().json(stats)),
+        StatisticsFormat::Csv => statistics_csv(&[stats]),
+    }
+}
+
 /// Get list of all versions of an editing process.
 ///
 /// ## Method
@@ -456,13 +535,16 @@ fn create_version(
     scope: TeamScoped<Process, EditProcess>,
     id: Path<i32>,
     data: Json<structure::Process>,
-) -> Result<Created<String, Json<<Version as Model>::Public>>> {
+) -> Result<Created<String, Json<Validated<<Version as Model>::Public>>>> {
     let process = scope.into_resource();
-    let version = Version::create(&db, process, &*data)?;
+    let (version, validation) = Version::create(&db, process, &*data)?;
     let location = format!("{}/api/v1/processes/{}/versions/{}",
         req.app_config().host(), *id, version.id);
 
-    Ok(Created(location, Json(version.get_public())))
+    Ok(Created(location, Json(Validated {
+        data: version.get_public(),
+        warnings: validation.warnings,
+    })))
 }
 
 /// Get a version by ID.
@@ -482,6 +564,46 @@ fn get_process_version(
     Ok(Json(scope.resource().get_version(&db, version_id)?.get_public()))
 }
 
+#[derive(Deserialize)]
+struct Migration {
+    /// Version from which to migrate drafts.
+    from: i32,
+    /// Mapping from step IDs in `from` to step IDs in the target version.
+    steps: Vec<(i32, i32)>,
+    /// Mapping from slot IDs in `from` to slot IDs in the target version.
+    #[serde(default)]
+    slots: Vec<(i32, i32)>,
+}
+
+#[derive(Serialize)]
+struct MigrationResult {
+    /// Number of drafts which were migrated.
+    drafts: usize,
+}
+
+/// Migrate drafts following one version of an editing process to another.
+///
+/// ## Method
+///
+/// ```text
+/// POST /processes/:id/versions/:version/migrate
+/// ```
+fn migrate_drafts(
+    db: Database,
+    scope: TeamScoped<Process, ManageProcess>,
+    id: Path<(i32, i32)>,
+    data: Json<Migration>,
+) -> Result<Json<MigrationResult>> {
+    let (_, version_id) = id.into_inner();
+    let process = scope.resource();
+    let version = process.get_version(&db, version_id)?;
+    let from = process.get_version(&db, data.from)?;
+
+    let drafts = version.migrate_drafts(&db, &from, &data.steps, &data.slots)?;
+
+    Ok(Json(MigrationResult { drafts }))
+}
+
 /// Get list of all slots in a particular version of an editing process.
 ///
 /// ## Method
@@ -752,3 +874,64 @@ fn get_version_structure(
 
     Ok(Json(scope.resource().get_version(&db, version_id)?.get_structure(&db)?))
 }
+
+#[derive(Deserialize)]
+struct DiagramQuery {
+    #[serde(default)]
+    format: DiagramFormat,
+    /// Draft whose current step to highlight.
+    draft: Option<Uuid>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum DiagramFormat {
+    Dot,
+    Svg,
+}
+
+impl Default for DiagramFormat {
+    fn default() -> Self {
+        DiagramFormat::Svg
+    }
+}
+
+/// Render a version of an editing process as a diagram.
+///
+/// ## Method
+///
+/// ```text
+/// GET /processes/:id/versions/:version/diagram?format=:format&draft=:draft
+/// ```
+fn get_version_diagram(
+    db: Database,
+    scope: TeamScoped<Process>,
+    id: Path<(i32, i32)>,
+    query: Query<DiagramQuery>,
+) -> Result<HttpResponse> {
+    let (_, version_id) = id.into_inner();
+    let process = scope.resource();
+    let structure = process.get_version(&db, version_id)?.get_structure(&db)?;
+
+    let current = match query.draft {
+        Some(id) => {
+            let draft = Draft::by_id(&db, id)?;
+
+            if draft.team_id() != process.team_id() {
+                return Err(FindModelError::<Draft>::not_found().into());
+            }
+
+            Some(draft.get_step(&db)?.id())
+        }
+        None => None,
+    };
+
+    match query.format {
+        DiagramFormat::Dot => Ok(HttpResponse::Ok()
+            .content_type("text/vnd.graphviz; charset=utf-8")
+            .body(diagram::render_dot(&structure, current))),
+        DiagramFormat::Svg => Ok(HttpResponse::Ok()
+            .content_type("image/svg+xml")
+            .body(diagram::render_svg(&structure, current)?)),
+    }
+}
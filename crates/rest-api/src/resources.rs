@@ -12,6 +12,7 @@ use adaptarr_models::{
     Resource,
     ResourceFileError,
     Team,
+    audit,
     db::Pool,
     permissions::{ManageResources, PermissionBits, TeamPermissions},
 };
@@ -39,7 +40,7 @@ pub fn configure(app: &mut ServiceConfig) {
     app
         .service(web::resource("/resources")
             .route(web::get().to(list_resources))
-            .route(web::post().to(create_resource))
+            .route(web::post().to_async(create_resource))
         )
         .service(web::resource("/resources/{id}")
             .route(web::get().to(get_resource))
@@ -94,31 +95,49 @@ fn create_resource(
     db: Database,
     session: Session,
     data: Multipart<NewResource>,
-) -> Result<Created<String, Json<<Resource as Model>::Public>>> {
+) -> Box<dyn Future<
+    Item = Created<String, Json<<Resource as Model>::Public>>,
+    Error = Error,
+>> {
     let NewResource { name, team, file, parent } = data.into_inner();
-    let team = Team::by_id(&db, *team)?;
 
-    if !session.is_elevated {
-        team.get_member(&db, &session.user(&db)?)?
-            .permissions()
-            .require(TeamPermissions::MANAGE_RESOURCES)?;
-    }
+    let prepare = || -> Result<_> {
+        let team = Team::by_id(&db, *team)?;
 
-    let parent = parent.map(|id| Resource::by_id(&db, *id)).transpose()?;
+        if !session.is_elevated {
+            team.get_member(&db, &session.user(&db)?)?
+                .permissions()
+                .require(TeamPermissions::MANAGE_RESOURCES)?;
+        }
 
-    let resource = db.transaction::<_, Error, _>(|| {
-        let storage_path = &adaptarr_models::Config::global().storage.path;
-        let file = file.map(|file| File::from_temporary(
-            &db, storage_path, file, None)).transpose()?;
+        let parent = parent.map(|id| Resource::by_id(&db, *id)).transpose()?;
 
-        Resource::create(&db, &team, &name, file.as_ref(), parent.as_ref())
-            .map_err(From::from)
-    })?;
+        Ok((team, parent))
+    };
+
+    let (team, parent) = match prepare() {
+        Ok(prepared) => prepared,
+        Err(err) => return Box::new(future::err(err)),
+    };
 
-    let location = format!("{}/api/v1/resources/{}",
-        req.app_config().host(), resource.id);
+    let host = req.app_config().host().to_string();
+    let actor = audit::get_actor();
 
-    Ok(Created(location, Json(resource.get_public())))
+    // Storing the file may require uploading it to a remote storage.
+    Box::new(web::block(move || audit::with_actor(actor, || {
+        db.transaction::<_, Error, _>(|| {
+            let file = file.map(|file| File::from_temporary(&db, file, None))
+                .transpose()?;
+
+            Resource::create(&db, &team, &name, file.as_ref(), parent.as_ref())
+                .map_err(From::from)
+        })
+    }))
+        .from_err()
+        .map(move |resource| {
+            let location = format!("{}/api/v1/resources/{}", host, resource.id);
+            Created(location, Json(resource.get_public()))
+        }))
 }
 
 /// Get a resource by ID.
@@ -166,11 +185,9 @@ fn update_resource(
 /// ```
 fn get_resource_content(db: Database, scope: TeamScoped<Resource>)
 -> Result<impl Responder> {
-    let storage_path = &adaptarr_models::Config::global().storage.path;
-
     Ok(scope.resource()
         .get_file(&db)?
-        .stream(storage_path)?
+        .stream()?
         .set_content_disposition(ContentDisposition {
             disposition: DispositionType::Inline,
             parameters: vec![],
@@ -210,8 +227,7 @@ fn update_resource_content(
         }
     }
 
-    let storage_path = &adaptarr_models::Config::global().storage.path;
-    Box::new(File::from_stream((*pool).clone(), storage_path, payload, None)
+    Box::new(File::from_stream((*pool).clone(), payload, None)
         .and_then(move |file|
             resource.set_file(&db, &file)
                 .map_err(From::from)
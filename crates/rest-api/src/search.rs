@@ -0,0 +1,58 @@
+use actix_web::web::{self, Json, Query, ServiceConfig};
+use adaptarr_models::{
+    Model,
+    Team,
+    permissions::TeamPermissions,
+    processing::search::{self, DraftScope, SearchResult},
+};
+use adaptarr_web::{Database, Session};
+use serde::Deserialize;
+
+use crate::Result;
+
+/// Configure routes.
+pub fn configure(app: &mut ServiceConfig) {
+    app
+        .route("/search", web::get().to(search))
+    ;
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+}
+
+/// Search for modules, books, and drafts.
+///
+/// ## Method
+///
+/// ```text
+/// GET /search?q=:query
+/// ```
+fn search(db: Database, session: Session, query: Query<SearchQuery>)
+-> Result<Json<Vec<SearchResult>>> {
+    if session.is_elevated {
+        let teams = Team::all(&db)?.iter().map(Model::id).collect::<Vec<_>>();
+        let results = search::search(&db, &query.q, &teams, DraftScope::All)?;
+
+        return Ok(Json(results));
+    }
+
+    let user = session.user(&db)?;
+    let mut teams = Vec::new();
+    let mut manage = Vec::new();
+
+    for team in user.get_teams(&db)? {
+        let permissions = team.get_member(&db, &user)?.permissions();
+
+        if permissions.contains(TeamPermissions::MANAGE_PROCESS) {
+            manage.push(team.id());
+        }
+
+        teams.push(team.id());
+    }
+
+    let scope = DraftScope::User { user: user.id(), manage: &manage };
+
+    Ok(Json(search::search(&db, &query.q, &teams, scope)?))
+}
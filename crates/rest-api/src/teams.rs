@@ -1,7 +1,7 @@
 use actix_web::{
     HttpResponse,
     HttpRequest,
-    web::{self, Json, Path, ServiceConfig},
+    web::{self, Json, Path, Query, ServiceConfig},
     http::StatusCode,
 };
 use adaptarr_error::Error;
@@ -14,9 +14,11 @@ use adaptarr_models::{
     TeamPermissions,
     TeamPublicParams,
     User,
+    editing::{Process, statistics},
     permissions::{
         AddMember,
         EditRole,
+        ManageProcess,
         PermissionBits,
         RemoveMember,
     },
@@ -31,7 +33,10 @@ use adaptarr_web::{
 use diesel::Connection as _;
 use serde::Deserialize;
 
-use crate::Result;
+use crate::{
+    Result,
+    process::{StatisticsFormat, StatisticsQuery, statistics_csv},
+};
 
 /// Configure routes.
 pub fn configure(app: &mut ServiceConfig) {
@@ -65,6 +70,9 @@ pub fn configure(app: &mut ServiceConfig) {
             .route(web::put().to(update_member))
             .route(web::delete().to(delete_member))
         )
+        .service(web::resource("/teams/{id}/statistics/processes")
+            .route(web::get().to(get_process_statistics))
+        )
     ;
 }
 
@@ -428,3 +436,24 @@ fn delete_member(
 
     Ok(HttpResponse::new(StatusCode::NO_CONTENT))
 }
+
+/// Get statistics of all editing processes in a team.
+///
+/// ## Method
+///
+/// ```text
+/// GET /teams/:id/statistics/processes?format=:format
+/// ```
+fn get_process_statistics(
+    db: Database,
+    scope: TeamScoped<Team, ManageProcess>,
+    query: Query<StatisticsQuery>,
+) -> Result<HttpResponse> {
+    let processes = Process::by_team(&db, &[scope.resource().id()])?;
+    let stats = statistics::compute(&db, &processes)?;
+
+    match query.format {
+        StatisticsFormat::Json => Ok(HttpResponse::Ok().json(stats)),
+        StatisticsFormat::Csv => statistics_csv(&stats),
+    }
+}
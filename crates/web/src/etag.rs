@@ -6,6 +6,7 @@ use actix_web::{
     dev::Payload,
     http::header::{
         IF_MATCH,
+        IF_NONE_MATCH,
         HeaderValue,
         IntoHeaderValue,
         InvalidHeaderValueBytes,
@@ -183,12 +184,118 @@ impl FromRequest for IfMatch<'static> {
             return Ok(IfMatch::Any);
         }
 
-    let tags = header.to_str()?.split(',')
-            .map(str::trim)
-            .map(EntityTag::from_str)
-            .map(|r| r.map(|tag| tag.to_owned()))
-            .collect::<Result<Vec<_>, _>>()?;
+        Ok(IfMatch::OneOf(parse_tags(header)?))
+    }
+}
+
+/// The If-None-Match header allows specifying version(s) of a resource
+/// a client already has, and can be used to avoid transferring it again.
+pub enum IfNoneMatch<'a> {
+    /// Header was not present.
+    None,
+    /// Any version of the resource will match.
+    Any,
+    /// Only specified versions of the resource will match.
+    OneOf(Vec<EntityTag<'a>>),
+}
+
+impl<'a> IfNoneMatch<'a> {
+    /// Test this header against a known entity tag. Returns true if it matches
+    /// (client already has this version of the resource) and false if it
+    /// doesn't.
+    ///
+    /// As required by RFC 7232, this uses weak comparison.
+    pub fn test(&self, tag: &EntityTag) -> bool {
+        match self {
+            IfNoneMatch::None => false,
+            IfNoneMatch::Any => true,
+            IfNoneMatch::OneOf(tags) =>
+                tags.iter().any(|t| t.compare(tag) != TagEquality::None),
+        }
+    }
+}
+
+impl FromRequest for IfNoneMatch<'static> {
+    type Config = ();
+    type Error = ParseEntityTagError;
+    type Future = Result<IfNoneMatch<'static>, Self::Error>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let header = match req.headers().get(IF_NONE_MATCH) {
+            Some(header) => header,
+            None => return Ok(IfNoneMatch::None),
+        };
+
+        if header == "*" {
+            return Ok(IfNoneMatch::Any);
+        }
+
+        Ok(IfNoneMatch::OneOf(parse_tags(header)?))
+    }
+}
+
+/// Parse a comma-separated list of entity tags.
+fn parse_tags(header: &HeaderValue)
+-> Result<Vec<EntityTag<'static>>, ParseEntityTagError> {
+    header.to_str()?.split(',')
+        .map(str::trim)
+        .map(EntityTag::from_str)
+        .map(|r| r.map(|tag| tag.to_owned()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use super::*;
+
+    fn if_none_match(header: Option<&str>) -> IfNoneMatch<'static> {
+        let mut req = TestRequest::default();
+
+        if let Some(header) = header {
+            req = req.header(IF_NONE_MATCH, header);
+        }
+
+        IfNoneMatch::from_request(&req.to_http_request(), &mut Payload::None)
+            .unwrap()
+    }
+
+    #[test]
+    fn if_none_match_absent() {
+        let tag = EntityTag::strong("abc").unwrap();
+        assert!(!if_none_match(None).test(&tag));
+    }
+
+    #[test]
+    fn if_none_match_any() {
+        let tag = EntityTag::strong("abc").unwrap();
+        assert!(if_none_match(Some("*")).test(&tag));
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let tag = EntityTag::strong("abc").unwrap();
+
+        assert!(if_none_match(Some(r#""xyz", "abc""#)).test(&tag));
+        assert!(if_none_match(Some(r#"W/"abc""#)).test(&tag));
+        assert!(!if_none_match(Some(r#""xyz""#)).test(&tag));
+    }
+
+    #[test]
+    fn if_match_uses_strong_comparison() {
+        let tag = EntityTag::strong("abc").unwrap();
+        let req = TestRequest::with_header(IF_MATCH, r#"W/"abc""#)
+            .to_http_request();
+        let if_match = IfMatch::from_request(&req, &mut Payload::None).unwrap();
+
+        assert!(!if_match.test(&tag));
+    }
+
+    #[test]
+    fn unquoted_tag_is_rejected() {
+        let req = TestRequest::with_header(IF_NONE_MATCH, "abc")
+            .to_http_request();
 
-        Ok(IfMatch::OneOf(tags))
+        assert!(IfNoneMatch::from_request(&req, &mut Payload::None).is_err());
     }
 }
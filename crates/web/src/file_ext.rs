@@ -1,21 +1,43 @@
-use actix_files::NamedFile;
+use actix_files::{HttpRange, NamedFile};
 use actix_web::{
+    Error,
+    FromRequest,
     HttpRequest,
     HttpResponse,
     Responder,
-    http::header::{ETAG, ContentDisposition, IntoHeaderValue},
+    dev::{Body, Payload, SizedStream},
+    error::BlockingError,
+    http::{
+        StatusCode,
+        header::{
+            ACCEPT_RANGES,
+            CONTENT_DISPOSITION,
+            CONTENT_RANGE,
+            ContentDisposition,
+            DispositionParam,
+            DispositionType,
+            ETAG,
+            IntoHeaderValue,
+            RANGE,
+        },
+    },
+    web,
 };
-use adaptarr_models::File;
+use adaptarr_models::{File, Model, storage::{Object, ReadSeek}};
 use adaptarr_util::bytes_to_hex;
-use std::path::Path;
+use bytes::Bytes;
+use futures::{Async, Future, Poll, future};
+use mime::Mime;
+use std::io::{self, Read, Seek, SeekFrom};
 
-use crate::etag::EntityTag;
+use crate::etag::{EntityTag, IfNoneMatch};
+
+/// Size of chunks in which contents streamed from a remote storage are sent.
+const CHUNK_SIZE: u64 = 64 * 1024;
 
 pub trait FileExt {
     /// Get an Actix responder streaming contents of this file.
-    fn stream<P>(&self, storage_path: P) -> std::io::Result<Stream>
-    where
-        P: AsRef<Path>;
+    fn stream(&self) -> std::io::Result<Stream>;
 
     /// Get an entity tag for the current version of this file.
     fn entity_tag(&self) -> EntityTag<'static>;
@@ -23,11 +45,8 @@ pub trait FileExt {
 
 impl FileExt for File {
     /// Get an Actix responder streaming contents of this file.
-    fn stream<P>(&self, storage_path: P) -> std::io::Result<Stream>
-    where
-        P: AsRef<Path>,
-    {
-        Stream::open(self, storage_path)
+    fn stream(&self) -> std::io::Result<Stream> {
+        Stream::open(self)
     }
 
     fn entity_tag(&self) -> EntityTag<'static> {
@@ -36,43 +55,311 @@ impl FileExt for File {
     }
 }
 
+/// Responder streaming contents of a file.
+///
+/// Since files are content-addressed their hashes are used as strong entity
+/// tags, and requests with a matching `If-None-Match` are answered with
+/// `304 Not Modified`. Range requests are also supported.
+///
+/// Contents are opened and read on Actix's thread pool, as they may have to be
+/// downloaded from a remote storage.
 pub struct Stream {
-    stream: NamedFile,
-    hash: Vec<u8>,
+    file: File,
+    disposition: Option<ContentDisposition>,
+    tag: EntityTag<'static>,
 }
 
 impl Stream {
-    fn open<P>(file: &File, storage_path: P) -> std::io::Result<Stream>
-    where
-        P: AsRef<Path>,
-    {
-        let hash = bytes_to_hex(&file.hash);
-        let path = storage_path.as_ref().join(hash);
-        let mime = file.mime.parse().expect("invalid mime type in database");
-        let stream = NamedFile::open(path)?.set_content_type(mime);
-
+    fn open(file: &File) -> std::io::Result<Stream> {
         Ok(Stream {
-            stream,
-            hash: file.hash.clone(),
+            file: File::from_db((**file).clone()),
+            disposition: None,
+            tag: file.entity_tag(),
         })
     }
 
     pub fn set_content_disposition(mut self, cd: ContentDisposition) -> Self {
-        self.stream = self.stream.set_content_disposition(cd);
+        self.disposition = Some(cd);
         self
     }
 }
 
 impl Responder for Stream {
-    type Error = <NamedFile as Responder>::Error;
-    type Future = Result<HttpResponse, Self::Error>;
+    type Error = Error;
+    type Future = Box<dyn Future<Item = HttpResponse, Error = Error>>;
 
     fn respond_to(self, req: &HttpRequest) -> Self::Future {
-        let mut rsp = self.stream.respond_to(req)?;
+        let if_none_match =
+            match IfNoneMatch::from_request(req, &mut Payload::None) {
+                Ok(if_none_match) => if_none_match,
+                Err(err) => return Box::new(future::err(err.into())),
+            };
+
+        if if_none_match.test(&self.tag) {
+            return Box::new(future::ok(HttpResponse::NotModified()
+                .header(ETAG, self.tag)
+                .finish()));
+        }
+
+        let Stream { file, disposition, tag } = self;
+        let name = bytes_to_hex(&file.hash);
+        let mime: Mime = file.mime.parse()
+            .expect("invalid mime type in database");
+        let req = req.clone();
+
+        Box::new(web::block(move || file.open())
+            .map_err(from_blocking)
+            .and_then(move |contents| {
+                let mut rsp = match contents {
+                    // Entity tag and modification time computed by NamedFile
+                    // are based on file's metadata, which is meaningless
+                    // for us.
+                    Object::Local(contents) => {
+                        let mut file = NamedFile::from_file(contents, name)?
+                            .set_content_type(mime)
+                            .use_etag(false)
+                            .use_last_modified(false);
+
+                        if let Some(disposition) = disposition {
+                            file = file.set_content_disposition(disposition);
+                        }
+
+                        file.respond_to(&req)?
+                    }
+                    Object::Remote { reader, size } => {
+                        let disposition = disposition.unwrap_or_else(||
+                            default_disposition(&mime, name));
+                        respond_remote(&req, reader, size, mime, disposition)?
+                    }
+                };
+
+                rsp.headers_mut().insert(ETAG, tag.try_into().unwrap());
+
+                Ok(rsp)
+            }))
+    }
+}
+
+/// Content disposition used when none was set, same as [`NamedFile`]'s
+/// default.
+fn default_disposition(mime: &Mime, name: String) -> ContentDisposition {
+    ContentDisposition {
+        disposition: match mime.type_() {
+            mime::IMAGE | mime::TEXT | mime::VIDEO => DispositionType::Inline,
+            _ => DispositionType::Attachment,
+        },
+        parameters: vec![DispositionParam::Filename(name)],
+    }
+}
+
+/// Respond with contents streamed from a remote storage.
+fn respond_remote(
+    req: &HttpRequest,
+    reader: Box<dyn ReadSeek>,
+    size: u64,
+    mime: Mime,
+    disposition: ContentDisposition,
+) -> Result<HttpResponse, Error> {
+    let range = req.headers().get(RANGE).and_then(|range| range.to_str().ok());
+    let range = match requested_range(range, size) {
+        Ok(range) => range,
+        Err(()) => return Ok(
+            HttpResponse::build(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(CONTENT_RANGE, format!("bytes */{}", size))
+                .finish()),
+    };
+
+    let mut rsp = match range {
+        Some(range) => {
+            let mut rsp = HttpResponse::build(StatusCode::PARTIAL_CONTENT);
+            rsp.header(CONTENT_RANGE, format!("bytes {}-{}/{}",
+                range.start, range.start + range.length - 1, size));
+            rsp
+        }
+        None => HttpResponse::Ok(),
+    };
+
+    let (start, length) = range.map_or((0, size), |r| (r.start, r.length));
+
+    Ok(rsp
+        .content_type(mime.to_string())
+        .header(ACCEPT_RANGES, "bytes")
+        .header(CONTENT_DISPOSITION, disposition)
+        .body(Body::from_message(SizedStream::new(length, ReaderStream {
+            state: ReadState::Idle(reader, Some(start)),
+            remaining: length,
+        }))))
+}
+
+/// Determine which part of contents of a given size was requested by
+/// a `Range` header.
+///
+/// Returns `Ok(None)` if entire contents should be sent, and `Err(())` if
+/// the range can't be satisfied. Only requests for a single range are
+/// supported, requests for multiple ranges are answered with entire contents.
+fn requested_range(header: Option<&str>, size: u64)
+-> Result<Option<HttpRange>, ()> {
+    match header.map(|header| HttpRange::parse(header, size)) {
+        Some(Ok(ref ranges)) if ranges.len() == 1 => Ok(Some(ranges[0])),
+        Some(Ok(_)) | None => Ok(None),
+        Some(Err(())) => Err(()),
+    }
+}
+
+/// Stream of chunks read from a reader on Actix's thread pool.
+struct ReaderStream {
+    state: ReadState,
+    remaining: u64,
+}
+
+enum ReadState {
+    /// Waiting for the next poll. Reader should first seek to the position, if
+    /// there is one.
+    Idle(Box<dyn ReadSeek>, Option<u64>),
+    /// Reading the next chunk.
+    Reading(Box<dyn Future<
+        Item = (Box<dyn ReadSeek>, Bytes),
+        Error = BlockingError<io::Error>,
+    >>),
+    /// Reader has failed.
+    Failed,
+}
+
+impl futures::Stream for ReaderStream {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, Error> {
+        loop {
+            match std::mem::replace(&mut self.state, ReadState::Failed) {
+                ReadState::Idle(reader, seek) => {
+                    if self.remaining == 0 {
+                        self.state = ReadState::Idle(reader, None);
+                        return Ok(Async::Ready(None));
+                    }
+
+                    let len = self.remaining.min(CHUNK_SIZE) as usize;
+                    self.state = ReadState::Reading(Box::new(web::block(
+                        move || read_chunk(reader, seek, len))));
+                }
+                ReadState::Reading(mut future) => match future.poll() {
+                    Ok(Async::NotReady) => {
+                        self.state = ReadState::Reading(future);
+                        return Ok(Async::NotReady);
+                    }
+                    Ok(Async::Ready((reader, chunk))) => {
+                        self.remaining -= chunk.len() as u64;
+                        self.state = ReadState::Idle(reader, None);
+                        return Ok(Async::Ready(Some(chunk)));
+                    }
+                    Err(err) => return Err(from_blocking(err)),
+                },
+                ReadState::Failed => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+/// Read a single chunk of at most `len` bytes, optionally seeking first.
+fn read_chunk(mut reader: Box<dyn ReadSeek>, seek: Option<u64>, len: usize)
+-> io::Result<(Box<dyn ReadSeek>, Bytes)> {
+    if let Some(position) = seek {
+        reader.seek(SeekFrom::Start(position))?;
+    }
+
+    let mut buf = vec![0; len];
+    let len = reader.read(&mut buf)?;
+
+    if len == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+            "contents ended before their declared size"));
+    }
+
+    buf.truncate(len);
+
+    Ok((reader, buf.into()))
+}
+
+fn from_blocking(err: BlockingError<io::Error>) -> Error {
+    match err {
+        BlockingError::Error(err) => err.into(),
+        BlockingError::Canceled =>
+            BlockingError::<io::Error>::Canceled.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use super::*;
+
+    fn respond(range: Option<&str>) -> HttpResponse {
+        let mut req = TestRequest::default();
+
+        if let Some(range) = range {
+            req = req.header(RANGE, range);
+        }
+
+        respond_remote(
+            &req.to_http_request(),
+            Box::new(io::Cursor::new(b"0123456789".to_vec())),
+            10,
+            mime::TEXT_PLAIN,
+            default_disposition(&mime::TEXT_PLAIN, "file".into()),
+        ).unwrap()
+    }
+
+    #[test]
+    fn no_range_requests_entire_contents() {
+        assert!(requested_range(None, 10).unwrap().is_none());
+    }
+
+    #[test]
+    fn single_range() {
+        let range = requested_range(Some("bytes=2-4"), 10).unwrap().unwrap();
+        assert_eq!((range.start, range.length), (2, 3));
+
+        let range = requested_range(Some("bytes=-3"), 10).unwrap().unwrap();
+        assert_eq!((range.start, range.length), (7, 3));
+
+        let range = requested_range(Some("bytes=5-20"), 10).unwrap().unwrap();
+        assert_eq!((range.start, range.length), (5, 5));
+    }
+
+    #[test]
+    fn multiple_ranges_request_entire_contents() {
+        assert!(requested_range(Some("bytes=0-1,4-5"), 10).unwrap().is_none());
+    }
+
+    #[test]
+    fn unsatisfiable_range() {
+        assert!(requested_range(Some("bytes=10-"), 10).is_err());
+        assert!(requested_range(Some("bytes=4-2"), 10).is_err());
+        assert!(requested_range(Some("lines=1-2"), 10).is_err());
+    }
+
+    #[test]
+    fn remote_contents_without_range() {
+        let rsp = respond(None);
+
+        assert_eq!(rsp.status(), StatusCode::OK);
+        assert_eq!(rsp.headers()[ACCEPT_RANGES], "bytes");
+        assert!(rsp.headers().get(CONTENT_RANGE).is_none());
+    }
+
+    #[test]
+    fn remote_contents_with_range() {
+        let rsp = respond(Some("bytes=2-4"));
+
+        assert_eq!(rsp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(rsp.headers()[CONTENT_RANGE], "bytes 2-4/10");
+    }
 
-        let etag = format!(r#""{}""#, base64::encode(&self.hash));
-        rsp.headers_mut().insert(ETAG, etag.try_into().unwrap());
+    #[test]
+    fn remote_contents_with_unsatisfiable_range() {
+        let rsp = respond(Some("bytes=10-"));
 
-        Ok(rsp)
+        assert_eq!(rsp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(rsp.headers()[CONTENT_RANGE], "bytes */10");
     }
 }
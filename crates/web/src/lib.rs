@@ -11,6 +11,6 @@ pub use self::{
     extractors::{Database, FormOrJson, Locale, Secret, TeamScoped},
     file_ext::FileExt,
     guards::ContentType,
-    responders::{Created, WithStatus},
+    responders::{Accepted, Created, WithStatus},
     session::{Session, SessionManager},
 };
@@ -9,11 +9,12 @@ use actix_web::{
     dev::Payload,
 };
 use adaptarr_macros::From;
+use adaptarr_models::storage;
 use bytes::Bytes;
 use failure::Fail;
 use futures::{Future, Stream, future::self};
 use std::{io::Write, str::FromStr};
-use tempfile::{Builder as TempBuilder, NamedTempFile};
+use tempfile::NamedTempFile;
 
 pub use adaptarr_macros::FromMultipart;
 
@@ -184,9 +185,7 @@ impl FromField for NamedTempFile {
     where
         S: Stream<Item = Bytes, Error = MultipartError> + 'static,
     {
-        let storage_path = &adaptarr_models::Config::global().storage.path;
-
-        Box::new(future::result(TempBuilder::new().tempfile_in(storage_path))
+        Box::new(future::result(storage::temporary())
             .map_err(|e| MultipartError::Internal(Box::new(e)))
             .and_then(|file| field.fold(file, |mut file, chunk| {
                 match file.write_all(chunk.as_ref()) {
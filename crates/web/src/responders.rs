@@ -38,6 +38,38 @@ where
     }
 }
 
+/// Build a 202 Accepted response.
+///
+/// The `Location` header is defined by the first field, and should point to
+/// a resource describing progress of the request. Remaining properties of
+/// the response (including its body) are defined by [`Responder`] in
+/// the second field.
+pub struct Accepted<L, T>(pub L, pub T);
+
+impl<L, T> Responder for Accepted<L, T>
+where
+    T: Responder + 'static,
+    L: 'static,
+    HeaderValue: HttpTryFrom<L>,
+    actix_web::Error: From<<HeaderValue as HttpTryFrom<L>>::Error>,
+{
+    type Future = Box<dyn Future<Item = HttpResponse, Error = Self::Error>>;
+    type Error = actix_web::Error;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let Accepted(location, responder) = self;
+
+        Box::new(responder.respond_to(req)
+            .into_future()
+            .map_err(Into::into)
+            .and_then(move |mut rsp| {
+                *rsp.status_mut() = StatusCode::ACCEPTED;
+                rsp.headers_mut().insert(LOCATION, HeaderValue::try_from(location)?);
+                Ok(rsp)
+            }))
+    }
+}
+
 /// Change status code of a response.
 pub struct WithStatus<T>(pub StatusCode, pub T);
 
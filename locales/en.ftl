@@ -398,6 +398,116 @@ mail-notify-event-new-message-text =
 mail-notify-event-new-message =
     { -mail-url(url: $authorurl, text: $authorname) } sends new message:
 
+# Header displayed before notifications about imports.
+mail-notify-group-header-import =
+    Information on your imports:
+
+# Notification about an import job finishing successfully.
+#
+# Variables:
+# - $title (string): title of the imported module or book
+# - $url (string): URL to the imported module or book
+mail-notify-event-import-finished-text =
+    Import of “{ $title }” ({ $url }) has finished successfully.
+mail-notify-event-import-finished =
+    Import of { -mail-url(url: $url, text: $title) } has finished successfully.
+
+# Notification about an import job failing.
+#
+# Variables:
+# - $title (string): title of the module or book which was being imported
+mail-notify-event-import-failed-text =
+    Import of “{ $title }” has failed.
+mail-notify-event-import-failed =
+    Import of “{ $title }” has failed.
+
+# Header displayed before notifications about proposed changes.
+mail-notify-group-header-changes =
+    Information on proposed changes:
+
+# Notification about changes being proposed to a draft.
+#
+# Variables:
+# - $authorname (string): name of the user who proposed changes
+# - $authorurl (string): URL to profile of the user who proposed changes
+# - $drafttitle (string): title of the draft to which changes were proposed
+# - $drafturl (string): URL to the draft $drafttitle
+mail-notify-event-changes-proposed-text =
+    { $authorname } ({ $authorurl }) proposed changes to “{ $drafttitle }” ({
+    $drafturl }).
+mail-notify-event-changes-proposed =
+    { -mail-url(url: $authorurl, text: $authorname) } proposed changes to {
+    -mail-url(url: $drafturl, text: $drafttitle) }.
+
+# Notification about changes proposed by the user being accepted.
+#
+# Variables:
+# - $drafttitle (string): title of the draft to which changes were proposed
+# - $drafturl (string): URL to the draft $drafttitle
+mail-notify-event-changes-accepted-text =
+    Your changes to “{ $drafttitle }” ({ $drafturl }) were accepted.
+mail-notify-event-changes-accepted =
+    Your changes to { -mail-url(url: $drafturl, text: $drafttitle) } were accepted.
+
+# Notification about changes proposed by the user being rejected.
+#
+# Variables:
+# - $drafttitle (string): title of the draft to which changes were proposed
+# - $drafturl (string): URL to the draft $drafttitle
+mail-notify-event-changes-rejected-text =
+    Your changes to “{ $drafttitle }” ({ $drafturl }) were rejected.
+mail-notify-event-changes-rejected =
+    Your changes to { -mail-url(url: $drafturl, text: $drafttitle) } were rejected.
+
+# Header displayed before notifications about comments.
+mail-notify-group-header-comments =
+    Information on comments:
+
+# A new comment was made on a module or a draft.
+#
+# Variables:
+# - $authorname (string): name of the user who made this comment
+# - $authorurl (string): URL to $authorname's profile
+# - $title (string): title of the module or draft commented on
+# - $url (string): URL to the module or draft $title
+mail-notify-event-new-comment-text =
+    { $authorname } ({ $authorurl }) commented on “{ $title }” ({ $url }):
+mail-notify-event-new-comment =
+    { -mail-url(url: $authorurl, text: $authorname) } commented on {
+    -mail-url(url: $url, text: $title) }:
+
+# Header displayed before notifications about deadlines.
+mail-notify-group-header-deadlines =
+    Information on deadlines:
+
+# Notification about a draft's deadline approaching.
+#
+# Variables:
+# - $drafttitle (string): title of the draft
+# - $drafturl (string): URL to the draft $drafttitle
+# - $stepname (string): name of the step the draft is in
+# - $deadline (string): date and time of the deadline
+mail-notify-event-due-soon-text =
+    “{ $drafttitle }” ({ $drafturl }) should leave step “{ $stepname }” by {
+    $deadline }.
+mail-notify-event-due-soon =
+    { -mail-url(url: $drafturl, text: $drafttitle) } should leave step
+    “{ $stepname }” by { $deadline }.
+
+# Notification about a draft's deadline having passed.
+#
+# Variables:
+# - $drafttitle (string): title of the draft
+# - $drafturl (string): URL to the draft $drafttitle
+# - $stepname (string): name of the step the draft is in
+# - $deadline (string): date and time of the deadline
+mail-notify-event-overdue-text =
+    “{ $drafttitle }” ({ $drafturl }) should have left step “{ $stepname }” by {
+    $deadline }.
+mail-notify-event-overdue =
+    { -mail-url(url: $drafturl, text: $drafttitle) } should have left step
+    “{ $stepname }” by { $deadline }.
+
 -mail-notify-unknown-text =
     You can see { $count ->
         [1] it
@@ -400,6 +400,116 @@ mail-notify-event-new-message-text =
 mail-notify-event-new-message =
     { -mail-url(url: $authorurl, text: $authorname) } wysłał/a nową wiadomość:
 
+# Header displayed before notifications about imports.
+mail-notify-group-header-import =
+    Informacja o importach:
+
+# Notification about an import job finishing successfully.
+#
+# Variables:
+# - $title (string): title of the imported module or book
+# - $url (string): URL to the imported module or book
+mail-notify-event-import-finished-text =
+    Import „{ $title }” ({ $url }) zakończył się powodzeniem.
+mail-notify-event-import-finished =
+    Import { -mail-url(url: $url, text: $title) } zakończył się powodzeniem.
+
+# Notification about an import job failing.
+#
+# Variables:
+# - $title (string): title of the module or book which was being imported
+mail-notify-event-import-failed-text =
+    Import „{ $title }” zakończył się niepowodzeniem.
+mail-notify-event-import-failed =
+    Import „{ $title }” zakończył się niepowodzeniem.
+
+# Header displayed before notifications about proposed changes.
+mail-notify-group-header-changes =
+    Informacja o proponowanych zmianach:
+
+# Notification about changes being proposed to a draft.
+#
+# Variables:
+# - $authorname (string): name of the user who proposed changes
+# - $authorurl (string): URL to profile of the user who proposed changes
+# - $drafttitle (string): title of the draft to which changes were proposed
+# - $drafturl (string): URL to the draft $drafttitle
+mail-notify-event-changes-proposed-text =
+    { $authorname } ({ $authorurl }) zaproponował/a zmiany w „{ $drafttitle }”
+    ({ $drafturl }).
+mail-notify-event-changes-proposed =
+    { -mail-url(url: $authorurl, text: $authorname) } zaproponował/a zmiany w {
+    -mail-url(url: $drafturl, text: $drafttitle) }.
+
+# Notification about changes proposed by the user being accepted.
+#
+# Variables:
+# - $drafttitle (string): title of the draft to which changes were proposed
+# - $drafturl (string): URL to the draft $drafttitle
+mail-notify-event-changes-accepted-text =
+    Twoje zmiany w „{ $drafttitle }” ({ $drafturl }) zostały zaakceptowane.
+mail-notify-event-changes-accepted =
+    Twoje zmiany w { -mail-url(url: $drafturl, text: $drafttitle) } zostały zaakceptowane.
+
+# Notification about changes proposed by the user being rejected.
+#
+# Variables:
+# - $drafttitle (string): title of the draft to which changes were proposed
+# - $drafturl (string): URL to the draft $drafttitle
+mail-notify-event-changes-rejected-text =
+    Twoje zmiany w „{ $drafttitle }” ({ $drafturl }) zostały odrzucone.
+mail-notify-event-changes-rejected =
+    Twoje zmiany w { -mail-url(url: $drafturl, text: $drafttitle) } zostały odrzucone.
+
+# Header displayed before notifications about comments.
+mail-notify-group-header-comments =
+    Informacja o komentarzach:
+
+# A new comment was made on a module or a draft.
+#
+# Variables:
+# - $authorname (string): name of the user who made this comment
+# - $authorurl (string): URL to $authorname's profile
+# - $title (string): title of the module or draft commented on
+# - $url (string): URL to the module or draft $title
+mail-notify-event-new-comment-text =
+    { $authorname } ({ $authorurl }) skomentował/a „{ $title }” ({ $url }):
+mail-notify-event-new-comment =
+    { -mail-url(url: $authorurl, text: $authorname) } skomentował/a {
+    -mail-url(url: $url, text: $title) }:
+
+# Header displayed before notifications about deadlines.
+mail-notify-group-header-deadlines =
+    Informacja o terminach:
+
+# Notification about a draft's deadline approaching.
+#
+# Variables:
+# - $drafttitle (string): title of the draft
+# - $drafturl (string): URL to the draft $drafttitle
+# - $stepname (string): name of the step the draft is in
+# - $deadline (string): date and time of the deadline
+mail-notify-event-due-soon-text =
+    „{ $drafttitle }” ({ $drafturl }) powinien opuścić etap „{ $stepname }” do {
+    $deadline }.
+mail-notify-event-due-soon =
+    { -mail-url(url: $drafturl, text: $drafttitle) } powinien opuścić etap
+    „{ $stepname }” do { $deadline }.
+
+# Notification about a draft's deadline having passed.
+#
+# Variables:
+# - $drafttitle (string): title of the draft
+# - $drafturl (string): URL to the draft $drafttitle
+# - $stepname (string): name of the step the draft is in
+# - $deadline (string): date and time of the deadline
+mail-notify-event-overdue-text =
+    „{ $drafttitle }” ({ $drafturl }) powinien był opuścić etap „{ $stepname }”
+    do { $deadline }.
+mail-notify-event-overdue =
+    { -mail-url(url: $drafturl, text: $drafttitle) } powinien był opuścić etap
+    „{ $stepname }” do { $deadline }.
+
 -mail-notify-unknown-text =
     Możesz zapoznać się z { $count ->
         [1] nim
@@ -0,0 +1,4 @@
+drop table import_job_modules;
+drop table import_jobs;
+drop type import_state;
+drop type import_kind;
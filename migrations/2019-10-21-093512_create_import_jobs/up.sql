@@ -0,0 +1,29 @@
+create type import_kind as enum ('module', 'book');
+
+create type import_state as enum ('queued', 'running', 'done', 'failed');
+
+create table import_jobs (
+    id          serial                      primary key,
+    kind        import_kind                 not null,
+    state       import_state                not null default 'queued',
+    team        integer                     not null references teams(id),
+    "user"      integer                     references users(id),
+    title       varchar                     not null,
+    module      uuid                        references modules(id) on delete set null,
+    book        uuid                        references books(id) on delete set null,
+    error       varchar,
+    message     varchar,
+    created     timestamp with time zone    not null default now(),
+    finished    timestamp with time zone,
+    owner       uuid                        not null,
+    heartbeat   timestamp with time zone    not null default now()
+);
+
+create table import_job_modules (
+    job         integer                     not null references import_jobs(id) on delete cascade,
+    index       integer                     not null,
+    title       varchar                     not null,
+    done        boolean                     not null default false,
+
+    primary key (job, index)
+);
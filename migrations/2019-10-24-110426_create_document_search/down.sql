@@ -0,0 +1,5 @@
+drop index books_title_search_idx;
+drop index documents_title_search_idx;
+drop table document_search;
+drop function search_query(text);
+drop function search_vector(text);
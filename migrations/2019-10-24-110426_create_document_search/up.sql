@@ -0,0 +1,38 @@
+-- Text search functions used for building and querying search indices. We use
+-- the simple configuration, as documents can be written in any language.
+
+create function search_vector(content text)
+returns tsvector
+as $$
+    select to_tsvector('simple', content)
+$$ language sql immutable;
+
+create function search_query(query text)
+returns tsquery
+as $$
+    select plainto_tsquery('simple', query)
+$$ language sql immutable;
+
+-- Plain text content of documents.
+
+create table document_search (
+    document integer primary key references documents(id) on delete cascade,
+    content text not null
+);
+
+create index document_search_content_idx
+    on document_search
+    using gin (search_vector(content));
+
+create index documents_title_search_idx
+    on documents
+    using gin (search_vector(title));
+
+create index books_title_search_idx
+    on books
+    using gin (search_vector(title));
+
+-- Force all existing documents to be processed again, so that they are added
+-- to the search index.
+
+update documents set xrefs_ready = false;
@@ -0,0 +1,5 @@
+drop table file_derivatives;
+
+alter table files
+    drop column width,
+    drop column height;
@@ -0,0 +1,10 @@
+alter table files
+    add column width    integer,
+    add column height   integer;
+
+create table file_derivatives (
+    source      bytea       not null,
+    width       integer     not null,
+    file        integer     not null references files(id) on delete cascade,
+    primary key (source, width)
+);
@@ -0,0 +1,5 @@
+delete from documents
+where id in (select document from draft_revisions);
+
+drop table draft_revisions;
+drop type revision_kind;
@@ -0,0 +1,11 @@
+create type revision_kind as enum ('advance', 'save', 'restore');
+
+create table draft_revisions (
+    id          serial                      primary key,
+    draft       uuid                        not null references drafts(module) on delete cascade,
+    document    integer                     not null references documents(id) on delete cascade,
+    kind        revision_kind               not null,
+    step        integer                     not null references edit_process_steps(id),
+    "user"      integer                     references users(id) on delete set null,
+    created     timestamp with time zone    not null default now()
+);
@@ -0,0 +1,2 @@
+drop table draft_changes;
+drop type change_state;
@@ -0,0 +1,13 @@
+create type change_state as enum ('pending', 'accepted', 'rejected');
+
+create table draft_changes (
+    id          serial                      primary key,
+    draft       uuid                        not null references drafts(module) on delete cascade,
+    "user"      integer                     references users(id) on delete set null,
+    base        integer                     not null references files(id),
+    file        integer                     not null references files(id),
+    state       change_state                not null default 'pending',
+    created     timestamp with time zone    not null default now(),
+    resolved    timestamp with time zone,
+    resolved_by integer                     references users(id) on delete set null
+);
@@ -0,0 +1,13 @@
+create table comments (
+    id          serial                      primary key,
+    module      uuid                        references modules(id) on delete cascade,
+    draft       uuid                        references drafts(module) on delete cascade,
+    parent      integer                     references comments(id) on delete cascade,
+    element     varchar,
+    author      integer                     references users(id) on delete set null,
+    body        bytea                       not null,
+    created     timestamp with time zone    not null default now(),
+    resolved    timestamp with time zone,
+    resolved_by integer                     references users(id) on delete set null,
+    check ((module is null) <> (draft is null))
+);
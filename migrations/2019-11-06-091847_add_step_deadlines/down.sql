@@ -0,0 +1,6 @@
+alter table drafts
+    drop column deadline,
+    drop column deadline_notified;
+
+alter table edit_process_steps
+    drop column duration;
@@ -0,0 +1,6 @@
+alter table edit_process_steps
+    add column duration integer check (duration > 0);
+
+alter table drafts
+    add column deadline             timestamp with time zone,
+    add column deadline_notified    timestamp with time zone;
@@ -0,0 +1,3 @@
+alter table edit_process_links drop column guards;
+
+drop type link_guard;
@@ -0,0 +1,9 @@
+create type link_guard as enum (
+    'no_unresolved_comments',
+    'no_pending_changes',
+    'valid_cnxml',
+    'resolved_links'
+);
+
+alter table edit_process_links
+add column guards link_guard[] not null default '{}';
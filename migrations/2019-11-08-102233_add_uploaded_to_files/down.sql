@@ -0,0 +1,2 @@
+alter table files
+    drop column uploaded;
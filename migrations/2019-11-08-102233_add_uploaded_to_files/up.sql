@@ -0,0 +1,2 @@
+alter table files
+    add column uploaded timestamp with time zone not null default now();
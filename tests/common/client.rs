@@ -50,6 +50,7 @@ lazy_static! {
         },
         storage: config::Storage {
             path: TEMP_DIR.path().to_path_buf(),
+            s3: None,
         },
         logging: config::Logging {
             level: log::LevelFilter::Debug,